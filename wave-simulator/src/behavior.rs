@@ -1,11 +1,12 @@
-use crate::app::Application;
-use winit::event::{ElementState, VirtualKeyCode};
+use winit::event::{ElementState, MouseButton, VirtualKeyCode};
 
-pub trait Behavior<T: Application> {
+pub trait Behavior<T> {
     fn init(&self, state: &mut T);
     fn update(&self, state: &mut T) -> Option<Box<dyn Behavior<T>>>;
     fn draw(&self, state: &mut T);
     fn on_resize(&self, state: &mut T, size: (u32, u32));
     fn on_death(&self, state: &mut T);
     fn on_keyboard_update(&self, state: &mut T, key: VirtualKeyCode, el_state: ElementState);
+    fn on_character(&self, state: &mut T, c: char);
+    fn on_mouse_input(&self, state: &mut T, button: MouseButton, el_state: ElementState);
 }
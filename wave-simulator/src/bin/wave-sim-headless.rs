@@ -0,0 +1,16 @@
+use wave_simulator::wave::cli::Options;
+
+// the same run as `wave-simulator --headless`, with the scene and tick count given in order
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let result = Options::parse_headless(&args).and_then(|options| {
+        for path in options.run_headless(options.headless.unwrap_or(0))? {
+            println!("Wrote {}", path.display());
+        }
+        Ok(())
+    });
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,3 @@
+pub mod app;
+pub mod behavior;
+pub mod wave;
@@ -1,13 +1,54 @@
-use crate::app::Application;
-use crate::wave::WaveApp;
+#[cfg(target_os = "macos")]
+use wave_simulator::app::Application;
+use wave_simulator::wave::cli::Options;
+#[cfg(target_os = "macos")]
+use wave_simulator::wave::WaveApp;
+#[cfg(target_os = "macos")]
 use winit::event_loop::EventLoop;
 
-mod app;
-mod behavior;
-mod wave;
-
 fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let result = Options::parse(&args).and_then(|options| match options.headless {
+        Some(ticks) => headless(&options, ticks),
+        None => windowed(options),
+    });
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+fn headless(options: &Options, ticks: u64) -> Result<(), String> {
+    for path in options.run_headless(ticks)? {
+        println!("Wrote {}", path.display());
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn windowed(options: Options) -> Result<(), String> {
+    // everything is read before the window opens, so mistakes show up straight away
+    let config = options.get_config()?;
+    let scene = options.get_scene()?;
+    let replay = options.get_replay()?;
+
     let event_loop = EventLoop::new();
-    let wave_app = WaveApp::new();
+    let mut wave_app = WaveApp::new();
+    wave_app.config = config;
+    wave_app.scene = Some(scene);
+    wave_app.scene_path = options.scene.clone();
+    wave_app.replay = replay;
+    wave_app.paused = options.paused;
+    wave_app.out = options.get_out();
     wave_app.execute(event_loop);
+    Ok(())
+}
+
+#[cfg(not(target_os = "macos"))]
+fn windowed(_options: Options) -> Result<(), String> {
+    Err(
+        "The windowed wave simulator needs Metal, which is only available on macOS; \
+         use --headless <ticks> to run without a window."
+            .to_string(),
+    )
 }
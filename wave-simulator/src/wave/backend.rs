@@ -0,0 +1,259 @@
+//! What the app needs from a graphics backend besides drawing: the water's buffers, and frames
+//! that draw it, update it and go on screen. `bundles::frame::MetalBackend` keeps the water on
+//! the GPU; `SoftwareBackend` keeps it in the CPU solvers and draws with a `SoftwareRenderer`,
+//! so the app's behaviors run on machines without Metal.
+
+use crate::wave::bathymetry::Bathymetry;
+use crate::wave::grid::Grid;
+use crate::wave::obstacles::ObstacleMask;
+use crate::wave::physics::{ForcedTile, WaveEquation};
+use crate::wave::render::software::SoftwareRenderer;
+use crate::wave::render::{Image, Renderer, Shading, Surface};
+use crate::wave::sim::{Ripple, Simulation, Solver, SolverMode, SolverSettings, WaveRegistry};
+use cgmath::{Matrix4, Vector3};
+
+/// A frame being drawn, which the water can be updated in before it's shown.
+pub trait Frame: Renderer {
+    /// Updates the water once, after what's drawn; `forced` is what the wave equation's drivers
+    /// push this update.
+    fn step(&mut self, forced: &[ForcedTile]);
+    /// Shows the frame; `wait` holds on until the update is done, so what it wrote can be read
+    /// back.
+    fn present(self, wait: bool);
+}
+
+/// The water as a backend keeps it: the automaton's layers and the wave equation's fields, both
+/// laid out like `Simulation` and `WaveEquation` lay theirs out, and the frames drawing it.
+pub trait Backend {
+    type Frame<'a>: Frame
+    where
+        Self: 'a;
+
+    /// Starts a frame, with the water updated the way `solver` says and `ripples` going across
+    /// it; None if there's nowhere to draw it.
+    fn begin(
+        &mut self,
+        solver: &SolverSettings,
+        ripples: &[Ripple],
+        wireframe: bool,
+    ) -> Option<Self::Frame<'_>>;
+    /// Makes the water over, empty, at the size of `grid`.
+    fn resize(&mut self, grid: Grid);
+    fn get_slot_count(&self) -> usize;
+    /// Adds empty layers up to `slots`; layers are never taken away.
+    fn grow_slots(&mut self, slots: usize);
+    /// Replaces the wave slots, adding layers for any new ones.
+    fn set_waves(&mut self, waves: &WaveRegistry);
+    /// Replaces the obstacles, and removes the water already on blocked tiles.
+    fn set_obstacles(&mut self, mask: &ObstacleMask);
+    fn set_bathymetry(&mut self, bathymetry: &Bathymetry);
+    fn get_tile(&self, slot: usize, x: u32, y: u32) -> Option<u16>;
+    fn get_travel(&self, slot: usize, x: u32, y: u32) -> Option<u32>;
+    /// Sets a tile of a slot's layer and starts its travel over.
+    fn set_tile(&mut self, slot: usize, x: u32, y: u32, value: u16);
+    /// Empties a tile in every layer and flattens the field on it, like `Solver::clear` does.
+    fn clear_tile(&mut self, x: u32, y: u32);
+    fn get_field_height(&self, x: u32, y: u32) -> Option<f32>;
+    /// The automaton's tiles and travel, every slot's layer one after the other.
+    fn read_layers(&self) -> (Vec<u16>, Vec<u32>);
+    /// Replaces the automaton's tiles and travel; slots past the end of `tiles` are cleared.
+    fn write_layers(&mut self, tiles: &[u16], travel: &[u32]);
+    /// The wave equation's current and previous fields.
+    fn read_fields(&self) -> (Vec<f32>, Vec<f32>);
+    fn write_fields(&mut self, heights: &[f32], previous: &[f32]);
+}
+
+/// The water in the CPU solvers, drawn into `renderer.frame`. Wireframes are drawn filled.
+pub struct SoftwareBackend {
+    pub renderer: SoftwareRenderer,
+    grid: Grid,
+    waves: WaveRegistry,
+    automaton: Simulation,
+    equation: WaveEquation,
+}
+
+impl SoftwareBackend {
+    /// Water the size of `grid`, drawn into a `width` by `height` frame.
+    pub fn new(grid: Grid, (width, height): (u32, u32)) -> SoftwareBackend {
+        SoftwareBackend {
+            renderer: SoftwareRenderer::new(width, height),
+            grid,
+            waves: WaveRegistry::new(),
+            automaton: Simulation::new(grid.width, grid.height, 0),
+            equation: WaveEquation::new(grid.width, grid.height, SolverSettings::default()),
+        }
+    }
+
+    fn index(&self, x: u32, y: u32) -> Option<usize> {
+        if x < self.grid.width && y < self.grid.height {
+            Some((y * self.grid.width + x) as usize)
+        } else {
+            None
+        }
+    }
+
+    fn layer_index(&self, slot: usize, x: u32, y: u32) -> Option<usize> {
+        match slot < self.automaton.slots {
+            true => Some(slot * self.grid.get_len() + self.index(x, y)?),
+            false => None,
+        }
+    }
+}
+
+pub struct SoftwareFrame<'a> {
+    backend: &'a mut SoftwareBackend,
+    mode: SolverMode,
+}
+
+impl<'a> Renderer for SoftwareFrame<'a> {
+    fn upload_surface(&mut self, surface: &Surface) {
+        self.backend.renderer.upload_surface(surface);
+    }
+
+    fn clear(&mut self, colour: [f32; 4]) {
+        self.backend.renderer.clear(colour);
+    }
+
+    fn draw_water(&mut self, projection: Matrix4<f32>, view: Matrix4<f32>, shading: &Shading) {
+        self.backend.renderer.draw_water(projection, view, shading);
+    }
+
+    fn draw_marker(
+        &mut self,
+        projection: Matrix4<f32>,
+        view: Matrix4<f32>,
+        position: Vector3<f32>,
+    ) {
+        self.backend
+            .renderer
+            .draw_marker(projection, view, position);
+    }
+
+    fn draw_quad(&mut self, quad: [f32; 4], image: &Image) {
+        self.backend.renderer.draw_quad(quad, image);
+    }
+}
+
+impl<'a> Frame for SoftwareFrame<'a> {
+    fn step(&mut self, forced: &[ForcedTile]) {
+        let backend = &mut *self.backend;
+        match self.mode {
+            SolverMode::Automaton => backend.automaton.step(&backend.waves),
+            SolverMode::WaveEquation => backend.equation.step_forced(forced),
+        }
+    }
+
+    // the frame is already in the renderer, and the update done
+    fn present(self, _wait: bool) {}
+}
+
+impl Backend for SoftwareBackend {
+    type Frame<'a> = SoftwareFrame<'a>;
+
+    // the water drawn is the solver's as it is now, ripples and all
+    fn begin(
+        &mut self,
+        solver: &SolverSettings,
+        ripples: &[Ripple],
+        _wireframe: bool,
+    ) -> Option<SoftwareFrame<'_>> {
+        self.automaton.boundary = solver.boundary;
+        self.automaton.sponge_width = solver.sponge_width;
+        self.automaton.ripples = ripples.to_vec();
+        self.equation.settings = *solver;
+        let sim: &dyn Solver = match solver.mode {
+            SolverMode::Automaton => &self.automaton,
+            SolverMode::WaveEquation => &self.equation,
+        };
+        let surface = Surface::new(sim, &self.waves, self.grid.cell_size);
+        self.renderer.upload_surface(&surface);
+        Some(SoftwareFrame {
+            backend: self,
+            mode: solver.mode,
+        })
+    }
+
+    fn resize(&mut self, grid: Grid) {
+        let frame = &self.renderer.frame;
+        *self = SoftwareBackend::new(grid, (frame.width, frame.height));
+    }
+
+    fn get_slot_count(&self) -> usize {
+        self.automaton.slots
+    }
+
+    fn grow_slots(&mut self, slots: usize) {
+        if slots > self.automaton.slots {
+            self.automaton.set_slot_count(slots);
+        }
+    }
+
+    fn set_waves(&mut self, waves: &WaveRegistry) {
+        self.grow_slots(waves.len());
+        self.waves = waves.clone();
+    }
+
+    fn set_obstacles(&mut self, mask: &ObstacleMask) {
+        self.automaton.set_obstacles(mask.clone());
+        self.equation.set_obstacles(mask.clone());
+    }
+
+    fn set_bathymetry(&mut self, bathymetry: &Bathymetry) {
+        self.automaton.set_bathymetry(bathymetry.clone());
+        self.equation.set_bathymetry(bathymetry.clone());
+    }
+
+    fn get_tile(&self, slot: usize, x: u32, y: u32) -> Option<u16> {
+        self.automaton.get(slot, x, y)
+    }
+
+    fn get_travel(&self, slot: usize, x: u32, y: u32) -> Option<u32> {
+        Some(self.automaton.travel[self.layer_index(slot, x, y)?])
+    }
+
+    fn set_tile(&mut self, slot: usize, x: u32, y: u32, value: u16) {
+        if let Some(index) = self.layer_index(slot, x, y) {
+            self.automaton.tiles[index] = value;
+            self.automaton.travel[index] = 0;
+        }
+    }
+
+    fn clear_tile(&mut self, x: u32, y: u32) {
+        (0..self.automaton.slots).for_each(|slot| self.set_tile(slot, x, y, 0));
+        self.equation.clear(x, y);
+    }
+
+    fn get_field_height(&self, x: u32, y: u32) -> Option<f32> {
+        Some(self.equation.heights[self.index(x, y)?])
+    }
+
+    fn read_layers(&self) -> (Vec<u16>, Vec<u32>) {
+        (self.automaton.tiles.clone(), self.automaton.travel.clone())
+    }
+
+    fn write_layers(&mut self, tiles: &[u16], travel: &[u32]) {
+        let automaton = &mut self.automaton;
+        automaton
+            .tiles
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, tile)| *tile = tiles.get(i).copied().unwrap_or(0));
+        automaton
+            .travel
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, t)| *t = travel.get(i).copied().unwrap_or(0));
+    }
+
+    fn read_fields(&self) -> (Vec<f32>, Vec<f32>) {
+        (
+            self.equation.heights.clone(),
+            self.equation.previous.clone(),
+        )
+    }
+
+    fn write_fields(&mut self, heights: &[f32], previous: &[f32]) {
+        self.equation.heights.copy_from_slice(heights);
+        self.equation.previous.copy_from_slice(previous);
+    }
+}
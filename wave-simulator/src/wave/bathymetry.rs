@@ -0,0 +1,124 @@
+//! How deep the water is, as the speed waves travel at on each tile.
+
+use crate::wave::constants::MIN_DEPTH_SPEED;
+use crate::wave::util::{read_grayscale_png, write_grayscale_png};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// One speed per tile, row by row like the height buffers, from `MIN_DEPTH_SPEED` in the
+/// shallows to 1 in deep water. Waves slow down, and bunch up, as the water gets shallower.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Bathymetry {
+    pub width: u32,
+    pub height: u32,
+    pub speeds: Vec<f32>,
+}
+
+impl Bathymetry {
+    /// Deep water everywhere.
+    pub fn flat(width: u32, height: u32) -> Bathymetry {
+        Bathymetry {
+            width,
+            height,
+            speeds: vec![1.0; width as usize * height as usize],
+        }
+    }
+
+    /// Takes the depth of every tile from `depth`, 0 being the shallowest and 1 the deepest.
+    /// As in shallow water, speed goes with the square root of the depth.
+    pub fn from_fn<F: Fn(u32, u32) -> f32>(width: u32, height: u32, depth: F) -> Bathymetry {
+        let speeds = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| depth(x, y).clamp(0.0, 1.0).sqrt().max(MIN_DEPTH_SPEED))
+            .collect();
+        Bathymetry {
+            width,
+            height,
+            speeds,
+        }
+    }
+
+    /// A round shoal in the middle of the grid, shallowest at its centre.
+    pub fn shoal(width: u32, height: u32) -> Bathymetry {
+        let centre = (width as f32 / 2.0, height as f32 / 2.0);
+        let radius = width.min(height) as f32 / 4.0;
+        Bathymetry::from_fn(width, height, |x, y| {
+            let distance = (x as f32 - centre.0).hypot(y as f32 - centre.1);
+            (distance / radius).min(1.0)
+        })
+    }
+
+    /// Reads the depths from a PNG the size of the grid; black is the shallowest, white the
+    /// deepest.
+    pub fn from_png<R: Read>(reader: R, width: u32, height: u32) -> Result<Bathymetry, String> {
+        let (png_width, png_height, pixels) = read_grayscale_png(reader)?;
+        if (png_width, png_height) != (width, height) {
+            return Err(format!(
+                "The depth map is {}x{}, but the grid is {}x{}.",
+                png_width, png_height, width, height
+            ));
+        }
+        Ok(Bathymetry::from_fn(width, height, |x, y| {
+            pixels[(y * width + x) as usize] as f32 / 255.0
+        }))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P, width: u32, height: u32) -> Result<Bathymetry, String> {
+        let file = File::open(path.as_ref())
+            .map_err(|e| format!("Could not read {}: {}", path.as_ref().display(), e))?;
+        Bathymetry::from_png(file, width, height)
+            .map_err(|e| format!("{}: {}", path.as_ref().display(), e))
+    }
+
+    /// Writes the depths as a PNG that `from_png` reads back; tiles at `MIN_DEPTH_SPEED` come
+    /// out black.
+    pub fn to_png<W: Write>(&self, writer: W) -> Result<(), String> {
+        let pixels = self
+            .speeds
+            .iter()
+            .map(|speed| {
+                if *speed <= MIN_DEPTH_SPEED {
+                    0
+                } else {
+                    (speed * speed * 255.0).round().min(255.0) as u8
+                }
+            })
+            .collect::<Vec<u8>>();
+        write_grayscale_png(writer, self.width, self.height, &pixels)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let file = File::create(path.as_ref())
+            .map_err(|e| format!("Could not write {}: {}", path.as_ref().display(), e))?;
+        self.to_png(file)
+            .map_err(|e| format!("{}: {}", path.as_ref().display(), e))
+    }
+
+    pub fn is_flat(&self) -> bool {
+        self.speeds.iter().all(|speed| *speed == 1.0)
+    }
+
+    // tiles outside of the map are deep
+    pub fn speed_at(&self, x: u32, y: u32) -> f32 {
+        if x < self.width && y < self.height {
+            self.speeds[(y * self.width + x) as usize]
+        } else {
+            1.0
+        }
+    }
+
+    /// How many more updates than usual the automaton takes to move a wave onto this tile; a
+    /// tile at half speed takes 2 updates, so its lag is 1.
+    pub fn lag_at(&self, x: u32, y: u32) -> u8 {
+        ((1.0 / self.speed_at(x, y)).round() as u8).saturating_sub(1)
+    }
+
+    /// `lag_at` for every tile, row by row.
+    pub fn get_lags(&self) -> Vec<u8> {
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .map(|(x, y)| self.lag_at(x, y))
+            .collect()
+    }
+}
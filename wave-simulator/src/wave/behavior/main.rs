@@ -1,39 +1,54 @@
 use crate::behavior::Behavior;
-use crate::wave::bundles::ui::UiBundle;
-use crate::wave::bundles::water::{WaterBundle, Wave};
-use crate::wave::constants::{CAMERA_SPEED, FILL_MODE, FREQ_OF_UPDATES, VERTEX_COUNT};
+use crate::wave::backend::{Backend, Frame};
+use crate::wave::bathymetry::Bathymetry;
+use crate::wave::boundary::Boundary;
+use crate::wave::bundles::matrix::MatrixBundle;
+use crate::wave::camera::to_clip;
+use crate::wave::command::{Command, DepthMap, HELP};
+use crate::wave::console::{CELL_HEIGHT, CELL_WIDTH};
+use crate::wave::export::Exporter;
+use crate::wave::grid::Grid;
+use crate::wave::mesh::Mesh;
+use crate::wave::obstacles::ObstacleMask;
+use crate::wave::physics::{drive, place_drivers};
 use crate::wave::raycaster::cast_ray;
-use crate::wave::util::generate_transformation;
+use crate::wave::recording::{Event, Recording};
+use crate::wave::render::colormap::{Mapping, Quantity};
+use crate::wave::render::{Image, Renderer, Shading};
+use crate::wave::scene::{Scene, Source};
+use crate::wave::sim::{age_ripples, turn, Ripple, SolverMode, Wave, WaveKind, ACTIVATED};
+use crate::wave::snapshot::Snapshot;
 use crate::wave::WaveApp;
-use cgmath::{Matrix4, Vector3};
-use cull_canyon::{
-    MTLCommandEncoder, MTLRenderPassAttachmentDescriptor, MTLRenderPassColorAttachmentDescriptor,
-    MTLRenderPassDescriptor,
-};
-use std::os::raw::c_void;
-use winit::event::{ElementState, VirtualKeyCode};
+use cgmath::Vector3;
+use std::path::Path;
+use winit::event::{ElementState, MouseButton, VirtualKeyCode};
+
+// lines of the console shown, the input included
+const CONSOLE_ROWS: usize = 12;
 
 pub struct MainBehavior;
-impl Behavior<WaveApp> for MainBehavior {
-    fn init(&self, state: &mut WaveApp) {
-        state.ui_bundle = Some(unsafe { UiBundle::new(state.base_metal_bundle.as_ref().unwrap()) });
-        state.water = Some(unsafe {
-            WaterBundle::generate_water(&state.base_metal_bundle.as_ref().unwrap())
-        });
+impl<B: Backend> Behavior<WaveApp<B>> for MainBehavior {
+    fn init(&self, state: &mut WaveApp<B>) {
+        let (width, height) = state.window_size;
+        let projection = state.config.projection_matrix(width as f32 / height as f32);
+        state.matrix_bundle = Some(MatrixBundle::new(projection));
+        // made at the scene's size straight away
+        if let Some(scene) = &state.scene {
+            state.grid = scene.grid;
+        }
+        state.backend.as_mut().unwrap().resize(state.grid);
+        if let Some(scene) = state.scene.take() {
+            load_scene(state, &scene);
+            if let Some(path) = &state.scene_path {
+                println!("Loaded {}.", path.display());
+            }
+        }
     }
 
-    fn update(&self, state: &mut WaveApp) -> Option<Box<dyn Behavior<WaveApp>>> {
-        state
-            .window_bundle
-            .as_ref()
-            .unwrap()
-            .window
-            .request_redraw();
-
-        let window_size = state.window_bundle.as_ref().unwrap().window.inner_size();
-        let pitch =
-            (window_size.height as f64 / 2.0) - (window_size.height as f64 - state.mouse_pos.1);
-        let yaw = -(window_size.width as f64 - state.mouse_pos.0 / 2.0);
+    fn update(&self, state: &mut WaveApp<B>) -> Option<Box<dyn Behavior<WaveApp<B>>>> {
+        let (width, height) = state.window_size;
+        let pitch = (height as f64 / 2.0) - (height as f64 - state.mouse_pos.1);
+        let yaw = -(width as f64 - state.mouse_pos.0 / 2.0);
 
         let pitch = if pitch >= 90.0 { 90.0 } else { pitch };
         let pitch = if pitch <= -90.0 { -90.0 } else { pitch };
@@ -41,31 +56,53 @@ impl Behavior<WaveApp> for MainBehavior {
         let pitch = pitch.to_radians() as f32;
         let yaw = yaw.to_radians() as f32;
 
+        // events are due once the water has had as many updates as when they were recorded
+        if let Some(mut replay) = state.replay.take() {
+            let due = replay
+                .events
+                .iter()
+                .take_while(|event| event.get_tick() <= state.updates)
+                .count();
+            for event in replay.events.drain(..due).collect::<Vec<Event>>() {
+                apply_event(state, &event);
+            }
+            if replay.events.is_empty() {
+                println!("The replay is over.");
+            } else {
+                state.replay = Some(replay);
+            }
+        }
+
+        let speed = state.config.camera_speed;
         let cam = &mut state.matrix_bundle.as_mut().unwrap().camera;
         cam.pitch = pitch;
         cam.yaw = yaw;
+        // keys typed into the console don't move anything
+        if state.console.open {
+            return None;
+        }
 
         if state.keyboard.is_key_down(VirtualKeyCode::W) {
-            cam.z -= yaw.cos() * CAMERA_SPEED;
-            cam.x += yaw.sin() * CAMERA_SPEED;
+            cam.z -= yaw.cos() * speed;
+            cam.x += yaw.sin() * speed;
         };
         if state.keyboard.is_key_down(VirtualKeyCode::S) {
-            cam.z += yaw.cos() * CAMERA_SPEED;
-            cam.x -= yaw.sin() * CAMERA_SPEED;
+            cam.z += yaw.cos() * speed;
+            cam.x -= yaw.sin() * speed;
         };
         if state.keyboard.is_key_down(VirtualKeyCode::D) {
-            cam.z += yaw.sin() * CAMERA_SPEED;
-            cam.x += yaw.cos() * CAMERA_SPEED;
+            cam.z += yaw.sin() * speed;
+            cam.x += yaw.cos() * speed;
         };
         if state.keyboard.is_key_down(VirtualKeyCode::A) {
-            cam.z -= yaw.sin() * CAMERA_SPEED;
-            cam.x -= yaw.cos() * CAMERA_SPEED;
+            cam.z -= yaw.sin() * speed;
+            cam.x -= yaw.cos() * speed;
         };
         if state.keyboard.is_key_down(VirtualKeyCode::Space) {
-            cam.y += CAMERA_SPEED;
+            cam.y += speed;
         };
         if state.keyboard.is_key_down(VirtualKeyCode::LShift) {
-            cam.y -= CAMERA_SPEED;
+            cam.y -= speed;
         };
 
         if state.keyboard.is_key_down(VirtualKeyCode::P) {
@@ -75,419 +112,631 @@ impl Behavior<WaveApp> for MainBehavior {
             state.paused = false;
         }
 
-        unsafe { state.matrix_bundle.as_ref().unwrap().edit_view() };
-
         None
     }
 
-    fn draw(&self, state: &mut WaveApp) {
-        let bundle = state.base_metal_bundle.as_ref().unwrap();
-        let ui = state.ui_bundle.as_ref().unwrap();
-        let water = state.water.as_ref().unwrap();
-        let debug = state.debug_bundle.as_ref().unwrap();
+    fn draw(&self, state: &mut WaveApp<B>) {
+        let stepping = !state.paused
+            && state.time != 0
+            && state.time.is_multiple_of(state.config.frames_per_update);
+        let forced = if stepping && state.solver.mode == SolverMode::WaveEquation {
+            drive(&mut state.drivers, &state.waves)
+        } else {
+            vec![]
+        };
+        if stepping && state.solver.mode == SolverMode::Automaton {
+            let grid = state.grid;
+            let lags = state.bathymetry.get_lags();
+            age_ripples(
+                &mut state.ripples,
+                &state.waves,
+                state.solver.boundary,
+                grid.width,
+                grid.height,
+                &lags,
+            );
+        }
+        let exporting = stepping
+            && state
+                .export
+                .as_ref()
+                .is_some_and(|(every, _)| (state.updates + 1).is_multiple_of(*every));
+
+        let (width, height) = state.window_size;
+        // along the bottom of the window, two pixels a pixel
+        let console = match state.console.open {
+            true => {
+                let columns = (width as usize / 2 / CELL_WIDTH).max(1);
+                let console = Image {
+                    width: (columns * CELL_WIDTH) as u32,
+                    height: (CONSOLE_ROWS * CELL_HEIGHT) as u32,
+                    pixels: state.console.render(columns, CONSOLE_ROWS),
+                };
+                let quad_size = (
+                    console.width as f32 * 2.0 / width as f32,
+                    console.height as f32 * 2.0 / height as f32,
+                );
+                let quad = [
+                    quad_size.0 - 1.0,
+                    quad_size.1 - 1.0,
+                    quad_size.0,
+                    quad_size.1,
+                ];
+                Some((quad, console))
+            }
+            false => None,
+        };
+
         let matrices = state.matrix_bundle.as_ref().unwrap();
+        let projection = matrices.projection;
+        let view = matrices.camera.get_matrix();
+        let point = cast_ray(projection, &matrices.camera, state);
+        if let Some(point) = point {
+            state.current_ray_pos = point;
+        }
 
-        unsafe {
-            if let Some(drawable) = bundle.surface.next_drawable() {
-                let command_buffer = bundle.queue.new_command_buffer();
-
-                let encoder = command_buffer.new_render_command_encoder_with_descriptor({
-                    let desc = MTLRenderPassDescriptor::new();
-                    {
-                        let desc = desc.get_depth_attachment();
-                        desc.set_texture(bundle.depth_texture.clone());
-                        desc.set_load_action(2);
-                        desc.set_store_action(1);
-                    };
-                    desc.get_color_attachments()
-                        .set_object_at_indexed_subscript(0, {
-                            let desc = MTLRenderPassColorAttachmentDescriptor::new();
-                            desc.set_texture(drawable.get_texture());
-                            desc.set_clear_color(0.0, 0.0, 0.0, 1.0);
-                            desc.set_load_action(2);
-                            desc.set_store_action(1);
-                            desc
-                        });
-                    desc
-                });
-                encoder.set_render_pipeline_state(water.render_pipeline.clone());
-                encoder.set_vertex_buffer(water.water_buffer.clone(), 0, 0);
-                encoder.set_vertex_buffer(matrices.projection.clone(), 0, 1);
-                encoder.set_vertex_buffer(matrices.view.clone(), 0, 2);
-                encoder.set_vertex_bytes(
-                    state.waves.as_ptr() as *const c_void,
-                    state.waves.len() as u64 * std::mem::size_of::<Wave>() as u64,
-                    3,
-                );
-                encoder.set_triangle_fill_mode(FILL_MODE);
-                encoder.set_depth_stencil_state(bundle.basic_depth.clone());
-                encoder.set_vertex_texture(water.texture.clone(), 0);
-                encoder.set_fragment_texture(water.water_surface.clone(), 0);
-                encoder.set_fragment_sampler_state(water.sampler.clone(), 0);
-
-                encoder.draw_indexed_primitives(
-                    3,
-                    water.indices_count as u64,
-                    1,
-                    water.water_indices.clone(),
-                    0,
-                    1,
-                    0,
-                    0,
-                );
+        let backend = state.backend.as_mut().unwrap();
+        let mut frame = match backend.begin(&state.solver, &state.ripples, state.wireframe) {
+            Some(frame) => frame,
+            None => return,
+        };
+        frame.clear([0.0, 0.0, 0.0, 1.0]);
+        let shading = match state.mapping {
+            Some(mapping) => Shading::Mapped(mapping),
+            None => Shading::Water(state.config.light()),
+        };
+        frame.draw_water(projection, view, &shading);
+        if let Some(point) = point {
+            frame.draw_marker(projection, view, point + Vector3::unit_y());
+        }
 
-                let point = cast_ray(
-                    matrices.proj_contents,
-                    &matrices.camera,
-                    water.texture.clone(),
-                    state,
-                );
-                if let Some(point) = point {
-                    state.current_ray_pos = point;
-                    encoder.set_render_pipeline_state(debug.pipeline.clone());
-                    encoder.set_vertex_buffer(debug.vertices.clone(), 0, 0);
-                    encoder.set_vertex_buffer(matrices.projection.clone(), 0, 1);
-                    encoder.set_vertex_buffer(matrices.view.clone(), 0, 2);
-                    let transformation = generate_transformation(
-                        Vector3 {
-                            x: point.x,
-                            y: point.y + 1.0,
-                            z: point.z,
-                        },
-                        (0.0, 0.0, 0.0),
-                        (1.0, 1.0, 1.0),
-                    );
-                    encoder.set_vertex_bytes(
-                        &transformation as *const Matrix4<f32> as *const c_void,
-                        64,
-                        3,
-                    );
-                    encoder.set_depth_stencil_state(bundle.basic_depth.clone());
-                    encoder.draw_indexed_primitives(
-                        3,
-                        debug.indices_count,
-                        0,
-                        debug.indices.clone(),
-                        0,
-                        1,
-                        0,
-                        0,
-                    );
-                }
+        // on the mouse, which is what the ray goes through
+        let aspect_ratio = width as f32 / height as f32;
+        let (x, y) = to_clip(state.mouse_pos, (width, height));
+        frame.draw_quad([x, y, 0.05, 0.05 * aspect_ratio], &state.crosshair);
+        // in the top right corner, two pixels a pixel like the console
+        if let Some(mapping) = &state.mapping {
+            let legend = mapping.legend();
+            let quad_size = (
+                legend.width as f32 * 2.0 / width as f32,
+                legend.height as f32 * 2.0 / height as f32,
+            );
+            let quad = [
+                1.0 - quad_size.0,
+                1.0 - quad_size.1,
+                quad_size.0,
+                quad_size.1,
+            ];
+            frame.draw_quad(quad, &legend);
+        }
+        if let Some((quad, console)) = &console {
+            frame.draw_quad(*quad, console);
+        }
 
-                encoder.set_render_pipeline_state(ui.pipeline.clone());
-                encoder.set_vertex_buffer(ui.quad.clone(), 0, 0);
-                let aspect_ratio = {
-                    let b = state.window_bundle.as_ref().unwrap().window.inner_size();
-                    b.width as f32 / b.height as f32
-                };
-                encoder.set_vertex_bytes(
-                    [0.0f32, 0.0, 0.05, 0.05 * aspect_ratio].as_ptr() as *const c_void,
-                    16,
-                    1,
-                );
-                encoder.set_fragment_texture(water.crosshair.clone(), 0);
-                encoder.set_fragment_sampler_state(water.sampler.clone(), 0);
-                encoder.draw_primitives(3, 0, 6, 1, 0);
-
-                encoder.end_encoding();
-
-                if !state.paused && state.time != 0 && state.time % FREQ_OF_UPDATES == 0 {
-                    let encoder = command_buffer.new_compute_command_encoder();
-                    encoder.set_compute_pipeline_state(water.compute_pipeline.clone());
-                    encoder.set_bytes(
-                        state.waves.as_ptr() as *const c_void,
-                        state.waves.len() as u64 * std::mem::size_of::<Wave>() as u64,
-                        0,
-                    );
-                    encoder.set_texture(water.texture.clone(), 0);
-                    encoder.set_texture(water.texture.clone(), 1);
-                    encoder.dispatch_threadgroups(
-                        (VERTEX_COUNT as u64 / 10, VERTEX_COUNT as u64 / 10, 1),
-                        (10, 10, 1),
-                    );
-                    encoder.end_encoding();
-                };
+        if stepping {
+            frame.step(&forced);
+        }
+        // the heights are read back right after the update
+        frame.present(exporting);
 
-                command_buffer.present_drawable(drawable);
-                command_buffer.commit();
+        if stepping {
+            state.updates += 1;
+            if exporting {
+                export_tick(state);
             }
-        };
+        }
     }
 
-    fn on_resize(&self, state: &mut WaveApp, size: (u32, u32)) {
-        unsafe {
+    fn on_resize(&self, state: &mut WaveApp<B>, size: (u32, u32)) {
+        state.matrix_bundle.as_mut().unwrap().edit_projection(
             state
-                .matrix_bundle
-                .as_ref()
-                .unwrap()
-                .edit_projection(size.0 as f32 / size.1 as f32)
-        };
+                .config
+                .projection_matrix(size.0 as f32 / size.1 as f32),
+        );
     }
 
-    fn on_death(&self, _state: &mut WaveApp) {
+    fn on_death(&self, _state: &mut WaveApp<B>) {
         //
     }
 
-    fn on_keyboard_update(&self, state: &mut WaveApp, key: VirtualKeyCode, el_state: ElementState) {
-        if el_state == ElementState::Pressed {
+    fn on_keyboard_update(
+        &self,
+        state: &mut WaveApp<B>,
+        key: VirtualKeyCode,
+        el_state: ElementState,
+    ) {
+        if el_state != ElementState::Pressed {
+            return;
+        }
+        if state.console.open {
             match key {
-                VirtualKeyCode::G => {
-                    println!("Let's build a wave!");
-                    println!(
-                        "This wave will NOT be placed on the scene.\
-                        We will JUST be filling the wave slot."
-                    );
-                    println!(
-                        "To abort wave production, just give \"abort\" as an input at any \
-                        time, and we'll abort."
-                    );
-                    let mut s;
-
-                    let mut amplitude: f32 = 0.0;
-                    let mut wavelength: u8 = 0;
-                    let directions: u8;
-
-                    println!("Enter the desired amplitude.");
-                    loop {
-                        s = String::new();
-                        std::io::stdin().read_line(&mut s).unwrap();
-                        s = s.trim().to_string();
-                        if s.to_lowercase().eq(&"abort".to_string()) {
-                            println!("Aborting.");
-                            return;
-                        };
-                        let value = s.parse::<f32>();
-                        let b = match value {
-                            Ok(val) => {
-                                if val < -50.0 || val > 50.0 {
-                                    println!(
-                                        "Amplitude {} is invalid; -50 <= amplitude <= 50.",
-                                        val
-                                    );
-                                    false
-                                } else {
-                                    amplitude = val;
-                                    true
-                                }
-                            }
-                            Err(_) => {
-                                println!("Invalid amplitude value {}.", s);
-                                false
-                            }
-                        };
-                        if b {
-                            break;
-                        };
-                    }
-                    println!("Amplitude = {}", amplitude);
-
-                    println!("Enter the desired wavelength.");
-                    loop {
-                        s = String::new();
-                        std::io::stdin().read_line(&mut s).unwrap();
-                        s = s.trim().to_string();
-                        if s.to_lowercase().eq(&"abort".to_string()) {
-                            println!("Aborting.");
-                            return;
-                        };
-                        let value = s.parse::<u8>();
-                        let b = match value {
-                            Ok(val) => {
-                                wavelength = val;
-                                true
-                            }
-                            Err(_) => {
-                                println!("Invalid wavelength value {}.", s);
-                                false
-                            }
-                        };
-                        if b {
-                            break;
-                        };
-                    }
-                    println!("Wavelength = {}", wavelength);
-
-                    println!("Enter the desired directions of propagation for the wave.");
-                    println!("Valid directions are up, left, right, and down.");
-                    println!("Direction instructions should be formatted like: \"up right\".");
-                    println!(
-                        "Warning: if you input two opposed directions (ie. left and right) \
-                         for the same wave, some very not-wavelike chaos ensues. If \
-                         you want a wave to propagate in all directions, I recommend \
-                         making several waves to achieve that effect."
-                    );
-                    s = String::new();
-                    std::io::stdin().read_line(&mut s).unwrap();
-                    s = s.trim().to_string();
-                    if s.to_lowercase().eq(&"abort".to_string()) {
-                        println!("Aborting.");
-                        return;
-                    };
-                    let mut up = false;
-                    let mut down = false;
-                    let mut right = false;
-                    let mut left = false;
-                    let parts = s.split(" ").collect::<Vec<&str>>();
-                    parts.iter().for_each(|item: &&str| {
-                        match *item {
-                            "up" => up = true,
-                            "left" => left = true,
-                            "down" => down = true,
-                            "right" => right = true,
-                            _ => {}
-                        };
-                    });
-                    println!(
-                        "Your wave will{}go up.",
-                        match up {
-                            true => " ",
-                            false => " not ",
-                        }
-                    );
-                    println!(
-                        "Your wave will{}go left.",
-                        match left {
-                            true => " ",
-                            false => " not ",
-                        }
-                    );
-                    println!(
-                        "Your wave will{}go right.",
-                        match right {
-                            true => " ",
-                            false => " not ",
-                        }
-                    );
-                    println!(
-                        "Your wave will{}go down.",
-                        match down {
-                            true => " ",
-                            false => " not ",
+                VirtualKeyCode::Return => {
+                    if let Some(line) = state.console.submit() {
+                        match Command::parse(&line).and_then(|command| run_command(state, command))
+                        {
+                            Ok(done) => state.console.print(&done),
+                            Err(e) => state.console.error(&e),
                         }
-                    );
-
-                    directions = match up {
-                        true => 1,
-                        false => 0,
-                    } | match down {
-                        true => 2,
-                        false => 0,
-                    } | match left {
-                        true => 4,
-                        false => 0,
-                    } | match right {
-                        true => 8,
-                        false => 0,
-                    };
-
-                    println!("Enter the desired wave slot.");
-                    let mut wave_id = 0;
-                    loop {
-                        s = String::new();
-                        std::io::stdin().read_line(&mut s).unwrap();
-                        s = s.trim().to_string();
-                        if s.to_lowercase().eq(&"abort".to_string()) {
-                            println!("Aborting.");
-                            return;
-                        };
-                        let value = s.parse::<usize>();
-                        let b = match value {
-                            Ok(val) => {
-                                if val > 3 {
-                                    println!("Invalid wave id {}; 0 <= id <= 3", val);
-                                    false
-                                } else {
-                                    wave_id = val;
-                                    true
-                                }
-                            }
-                            Err(_) => {
-                                println!("Invalid wave id {}.", s);
-                                false
-                            }
-                        };
-                        if b {
-                            break;
-                        };
                     }
+                }
+                VirtualKeyCode::Back => state.console.backspace(),
+                VirtualKeyCode::Up => state.console.previous(),
+                VirtualKeyCode::Down => state.console.next(),
+                VirtualKeyCode::Escape => state.console.close(),
+                _ => {}
+            }
+            return;
+        }
+        match key {
+            VirtualKeyCode::Return => state.console.open("", false),
+            // the keys that asked for input on the terminal start their command instead
+            VirtualKeyCode::G => state.console.open("wave ", true),
+            VirtualKeyCode::N => state.console.open("place ", true),
+            VirtualKeyCode::O => state.console.open("obstacles ", true),
+            VirtualKeyCode::H => state.console.open("depth ", true),
+            VirtualKeyCode::K => state.console.open("scene save ", true),
+            VirtualKeyCode::C => state.console.open("snapshot ", true),
+            VirtualKeyCode::J => state.console.open("recording save ", true),
+            VirtualKeyCode::X => state.console.open("export ", true),
+            VirtualKeyCode::V => state.console.open("mesh ", true),
+            VirtualKeyCode::Key0
+            | VirtualKeyCode::Key1
+            | VirtualKeyCode::Key2
+            | VirtualKeyCode::Key3
+            | VirtualKeyCode::Key4
+            | VirtualKeyCode::Key5
+            | VirtualKeyCode::Key6
+            | VirtualKeyCode::Key7
+            | VirtualKeyCode::Key8
+            | VirtualKeyCode::Key9 => {
+                // winit has 1 to 9 first, then 0
+                state.active_slot = (key as usize + 1 - VirtualKeyCode::Key1 as usize) % 10;
+                println!("Left clicks place wave slot {}.", state.active_slot);
+            }
+            VirtualKeyCode::R => state.wireframe = !state.wireframe,
+            VirtualKeyCode::T => {
+                let next = match state.mapping.map(|mapping| mapping.quantity) {
+                    None => Some(Quantity::Height),
+                    Some(Quantity::Height) => Some(Quantity::Activation),
+                    Some(Quantity::Activation) => Some(Quantity::Tick),
+                    Some(Quantity::Tick) => None,
+                };
+                state.mapping =
+                    next.map(|quantity| Mapping::new(quantity, state.active_slot, &state.waves));
+                match next {
+                    Some(quantity) => println!("Showing: {}", quantity.name()),
+                    None => println!("Showing: water"),
+                }
+            }
+            VirtualKeyCode::M => {
+                state.solver.mode = match state.solver.mode {
+                    SolverMode::Automaton => SolverMode::WaveEquation,
+                    SolverMode::WaveEquation => SolverMode::Automaton,
+                };
+                println!("Solver: {:?}", state.solver.mode);
+                state.recording.solver_switched = true;
+            }
+            VirtualKeyCode::B => {
+                state.solver.boundary = match state.solver.boundary {
+                    Boundary::Fixed => Boundary::Reflective,
+                    Boundary::Reflective => Boundary::Absorbing,
+                    Boundary::Absorbing => Boundary::Periodic,
+                    Boundary::Periodic => Boundary::Fixed,
+                };
+                println!("Boundary: {:?}", state.solver.boundary);
+                state
+                    .recording
+                    .record_boundary(state.updates, state.solver.boundary);
+            }
+            _ => {}
+        }
+    }
 
-                    println!("Wave id {}", wave_id);
+    fn on_character(&self, state: &mut WaveApp<B>, c: char) {
+        state.console.type_char(c);
+    }
 
-                    // TODO fix the bug with waves going in all directions not working
+    fn on_mouse_input(&self, state: &mut WaveApp<B>, button: MouseButton, el_state: ElementState) {
+        if el_state != ElementState::Pressed {
+            return;
+        }
+        let command = match button {
+            MouseButton::Left => Command::Place {
+                slot: state.active_slot,
+                tile: None,
+            },
+            MouseButton::Right => Command::Clear(None),
+            _ => return,
+        };
+        match run_command(state, command) {
+            Ok(done) => println!("{}", done),
+            Err(e) => println!("{}", e),
+        }
+    }
+}
 
-                    state.waves[wave_id].wavelength = wavelength;
-                    state.waves[wave_id].amplitude_factor = amplitude;
-                    state.waves[wave_id].directions = directions;
+/// Does what a console command says, and gives back what to print about it.
+fn run_command<B: Backend>(state: &mut WaveApp<B>, command: Command) -> Result<String, String> {
+    let (width, height) = (state.grid.width, state.grid.height);
+    match command {
+        Command::Wave(wave) => {
+            if wave.slot > state.waves.len() {
+                return Err(format!(
+                    "Invalid wave slot {}; 0 <= slot <= {}.",
+                    wave.slot,
+                    state.waves.len()
+                ));
+            }
+            define_wave(state, wave.slot, wave.to_wave());
+            state.active_slot = wave.slot;
+            Ok(format!("Filled wave slot {}.", wave.slot))
+        }
+        Command::Place { slot, tile } => {
+            if slot >= state.waves.len() {
+                return Err(match state.waves.len() {
+                    0 => "There are no wave slots yet; fill one with \"wave\".".to_string(),
+                    len => format!("Invalid wave slot {}; 0 <= slot < {}.", slot, len),
+                });
+            }
+            let ray = state.current_ray_pos;
+            let (x, y) = tile
+                .or_else(|| state.grid.tile_at(ray.x, ray.z))
+                .ok_or("You aren't pointing at the water.")?;
+            if !place_wave(state, x, y, slot) {
+                return Err(format!("Tile ({}, {}) is land or off the grid.", x, y));
+            }
+            state.recording.record_placement(state.updates, x, y, slot);
+            Ok(format!("Placed wave slot {} on ({}, {}).", slot, x, y))
+        }
+        Command::Clear(tile) => {
+            let ray = state.current_ray_pos;
+            let (x, y) = tile
+                .or_else(|| state.grid.tile_at(ray.x, ray.z))
+                .ok_or("You aren't pointing at the water.")?;
+            if !clear_tile(state, x, y) {
+                return Err(format!("Tile ({}, {}) is off the grid.", x, y));
+            }
+            state.recording.record_clear(state.updates, x, y);
+            Ok(format!("Cleared ({}, {}).", x, y))
+        }
+        Command::Obstacles(path) => {
+            let mask = match &path {
+                Some(path) => ObstacleMask::load(path, width, height)?,
+                None => ObstacleMask::open(width, height),
+            };
+            state
+                .drivers
+                .retain(|driver| !mask.is_blocked(driver.x, driver.y));
+            state.backend.as_mut().unwrap().set_obstacles(&mask);
+            state.obstacles = mask;
+            state.recording.record_obstacles(state.updates, path.clone());
+            Ok(match path {
+                Some(path) => format!("Loaded {}.", path.display()),
+                None => "Cleared every obstacle.".to_string(),
+            })
+        }
+        Command::Depth(map) => {
+            let bathymetry = map.load(width, height)?;
+            state.backend.as_mut().unwrap().set_bathymetry(&bathymetry);
+            state.bathymetry = bathymetry;
+            state.recording.record_depth(state.updates, map.clone());
+            Ok(match map {
+                DepthMap::File(path) => format!("Loaded {}.", path.display()),
+                _ => "Done!".to_string(),
+            })
+        }
+        Command::SaveScene(path) => {
+            let path = path
+                .or_else(|| state.scene_path.clone())
+                .ok_or("No scene was loaded to save over; give a file.")?;
+            save_scene(state, &path)?;
+            state.scene_path = Some(path.clone());
+            Ok(format!("Saved {}.", path.display()))
+        }
+        Command::SaveSnapshot(path) => {
+            take_snapshot(state).save(&path)?;
+            Ok(format!("Saved {}.", path.display()))
+        }
+        Command::LoadSnapshot(path) => {
+            restore_snapshot(state, &Snapshot::load(&path)?)?;
+            Ok(format!("Loaded {}.", path.display()))
+        }
+        Command::SaveRecording(path) => {
+            state.recording.save(&path)?;
+            Ok(format!(
+                "Saved {} waves defined or placed to {}.",
+                state.recording.events.len(),
+                path.display()
+            ))
+        }
+        Command::Export {
+            every,
+            formats,
+            dir,
+        } => {
+            let dir = state.out.join(dir.unwrap_or_default());
+            let done = format!("Exporting every {} updates to {}.", every, dir.display());
+            let exporter = Exporter {
+                dir,
+                formats,
+                range: None,
+            };
+            state.export = Some((every, exporter));
+            Ok(done)
+        }
+        Command::StopExport => {
+            state.export = None;
+            Ok("Stopped exporting.".to_string())
+        }
+        Command::Mesh(path) => {
+            let snapshot = take_snapshot(state);
+            let mesh = Mesh::water(
+                snapshot.restore().as_ref(),
+                &snapshot.waves,
+                state.grid.cell_size,
+            );
+            mesh.save(&path)?;
+            Ok(format!("Saved {}.", path.display()))
+        }
+        Command::View(None) => {
+            state.mapping = None;
+            Ok("Showing the water.".to_string())
+        }
+        Command::View(Some(view)) => {
+            let mapping = view.to_mapping(state.active_slot, &state.waves);
+            state.mapping = Some(mapping);
+            Ok(format!(
+                "Showing {} from {} to {}.",
+                mapping.quantity.name(),
+                mapping.range.0,
+                mapping.range.1
+            ))
+        }
+        Command::Help => Ok(HELP.join("\n")),
+    }
+}
 
-                    println!("Done!");
-                }
-                VirtualKeyCode::N => {
-                    println!("Alright, let's place a wave!");
-                    println!("We will place a wave on the tile you are currently pointing at.");
-                    println!(
-                        "To abort wave placement, just give \"abort\" as an input at any \
-                        time, and we'll abort."
-                    );
-                    let mut s;
-                    let mut wave_id = 0;
-                    loop {
-                        println!("Pick a wave slot.");
-                        println!("If a wave slot picked is empty, the wave will not be placed.");
-                        s = String::new();
-                        std::io::stdin().read_line(&mut s).unwrap();
-                        s = s.trim().to_string();
-                        if s.to_lowercase().eq(&"abort".to_string()) {
-                            println!("Aborting.");
-                            return;
-                        };
-                        let value = s.parse::<usize>();
-                        let b = match value {
-                            Ok(val) => {
-                                if val > 3 {
-                                    println!("Invalid wave id {}; 0 <= id <= 3", val);
-                                    false
-                                } else {
-                                    wave_id = val;
-                                    true
-                                }
-                            }
-                            Err(_) => {
-                                println!("Invalid wave id {}.", s);
-                                false
-                            }
-                        };
-                        if b {
-                            break;
-                        };
-                    }
+/// Writes the heights after this update with the exporter picked with `X`.
+fn export_tick<B: Backend>(state: &WaveApp<B>) {
+    let (_, exporter) = state.export.as_ref().unwrap();
+    let snapshot = take_snapshot(state);
+    match exporter.export(state.updates, snapshot.restore().as_ref(), &snapshot.waves) {
+        Ok(paths) => paths
+            .iter()
+            .for_each(|path| println!("Wrote {}", path.display())),
+        Err(e) => eprintln!("{}", e),
+    }
+}
 
-                    println!("Wave id {}", wave_id);
-
-                    let normalized_ray_coords = (
-                        (state.current_ray_pos.x + (VERTEX_COUNT / 2) as f32) as u64,
-                        VERTEX_COUNT as u64
-                            - (state.current_ray_pos.z + VERTEX_COUNT as f32 / 2.0) as u64,
-                    );
-
-                    let mut k = [0, 0, 0, 0];
-                    k[wave_id] = 1u16 << 8;
-                    unsafe {
-                        state.water.as_ref().unwrap().texture.replace_region(
-                            (normalized_ray_coords.0, normalized_ray_coords.1, 1, 1),
-                            0,
-                            k.as_ptr() as *mut c_void,
-                            VERTEX_COUNT as u64 * 8,
-                        );
-                    };
-
-                    println!("Done!");
-                }
-                VirtualKeyCode::R => unsafe {
-                    FILL_MODE = !FILL_MODE;
-                },
-                _ => {}
+/// Fills `slot` with `wave` and records it.
+fn define_wave<B: Backend>(state: &mut WaveApp<B>, slot: usize, wave: Wave) {
+    state.waves.set(slot, wave);
+    state.backend.as_mut().unwrap().set_waves(&state.waves);
+    state
+        .recording
+        .record_definition(state.updates, slot, &wave);
+}
+
+/// Does what a recorded event did, and records it again.
+fn apply_event<B: Backend>(state: &mut WaveApp<B>, event: &Event) {
+    match *event {
+        Event::Define { ref wave, .. } => define_wave(state, wave.slot, wave.to_wave()),
+        Event::Place { x, y, slot, .. } => {
+            if place_wave(state, x, y, slot) {
+                state.recording.record_placement(state.updates, x, y, slot);
+            } else {
+                eprintln!("Replayed wave ({}, {}) is off the grid or land.", x, y);
+            }
+        }
+        Event::Clear { x, y, .. } => {
+            if clear_tile(state, x, y) {
+                state.recording.record_clear(state.updates, x, y);
             }
         }
+        Event::Boundary { boundary, .. } => {
+            state.solver.boundary = boundary;
+            state.recording.record_boundary(state.updates, boundary);
+        }
+        Event::Obstacles { ref file, .. } => {
+            if let Err(e) = run_command(state, Command::Obstacles(file.clone())) {
+                eprintln!("Replayed obstacles: {}", e);
+            }
+        }
+        Event::Depth { ref map, .. } => {
+            if let Err(e) = run_command(state, Command::Depth(map.clone())) {
+                eprintln!("Replayed depth map: {}", e);
+            }
+        }
+    }
+}
+
+/// Places the wave in `slot` on the tile at `(x, y)` of the height texture and records it as a
+/// source, like `Solver::place` does; false if the tile is off the grid or land.
+fn place_wave<B: Backend>(state: &mut WaveApp<B>, x: u32, y: u32, slot: usize) -> bool {
+    if x >= state.grid.width || y >= state.grid.height || state.obstacles.is_blocked(x, y) {
+        return false;
+    }
+    let ripple = state
+        .waves
+        .get(slot)
+        .is_some_and(|wave| wave.kind != WaveKind::Directional);
+    match state.solver.mode {
+        SolverMode::Automaton if ripple => state.ripples.push(Ripple {
+            x,
+            y,
+            slot: slot as u32,
+            age: 0,
+        }),
+        SolverMode::Automaton => {
+            let directions = state.waves.get(slot).map_or(0, |wave| wave.directions);
+            let size = (state.grid.width, state.grid.height);
+            let turned = turn(directions, 0, state.solver.boundary, (x, y), size);
+            state
+                .backend
+                .as_mut()
+                .unwrap()
+                .set_tile(slot, x, y, ACTIVATED | turned);
+        }
+        SolverMode::WaveEquation => {
+            let speed = state.solver.speed * state.solver.timestep;
+            state.drivers.extend(place_drivers(
+                (x, y),
+                slot,
+                state.waves.get(slot),
+                &state.obstacles,
+                speed,
+            ));
+        }
+    }
+    state.sources.push(Source { x, y, slot });
+    true
+}
+
+/// Removes every wave on the tile at `(x, y)`, like `Solver::clear` does, and the sources placed
+/// on it; false if the tile is off the grid.
+fn clear_tile<B: Backend>(state: &mut WaveApp<B>, x: u32, y: u32) -> bool {
+    if x >= state.grid.width || y >= state.grid.height {
+        return false;
+    }
+    let on_tile = |tile: (u32, u32)| tile == (x, y);
+    state
+        .ripples
+        .retain(|ripple| !on_tile((ripple.x, ripple.y)));
+    state
+        .drivers
+        .retain(|driver| !on_tile((driver.x, driver.y)) && !on_tile(driver.from));
+    state
+        .sources
+        .retain(|source| !on_tile((source.x, source.y)));
+    state.backend.as_mut().unwrap().clear_tile(x, y);
+    true
+}
+
+/// Replaces the waves, solver settings, obstacles, depth map and camera with a scene's, and
+/// places its sources.
+fn load_scene<B: Backend>(state: &mut WaveApp<B>, scene: &Scene) {
+    resize_grid(state, scene.grid);
+    state.waves = scene.get_waves();
+    state.solver = scene.solver;
+    let (width, height) = (scene.grid.width, scene.grid.height);
+    let obstacles = scene
+        .obstacle_mask
+        .clone()
+        .unwrap_or_else(|| ObstacleMask::open(width, height));
+    let bathymetry = scene
+        .bathymetry_map
+        .clone()
+        .unwrap_or_else(|| Bathymetry::flat(width, height));
+    let water = state.backend.as_mut().unwrap();
+    water.set_waves(&state.waves);
+    water.set_obstacles(&obstacles);
+    water.set_bathymetry(&bathymetry);
+    state.obstacles = obstacles;
+    state.bathymetry = bathymetry;
+    if let Some(camera) = scene.camera {
+        state.matrix_bundle.as_mut().unwrap().camera = camera;
+    }
+    for source in scene.sources.iter() {
+        if !place_wave(state, source.x, source.y, source.slot) {
+            eprintln!(
+                "Source ({}, {}) is off the grid or land.",
+                source.x, source.y
+            );
+        }
+    }
+}
+
+/// Makes the water over at the size of `grid` if it isn't already; everything on the old water
+/// is gone, and the caller sets its waves, obstacles and depths again.
+fn resize_grid<B: Backend>(state: &mut WaveApp<B>, grid: Grid) {
+    if state.grid == grid {
+        return;
+    }
+    state.grid = grid;
+    state.backend.as_mut().unwrap().resize(grid);
+    state.ripples.clear();
+    state.drivers.clear();
+    state.sources.clear();
+}
+
+/// Writes everything `load_scene` reads back to `path`.
+fn save_scene<B: Backend>(state: &WaveApp<B>, path: &Path) -> Result<(), String> {
+    let mut scene = Scene {
+        obstacle_mask: Some(state.obstacles.clone()),
+        bathymetry_map: Some(state.bathymetry.clone()),
+        // sources land has since covered can't be placed again
+        sources: state
+            .sources
+            .iter()
+            .filter(|source| !state.obstacles.is_blocked(source.x, source.y))
+            .copied()
+            .collect(),
+        solver: state.solver,
+        grid: state.grid,
+        camera: Some(state.matrix_bundle.as_ref().unwrap().camera),
+        ..Scene::default()
+    };
+    scene.set_waves(&state.waves);
+    scene.save(path)
+}
+
+/// Reads the state of the simulation back from the GPU.
+fn take_snapshot<B: Backend>(state: &WaveApp<B>) -> Snapshot {
+    let water = state.backend.as_ref().unwrap();
+    let (tiles, travel) = water.read_layers();
+    let (heights, previous) = water.read_fields();
+    Snapshot {
+        time: state.updates,
+        waves: state.waves.clone(),
+        solver: state.solver,
+        width: state.grid.width,
+        height: state.grid.height,
+        obstacles: state.obstacles.clone(),
+        bathymetry: state.bathymetry.clone(),
+        slots: water.get_slot_count() as u32,
+        tiles,
+        travel,
+        ripples: state.ripples.clone(),
+        heights,
+        previous,
+        drivers: state.drivers.clone(),
+    }
+}
+
+/// Puts the simulation back in the state a snapshot was taken in. Scenes saved afterwards
+/// don't know where its waves were placed, and recording starts over from the snapshot.
+fn restore_snapshot<B: Backend>(state: &mut WaveApp<B>, snapshot: &Snapshot) -> Result<(), String> {
+    // snapshots don't keep the cell size
+    let grid = Grid {
+        width: snapshot.width,
+        height: snapshot.height,
+        ..state.grid
+    };
+    grid.validate()?;
+    resize_grid(state, grid);
+    state.waves = snapshot.waves.clone();
+    state.solver = snapshot.solver;
+    state.updates = snapshot.time;
+    state.time = snapshot.time * state.config.frames_per_update;
+    state.ripples = snapshot.ripples.clone();
+    state.drivers = snapshot.drivers.clone();
+    state.sources.clear();
+    state.recording = Recording::default();
+    state.replay = None;
+    let water = state.backend.as_mut().unwrap();
+    // the snapshot can have more layers than waves
+    water.grow_slots(snapshot.slots as usize);
+    water.set_waves(&state.waves);
+    water.set_obstacles(&snapshot.obstacles);
+    water.set_bathymetry(&snapshot.bathymetry);
+    water.write_layers(&snapshot.tiles, &snapshot.travel);
+    if snapshot.heights.is_empty() {
+        let flat = vec![0.0; state.grid.get_len()];
+        water.write_fields(&flat, &flat);
+    } else {
+        water.write_fields(&snapshot.heights, &snapshot.previous);
     }
+    state.obstacles = snapshot.obstacles.clone();
+    state.bathymetry = snapshot.bathymetry.clone();
+    Ok(())
 }
@@ -0,0 +1,52 @@
+//! What happens to the water at the edges of the grid. Both solvers, and both of their kernels in
+//! `bundles/shaders.metal`, read past the edges through `Boundary::resolve`.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+#[repr(u32)]
+pub enum Boundary {
+    /// Tiles past the edges are flat and hold no waves.
+    #[default]
+    Fixed = 0,
+    /// Tiles past the edges mirror the edge tiles, so nothing flows out.
+    Reflective = 1,
+    /// Like `Reflective`, with a sponge layer along the edges that soaks waves up; the automaton
+    /// fades them out across it and lets them leave the grid.
+    Absorbing = 2,
+    /// The grid wraps around; the left edge neighbours the right one, the top the bottom.
+    Periodic = 3,
+}
+
+impl Boundary {
+    /// The tile read in place of `(x, y)`, which may be outside of the grid, or `None` if
+    /// nothing is there.
+    pub fn resolve(&self, x: i64, y: i64, width: i64, height: i64) -> Option<(i64, i64)> {
+        if x >= 0 && y >= 0 && x < width && y < height {
+            return Some((x, y));
+        }
+        match self {
+            Boundary::Fixed => None,
+            Boundary::Reflective | Boundary::Absorbing => {
+                Some((x.max(0).min(width - 1), y.max(0).min(height - 1)))
+            }
+            Boundary::Periodic => Some((x.rem_euclid(width), y.rem_euclid(height))),
+        }
+    }
+
+    /// How deep into the sponge layer a tile is: 0 outside of it, rising to 1 on the edge.
+    /// Only `Absorbing` has a sponge.
+    pub fn sponge(&self, x: u32, y: u32, width: u32, height: u32, sponge_width: u32) -> f32 {
+        if *self != Boundary::Absorbing || sponge_width == 0 || x >= width || y >= height {
+            return 0.0;
+        }
+        let distance = x.min(y).min(width - 1 - x).min(height - 1 - y);
+        if distance >= sponge_width {
+            0.0
+        } else {
+            let depth = (sponge_width - distance) as f32 / sponge_width as f32;
+            depth * depth
+        }
+    }
+}
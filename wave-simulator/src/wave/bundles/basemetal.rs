@@ -1,5 +1,4 @@
 use crate::wave::bundles::window::WindowBundle;
-use crate::wave::constants::VSYNC;
 use cull_canyon::{set_layer_for_raw_window_handle, CAMetalLayer, MTLCommandQueue, MTLCompileOptions, MTLDepthStencilDescriptor, MTLDepthStencilState, MTLDevice, MTLLibrary, MTLTexture, MTLTextureDescriptor};
 use objc::runtime::Object;
 use objc::{msg_send, class, sel, sel_impl};
@@ -29,7 +28,7 @@ unsafe fn string_to_nsstring(src: &str) -> *mut Object {
 }
 
 impl BaseMetalBundle {
-    pub unsafe fn new(window_bundle: &WindowBundle) -> BaseMetalBundle {
+    pub unsafe fn new(window_bundle: &WindowBundle, vsync: bool) -> BaseMetalBundle {
         let devices = {
             #[cfg(target_os = "macos")]
             {
@@ -46,7 +45,7 @@ impl BaseMetalBundle {
 
         let surface = CAMetalLayer::new();
         surface.set_pixel_format(80); // rgba8unorm = 70; bgra8unorm = 80
-        surface.set_display_sync_enabled(VSYNC);
+        surface.set_display_sync_enabled(vsync);
         surface.set_device(device.clone());
         surface.set_contents_scale(2.0);
         surface.set_presents_with_transaction(false);
@@ -1,4 +1,5 @@
 use crate::wave::bundles::basemetal::BaseMetalBundle;
+use crate::wave::render::{MARKER_CORNERS, MARKER_INDICES};
 use cull_canyon::{
     MTLBuffer, MTLRenderPipelineColorAttachmentDescriptor, MTLRenderPipelineDescriptor,
     MTLRenderPipelineState, MTLVertexDescriptor,
@@ -14,17 +15,8 @@ pub struct DebugBundle {
 
 impl DebugBundle {
     pub unsafe fn new(bundle: &BaseMetalBundle) -> DebugBundle {
-        let vertices = [
-            -0.5f32, 0.5, -0.5, -0.5, -0.5, -0.5, 0.5, -0.5, -0.5, 0.5, 0.5, -0.5, -0.5, 0.5, 0.5,
-            -0.5, -0.5, 0.5, 0.5, -0.5, 0.5, 0.5, 0.5, 0.5, 0.5, 0.5, -0.5, 0.5, -0.5, -0.5, 0.5,
-            -0.5, 0.5, 0.5, 0.5, 0.5, -0.5, 0.5, -0.5, -0.5, -0.5, -0.5, -0.5, -0.5, 0.5, -0.5,
-            0.5, 0.5, -0.5, 0.5, 0.5, -0.5, 0.5, -0.5, 0.5, 0.5, -0.5, 0.5, 0.5, 0.5, -0.5, -0.5,
-            0.5, -0.5, -0.5, -0.5, 0.5, -0.5, -0.5, 0.5, -0.5, 0.5,
-        ];
-        let indices = [
-            0u32, 1, 3, 3, 1, 2, 4, 5, 7, 7, 5, 6, 8, 9, 11, 11, 9, 10, 12, 13, 15, 15, 13, 14, 16,
-            17, 19, 19, 17, 18, 20, 21, 23, 23, 21, 22,
-        ];
+        let vertices = MARKER_CORNERS.concat();
+        let indices = MARKER_INDICES;
         let indices_count = indices.len() as u64;
         let vertices = bundle.device.new_buffer_with_bytes(
             vertices.as_ptr() as *const c_void,
@@ -0,0 +1,515 @@
+//! The app's `Backend` on macOS: the water on the GPU, and frames drawn with Metal, with the
+//! update of the water going into the same command buffer.
+
+use crate::wave::backend::{Backend, Frame};
+use crate::wave::bathymetry::Bathymetry;
+use crate::wave::bundles::basemetal::BaseMetalBundle;
+use crate::wave::bundles::debug::DebugBundle;
+use crate::wave::bundles::ui::UiBundle;
+use crate::wave::bundles::water::{
+    new_forced_buffer, new_ripple_buffer, LightUniform, MappingUniform, SolverUniform, WaterBundle,
+};
+use crate::wave::bundles::window::WindowBundle;
+use crate::wave::grid::Grid;
+use crate::wave::mesh::Mesh;
+use crate::wave::obstacles::ObstacleMask;
+use crate::wave::physics::ForcedTile;
+use crate::wave::render::colormap::Colormap;
+use crate::wave::render::{Image, Light, Renderer, Shading, Surface};
+use crate::wave::sim::{Ripple, SolverMode, SolverSettings, WaveRegistry};
+use crate::wave::util::generate_transformation;
+use cgmath::{Matrix4, SquareMatrix, Vector3};
+use cull_canyon::{
+    CAMetalDrawable, MTLBuffer, MTLCommandBuffer, MTLCommandEncoder, MTLRenderCommandEncoder,
+    MTLRenderPassAttachmentDescriptor, MTLRenderPassColorAttachmentDescriptor,
+    MTLRenderPassDescriptor, MTLTexture, MTLTextureDescriptor,
+};
+use std::os::raw::c_void;
+
+// a surface uploaded from the CPU, laid out for surface_vert
+struct SurfaceBuffers {
+    positions: MTLBuffer,
+    normals: MTLBuffer,
+    texture_coords: MTLBuffer,
+    land: MTLBuffer,
+    values: MTLBuffer,
+    indices: MTLBuffer,
+    indices_count: usize,
+}
+
+/// The water on the GPU, and the window it's drawn in.
+pub struct MetalBackend {
+    pub window: WindowBundle,
+    pub bundle: BaseMetalBundle,
+    pub ui: UiBundle,
+    pub debug: DebugBundle,
+    pub water: WaterBundle,
+}
+
+impl MetalBackend {
+    pub unsafe fn new(window: WindowBundle, vsync: bool, grid: Grid) -> MetalBackend {
+        let bundle = BaseMetalBundle::new(&window, vsync);
+        MetalBackend {
+            ui: UiBundle::new(&bundle),
+            debug: DebugBundle::new(&bundle),
+            water: WaterBundle::generate_water(&bundle, grid),
+            window,
+            bundle,
+        }
+    }
+}
+
+pub struct MetalFrame<'a> {
+    bundle: &'a BaseMetalBundle,
+    ui: &'a UiBundle,
+    debug: &'a DebugBundle,
+    // the simulation, which is the water drawn until a surface is uploaded
+    water: &'a mut WaterBundle,
+    mode: SolverMode,
+    // the buffers written are made the current ones once the update is committed
+    stepped: bool,
+    settings: SolverUniform,
+    ripples: MTLBuffer,
+    ripple_count: u32,
+    surface: Option<SurfaceBuffers>,
+    pub wireframe: bool,
+    drawable: CAMetalDrawable,
+    command_buffer: MTLCommandBuffer,
+    encoder: Option<MTLRenderCommandEncoder>,
+}
+
+impl<'a> MetalFrame<'a> {
+    /// Starts drawing into the window's next drawable, with the backend's water as the water;
+    /// None if there's no drawable to draw into.
+    pub unsafe fn begin(
+        backend: &'a mut MetalBackend,
+        solver: &SolverSettings,
+        ripples: &[Ripple],
+        wireframe: bool,
+    ) -> Option<MetalFrame<'a>> {
+        let bundle = &backend.bundle;
+        let drawable = bundle.surface.next_drawable()?;
+        Some(MetalFrame {
+            bundle,
+            ui: &backend.ui,
+            debug: &backend.debug,
+            settings: SolverUniform::new(solver, &backend.water.grid),
+            water: &mut backend.water,
+            mode: solver.mode,
+            stepped: false,
+            ripples: new_ripple_buffer(&bundle.device, ripples),
+            ripple_count: ripples.len() as u32,
+            surface: None,
+            wireframe,
+            drawable,
+            command_buffer: bundle.queue.new_command_buffer(),
+            encoder: None,
+        })
+    }
+
+    // the render pass, started over what's on the drawable unless `clear` started it
+    unsafe fn encoder(&mut self) -> &MTLRenderCommandEncoder {
+        if self.encoder.is_none() {
+            self.start_pass(None);
+        }
+        self.encoder.as_ref().unwrap()
+    }
+
+    unsafe fn start_pass(&mut self, clear: Option<[f32; 4]>) {
+        self.end_pass();
+        let bundle = self.bundle;
+        let texture = self.drawable.get_texture();
+        // 2 clears, 1 keeps what's there
+        let load = if clear.is_some() { 2 } else { 1 };
+        let colour = clear.unwrap_or([0.0, 0.0, 0.0, 1.0]);
+        let encoder = self
+            .command_buffer
+            .new_render_command_encoder_with_descriptor({
+                let desc = MTLRenderPassDescriptor::new();
+                {
+                    let desc = desc.get_depth_attachment();
+                    desc.set_texture(bundle.depth_texture.clone());
+                    desc.set_load_action(load);
+                    desc.set_store_action(1);
+                };
+                desc.get_color_attachments()
+                    .set_object_at_indexed_subscript(0, {
+                        let desc = MTLRenderPassColorAttachmentDescriptor::new();
+                        desc.set_texture(texture);
+                        desc.set_clear_color(
+                            colour[0] as f64,
+                            colour[1] as f64,
+                            colour[2] as f64,
+                            colour[3] as f64,
+                        );
+                        desc.set_load_action(load);
+                        desc.set_store_action(1);
+                        desc
+                    });
+                desc
+            });
+        self.encoder = Some(encoder);
+    }
+
+    unsafe fn end_pass(&mut self) {
+        if let Some(encoder) = self.encoder.take() {
+            encoder.end_encoding();
+        }
+    }
+
+    // made for every draw, since the GPU reads it after this returns
+    unsafe fn new_texture(&self, image: &Image) -> MTLTexture {
+        let texture = self.bundle.device.new_texture_with_descriptor({
+            let desc = MTLTextureDescriptor::new();
+            desc.set_width(image.width as u64);
+            desc.set_height(image.height as u64);
+            desc.set_pixel_format(70); // rgba8unorm
+            desc.set_texture_type(2);
+            desc
+        });
+        texture.replace_region(
+            (0, 0, image.width as u64, image.height as u64),
+            0,
+            image.pixels.as_ptr() as *mut c_void,
+            image.width as u64 * 4,
+        );
+        texture
+    }
+
+    unsafe fn set_matrices(&mut self, projection: &Matrix4<f32>, view: &Matrix4<f32>) {
+        let encoder = self.encoder();
+        encoder.set_vertex_bytes(projection as *const Matrix4<f32> as *const c_void, 64, 1);
+        encoder.set_vertex_bytes(view as *const Matrix4<f32> as *const c_void, 64, 2);
+    }
+
+    // the height of every vertex for water_vert, from the water as it is before this frame's
+    // update; the render pass starts over after it
+    unsafe fn encode_surface_heights(&mut self) {
+        self.end_pass();
+        let water = &*self.water;
+        let slot_count = water.slots as u32;
+        let mode = self.mode as u32;
+        let settings_len = std::mem::size_of::<SolverUniform>() as u64;
+        let settings_ptr = &self.settings as *const SolverUniform as *const c_void;
+        let ripple_count = &self.ripple_count as *const u32 as *const c_void;
+        let encoder = self.command_buffer.new_compute_command_encoder();
+        encoder.set_compute_pipeline_state(water.heights_pipeline.clone());
+        encoder.set_buffer(water.waves.clone(), 0, 0);
+        encoder.set_bytes(&slot_count as *const u32 as *const c_void, 4, 1);
+        encoder.set_buffer(water.get_heights(), 0, 2);
+        encoder.set_bytes(&mode as *const u32 as *const c_void, 4, 3);
+        encoder.set_buffer(water.get_field(), 0, 4);
+        encoder.set_bytes(settings_ptr, settings_len, 5);
+        encoder.set_buffer(water.obstacles.clone(), 0, 6);
+        encoder.set_buffer(self.ripples.clone(), 0, 7);
+        encoder.set_bytes(ripple_count, 4, 8);
+        encoder.set_buffer(water.get_travel_buffer(), 0, 9);
+        encoder.set_buffer(water.lags.clone(), 0, 10);
+        encoder.set_buffer(water.surface_heights.clone(), 0, 11);
+        let groups = |tiles: u32| (tiles as u64).div_ceil(10);
+        encoder.dispatch_threadgroups(
+            (groups(water.grid.width), groups(water.grid.height), 1),
+            (10, 10, 1),
+        );
+        encoder.end_encoding();
+    }
+
+    // the update, after what's drawn so far
+    unsafe fn encode_step(&mut self, forced: &[ForcedTile]) {
+        self.end_pass();
+        let water = &*self.water;
+        let slot_count = water.slots as u32;
+        let settings_len = std::mem::size_of::<SolverUniform>() as u64;
+        let settings_ptr = &self.settings as *const SolverUniform as *const c_void;
+        let encoder = self.command_buffer.new_compute_command_encoder();
+        match self.mode {
+            SolverMode::Automaton => {
+                encoder.set_compute_pipeline_state(water.compute_pipeline.clone());
+                encoder.set_buffer(water.waves.clone(), 0, 0);
+                encoder.set_bytes(&slot_count as *const u32 as *const c_void, 4, 1);
+                encoder.set_buffer(water.get_heights(), 0, 2);
+                encoder.set_buffer(water.get_next_heights(), 0, 3);
+                encoder.set_bytes(settings_ptr, settings_len, 4);
+                encoder.set_buffer(water.obstacles.clone(), 0, 5);
+                encoder.set_buffer(water.lags.clone(), 0, 6);
+                encoder.set_buffer(water.get_travel_buffer(), 0, 7);
+                encoder.set_buffer(water.get_next_travel_buffer(), 0, 8);
+            }
+            SolverMode::WaveEquation => {
+                let forced_count = forced.len() as u32;
+                encoder.set_compute_pipeline_state(water.wave_equation_pipeline.clone());
+                encoder.set_bytes(settings_ptr, settings_len, 0);
+                encoder.set_buffer(water.get_previous_field(), 0, 1);
+                encoder.set_buffer(water.get_field(), 0, 2);
+                encoder.set_buffer(water.get_next_field(), 0, 3);
+                encoder.set_buffer(new_forced_buffer(&self.bundle.device, forced), 0, 4);
+                encoder.set_bytes(&forced_count as *const u32 as *const c_void, 4, 5);
+                encoder.set_buffer(water.obstacles.clone(), 0, 6);
+                encoder.set_buffer(water.speeds.clone(), 0, 7);
+            }
+        }
+        // the kernels skip the threads past the edges
+        let groups = |tiles: u32| (tiles as u64).div_ceil(10);
+        encoder.dispatch_threadgroups(
+            (groups(water.grid.width), groups(water.grid.height), 1),
+            (10, 10, 1),
+        );
+        encoder.end_encoding();
+    }
+}
+
+impl<'a> Renderer for MetalFrame<'a> {
+    fn upload_surface(&mut self, surface: &Surface) {
+        let mesh = Mesh::from_heights(&surface.grid, &surface.heights);
+        let land = surface.land.iter().map(|l| *l as u8).collect::<Vec<u8>>();
+        let device = &self.bundle.device;
+        let buffer = |bytes: *const c_void, len: usize| unsafe {
+            device.new_buffer_with_bytes(bytes, len as u64, 0)
+        };
+        self.surface = Some(SurfaceBuffers {
+            positions: buffer(
+                mesh.positions.as_ptr() as *const c_void,
+                mesh.positions.len() * 12,
+            ),
+            normals: buffer(
+                mesh.normals.as_ptr() as *const c_void,
+                mesh.normals.len() * 12,
+            ),
+            texture_coords: buffer(mesh.uvs.as_ptr() as *const c_void, mesh.uvs.len() * 8),
+            land: buffer(land.as_ptr() as *const c_void, land.len()),
+            values: buffer(
+                surface.values.as_ptr() as *const c_void,
+                surface.values.len() * 4,
+            ),
+            indices: buffer(
+                mesh.indices.as_ptr() as *const c_void,
+                mesh.indices.len() * 4,
+            ),
+            indices_count: mesh.indices.len(),
+        });
+    }
+
+    fn clear(&mut self, colour: [f32; 4]) {
+        unsafe { self.start_pass(Some(colour)) };
+    }
+
+    fn draw_water(&mut self, projection: Matrix4<f32>, view: Matrix4<f32>, shading: &Shading) {
+        unsafe {
+            // the water shaders read a colormap even when there's none to show
+            let colormap = match shading {
+                Shading::Water(_) => self.new_texture(&Colormap::Viridis.strip()),
+                Shading::Mapped(mapping) => self.new_texture(&mapping.colormap.strip()),
+            };
+            let mapping = MappingUniform::new(shading);
+            let mapping_len = std::mem::size_of::<MappingUniform>() as u64;
+            let mapping_ptr = &mapping as *const MappingUniform as *const c_void;
+            if self.surface.is_none() {
+                self.encode_surface_heights();
+            }
+            self.set_matrices(&projection, &view);
+            let bundle = self.bundle;
+            let water = &*self.water;
+            let wireframe = self.wireframe;
+            let encoder = self.encoder.as_ref().unwrap();
+            let (indices, indices_count) = match &self.surface {
+                Some(surface) => {
+                    encoder.set_render_pipeline_state(water.surface_pipeline.clone());
+                    encoder.set_vertex_buffer(surface.positions.clone(), 0, 0);
+                    encoder.set_vertex_buffer(surface.texture_coords.clone(), 0, 3);
+                    encoder.set_vertex_buffer(surface.land.clone(), 0, 4);
+                    encoder.set_vertex_buffer(surface.normals.clone(), 0, 5);
+                    encoder.set_vertex_buffer(surface.values.clone(), 0, 6);
+                    encoder.set_vertex_bytes(mapping_ptr, mapping_len, 7);
+                    (surface.indices.clone(), surface.indices_count)
+                }
+                None => {
+                    encoder.set_render_pipeline_state(water.render_pipeline.clone());
+                    encoder.set_vertex_buffer(water.water_buffer.clone(), 0, 0);
+                    let slot_count = water.slots as u32;
+                    encoder.set_vertex_buffer(water.waves.clone(), 0, 3);
+                    encoder.set_vertex_bytes(&slot_count as *const u32 as *const c_void, 4, 4);
+                    encoder.set_vertex_buffer(water.get_heights(), 0, 5);
+                    let mode = self.mode as u32;
+                    encoder.set_vertex_bytes(&mode as *const u32 as *const c_void, 4, 6);
+                    encoder.set_vertex_buffer(water.surface_heights.clone(), 0, 7);
+                    let settings_len = std::mem::size_of::<SolverUniform>() as u64;
+                    let settings_ptr = &self.settings as *const SolverUniform as *const c_void;
+                    encoder.set_vertex_bytes(settings_ptr, settings_len, 8);
+                    encoder.set_vertex_buffer(water.obstacles.clone(), 0, 9);
+                    encoder.set_vertex_buffer(self.ripples.clone(), 0, 10);
+                    let ripple_count = &self.ripple_count as *const u32 as *const c_void;
+                    encoder.set_vertex_bytes(ripple_count, 4, 11);
+                    encoder.set_vertex_bytes(mapping_ptr, mapping_len, 12);
+                    encoder.set_vertex_buffer(water.lags.clone(), 0, 13);
+                    (water.water_indices.clone(), water.indices_count)
+                }
+            };
+            encoder.set_triangle_fill_mode(wireframe as u64);
+            encoder.set_depth_stencil_state(bundle.basic_depth.clone());
+            // the camera is where the view takes the origin from
+            let eye = view
+                .invert()
+                .map_or(Vector3::unit_y(), |inverse| inverse.w.truncate());
+            let light = match shading {
+                Shading::Water(light) => LightUniform::new(light, eye),
+                Shading::Mapped(_) => LightUniform::new(&Light::unlit(), eye),
+            };
+            encoder.set_fragment_bytes(
+                &light as *const LightUniform as *const c_void,
+                std::mem::size_of::<LightUniform>() as u64,
+                0,
+            );
+            encoder.set_fragment_bytes(mapping_ptr, mapping_len, 1);
+            encoder.set_fragment_texture(water.water_surface.clone(), 0);
+            encoder.set_fragment_texture(colormap, 1);
+            encoder.set_fragment_sampler_state(water.sampler.clone(), 0);
+            encoder.draw_indexed_primitives(3, indices_count as u64, 1, indices, 0, 1, 0, 0);
+        }
+    }
+
+    fn draw_marker(
+        &mut self,
+        projection: Matrix4<f32>,
+        view: Matrix4<f32>,
+        position: Vector3<f32>,
+    ) {
+        unsafe {
+            self.set_matrices(&projection, &view);
+            let bundle = self.bundle;
+            let debug = self.debug;
+            let encoder = self.encoder.as_ref().unwrap();
+            encoder.set_render_pipeline_state(debug.pipeline.clone());
+            encoder.set_vertex_buffer(debug.vertices.clone(), 0, 0);
+            let transformation =
+                generate_transformation(position, (0.0, 0.0, 0.0), (1.0, 1.0, 1.0));
+            encoder.set_vertex_bytes(
+                &transformation as *const Matrix4<f32> as *const c_void,
+                64,
+                3,
+            );
+            encoder.set_depth_stencil_state(bundle.basic_depth.clone());
+            encoder.draw_indexed_primitives(
+                3,
+                debug.indices_count,
+                0,
+                debug.indices.clone(),
+                0,
+                1,
+                0,
+                0,
+            );
+        }
+    }
+
+    fn draw_quad(&mut self, quad: [f32; 4], image: &Image) {
+        unsafe {
+            let texture = self.new_texture(image);
+            let ui = self.ui;
+            let sampler = self.water.sampler.clone();
+            let encoder = self.encoder();
+            encoder.set_render_pipeline_state(ui.pipeline.clone());
+            encoder.set_vertex_buffer(ui.quad.clone(), 0, 0);
+            encoder.set_vertex_bytes(quad.as_ptr() as *const c_void, 16, 1);
+            encoder.set_fragment_texture(texture, 0);
+            encoder.set_fragment_sampler_state(sampler, 0);
+            encoder.draw_primitives(3, 0, 6, 1, 0);
+        }
+    }
+}
+
+impl<'a> Frame for MetalFrame<'a> {
+    fn step(&mut self, forced: &[ForcedTile]) {
+        unsafe { self.encode_step(forced) };
+        self.stepped = true;
+    }
+
+    fn present(mut self, wait: bool) {
+        unsafe {
+            self.end_pass();
+            self.command_buffer.present_drawable(self.drawable.clone());
+            self.command_buffer.commit();
+            if wait {
+                self.command_buffer.wait_until_completed();
+            }
+        }
+        if self.stepped {
+            match self.mode {
+                SolverMode::Automaton => self.water.swap(),
+                SolverMode::WaveEquation => self.water.rotate_fields(),
+            }
+        }
+    }
+}
+
+impl Backend for MetalBackend {
+    type Frame<'a> = MetalFrame<'a>;
+
+    fn begin(
+        &mut self,
+        solver: &SolverSettings,
+        ripples: &[Ripple],
+        wireframe: bool,
+    ) -> Option<MetalFrame<'_>> {
+        unsafe { MetalFrame::begin(self, solver, ripples, wireframe) }
+    }
+
+    fn resize(&mut self, grid: Grid) {
+        self.water = unsafe { WaterBundle::generate_water(&self.bundle, grid) };
+    }
+
+    fn get_slot_count(&self) -> usize {
+        self.water.slots
+    }
+
+    fn grow_slots(&mut self, slots: usize) {
+        unsafe { self.water.grow_slots(&self.bundle.device, slots) };
+    }
+
+    fn set_waves(&mut self, waves: &WaveRegistry) {
+        unsafe { self.water.set_waves(&self.bundle.device, waves) };
+    }
+
+    fn set_obstacles(&mut self, mask: &ObstacleMask) {
+        unsafe { self.water.set_obstacles(&self.bundle.device, mask) };
+    }
+
+    fn set_bathymetry(&mut self, bathymetry: &Bathymetry) {
+        unsafe { self.water.set_bathymetry(&self.bundle.device, bathymetry) };
+    }
+
+    fn get_tile(&self, slot: usize, x: u32, y: u32) -> Option<u16> {
+        unsafe { self.water.get_tile(slot, x as u64, y as u64) }
+    }
+
+    fn get_travel(&self, slot: usize, x: u32, y: u32) -> Option<u32> {
+        unsafe { self.water.get_travel(slot, x as u64, y as u64) }
+    }
+
+    fn set_tile(&mut self, slot: usize, x: u32, y: u32, value: u16) {
+        unsafe { self.water.set_tile(slot, x as u64, y as u64, value) };
+    }
+
+    fn clear_tile(&mut self, x: u32, y: u32) {
+        unsafe { self.water.clear_tile(x as u64, y as u64) };
+    }
+
+    fn get_field_height(&self, x: u32, y: u32) -> Option<f32> {
+        unsafe { self.water.get_field_height(x as u64, y as u64) }
+    }
+
+    fn read_layers(&self) -> (Vec<u16>, Vec<u32>) {
+        unsafe { self.water.read_layers() }
+    }
+
+    fn write_layers(&mut self, tiles: &[u16], travel: &[u32]) {
+        unsafe { self.water.write_layers(tiles, travel) };
+    }
+
+    fn read_fields(&self) -> (Vec<f32>, Vec<f32>) {
+        unsafe { self.water.read_fields() }
+    }
+
+    fn write_fields(&mut self, heights: &[f32], previous: &[f32]) {
+        unsafe { self.water.write_fields(heights, previous) };
+    }
+}
@@ -1,38 +1,16 @@
-use crate::wave::bundles::basemetal::BaseMetalBundle;
 use crate::wave::camera::Camera;
-use crate::wave::constants::new_projection_matrix;
-use cgmath::{Matrix4, Matrix};
-use cull_canyon::MTLBuffer;
-use std::os::raw::c_void;
+use cgmath::Matrix4;
 
 pub struct MatrixBundle {
-    pub projection: MTLBuffer,
-    pub proj_contents: Matrix4<f32>,
-    pub view: MTLBuffer,
+    pub projection: Matrix4<f32>,
+    // the view is the camera's, whenever a frame is drawn
     pub camera: Camera,
 }
 
 impl MatrixBundle {
-    pub unsafe fn new(bundle: &BaseMetalBundle, aspect_ratio: f32) -> MatrixBundle {
-        let projection = new_projection_matrix(aspect_ratio);
-        let view = [
-            1.0f32, 0.0, 0.0, 0.0, // r1
-            0.0, 1.0, 0.0, 0.0, // r2
-            0.0, 0.0, 1.0, 0.0, // r3
-            0.0, 0.0, 0.0, 1.0, // r4
-        ];
+    pub fn new(projection: Matrix4<f32>) -> MatrixBundle {
         MatrixBundle {
-            projection: bundle.device.new_buffer_with_bytes(
-                projection.as_ptr() as *const c_void,
-                64,
-                0,
-            ),
-            proj_contents: projection,
-            view: bundle.device.new_buffer_with_bytes(
-                view.as_ptr() as *const c_void,
-                view.len() as u64 * 4,
-                0,
-            ),
+            projection,
             camera: Camera {
                 x: 0.0,
                 y: 0.0,
@@ -43,14 +21,8 @@ impl MatrixBundle {
             },
         }
     }
-    pub unsafe fn edit_projection(&self, aspect_ratio: f32) {
-        let projection = new_projection_matrix(aspect_ratio);
-        let contents = self.projection.get_contents() as *mut cgmath::Matrix4<f32>;
-        std::mem::replace(&mut *contents, projection);
-    }
-    pub unsafe fn edit_view(&self) {
-        let view = self.camera.get_matrix();
-        let contents = self.view.get_contents() as *mut cgmath::Matrix4<f32>;
-        std::mem::replace(&mut *contents, view);
+    pub fn edit_projection(&mut self, projection: Matrix4<f32>) {
+        // rays from the mouse go through the projection too
+        self.projection = projection;
     }
 }
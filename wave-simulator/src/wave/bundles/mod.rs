@@ -1,6 +1,13 @@
+#[cfg(target_os = "macos")]
 pub mod basemetal;
+#[cfg(target_os = "macos")]
 pub mod debug;
+#[cfg(target_os = "macos")]
+pub mod frame;
 pub mod matrix;
+#[cfg(target_os = "macos")]
 pub mod ui;
+#[cfg(target_os = "macos")]
 pub mod water;
+#[cfg(target_os = "macos")]
 pub mod window;
@@ -66,163 +66,670 @@ struct WaterVertex {
 struct WaterFragment {
     float4 position [[ position ]];
     float2 textureCoords;
+    float land;
+    // how far along the colormap, 0 to 1 inside the mapping's range
+    float value;
+    float3 normal;
+    // where it is in the world, for the way to the eye
+    float3 world;
 };
 
 struct Wave {
-    char directions;
-    char wavelength;
+    uchar directions;
+    uchar wavelength;
+    uchar kind;
     float amplitude;
+    float heading;
+    float decay;
+    float falloff;
+    uint lifetime;
 };
 
-vertex WaterFragment water_vert(device WaterVertex *vertexArray [[ buffer(0) ]],
-                                constant float4x4 &projection [[ buffer(1) ]],
-                                constant float4x4 &view [[ buffer(2) ]],
-                                constant Wave *waves [[ buffer(3) ]],
-                                texture2d<ushort, access::read> heightMap [[ texture(0) ]],
-                                uint vid [[ vertex_id ]])
-{
-    float2 pos = vertexArray[vid].position;
+// what is left of a wave's amplitude (see wave::sim::attenuation)
+float attenuation(Wave wave, uint age, float distance) {
+    return exp(-wave.decay * float(age)) / (1.0 + wave.falloff * distance);
+}
+
+bool isExpired(Wave wave, uint age) {
+    return wave.lifetime != 0 && age >= wave.lifetime;
+}
 
-    int2 texturedPos = int2(pos);
-    texturedPos += 50;
-    texturedPos.y = 100 - texturedPos.y;
-    ushort4 encodedInfo = heightMap.read(uint2(texturedPos));
-    ushort4 tickPos = encodedInfo & 255;
-    ushort4 waveBoolChart = (encodedInfo >> 8) & 255;
-    float r = 0;
-    float g = 0;
-    float b = 0;
-    float a = 0;
-    if (waveBoolChart.r == 1) {
-        r = waves[0].amplitude * sin(float(tickPos.r) * (M_PI_F / float(waves[0].wavelength)));
+// a tile's travel: the tiles its wave came across in the high half, the updates since it was
+// placed in the low half (see wave::sim::pack_travel)
+uint packTravel(uint age, uint distance) {
+    return (min(distance, 65535u) << 16) | min(age, 65535u);
+}
+
+// wave::sim::WaveKind
+constant uchar PLANE = 2;
+
+// wave::sim::Ripple
+struct Ripple {
+    uint x;
+    uint y;
+    uint slot;
+    uint age;
+};
+
+// LAND_HEIGHT in constants.rs
+constant float LAND_HEIGHT = 1.0;
+constant float4 LAND_COLOUR = float4(0.76, 0.70, 0.50, 1.0);
+
+// wave::sim::SolverMode; the automaton is 0
+constant uint WAVE_EQUATION_MODE = 1;
+
+// wave::boundary::Boundary
+constant uint FIXED = 0;
+constant uint REFLECTIVE = 1;
+constant uint ABSORBING = 2;
+constant uint PERIODIC = 3;
+
+// bundles::water::SolverUniform
+struct SolverSettings {
+    float speed;
+    float damping;
+    float timestep;
+    uint boundary;
+    uint spongeWidth;
+    float spongeDamping;
+    // wave::grid::Grid
+    uint width;
+    uint height;
+    float cellSize;
+};
+
+// the tile read in place of pos, which may be outside of the grid; false if nothing is there
+// (see Boundary::resolve)
+bool resolve(constant SolverSettings &settings, int2 pos, thread uint2 &tile) {
+    int2 size = int2(settings.width, settings.height);
+    if (pos.x >= 0 && pos.y >= 0 && pos.x < size.x && pos.y < size.y) {
+        tile = uint2(pos);
+        return true;
     }
-    if (waveBoolChart.g == 1) {
-        g = waves[1].amplitude * sin(float(tickPos.g) * (M_PI_F / float(waves[1].wavelength)));
+    if (settings.boundary == FIXED) {
+        return false;
     }
-    if (waveBoolChart.b == 1) {
-        b = waves[2].amplitude * sin(float(tickPos.b) * (M_PI_F / float(waves[2].wavelength)));
+    if (settings.boundary == PERIODIC) {
+        tile = uint2(((pos % size) + size) % size);
+    } else {
+        tile = uint2(clamp(pos, int2(0), size - 1));
     }
-    if (waveBoolChart.a == 1) {
-        a = waves[3].amplitude * sin(float(tickPos.a) * (M_PI_F / float(waves[3].wavelength)));
+    return true;
+}
+
+// 0 outside of the sponge layer, rising to 1 on the edge (see Boundary::sponge)
+float sponge(constant SolverSettings &settings, uint2 pos) {
+    if (settings.boundary != ABSORBING || settings.spongeWidth == 0) {
+        return 0.0;
     }
+    uint distance = min(min(pos.x, pos.y), min(settings.width - 1 - pos.x, settings.height - 1 - pos.y));
+    if (distance >= settings.spongeWidth) {
+        return 0.0;
+    }
+    float depth = float(settings.spongeWidth - distance) / float(settings.spongeWidth);
+    return depth * depth;
+}
 
-    float amplitude = r + g + b + a;
-    float4 finalPosition = float4(pos.x, amplitude, pos.y, 1.0);
+// the heights buffers hold one width x height layer of tiles per wave slot, slot after slot,
+// each one row by row
+// each tile stores whether its wave is there in the first byte, and the wave's tick - how long
+// has it been here - in the second; until the wave is there, the second byte counts how long it
+// has waited to move onto the tile (see Bathymetry::lag_at)
+// [index] [tick]; ticks should NEVER overflow into indices (wavelength < 256)
+// the first byte also holds the turned bits (see TURNED); anything else in it is UNDEFINED BEHAVIOR
+uint tileIndex(constant SolverSettings &settings, uint slot, uint2 pos) {
+    return (slot * settings.height + pos.y) * settings.width + pos.x;
+}
 
-    WaterFragment out;
-    out.position = projection * view * finalPosition;
-    out.textureCoords = ((finalPosition.xz / 100.0) + 1.0) / 2.0;
-    return out;
-};
+// the high byte is the activation bit, and whether the wave has turned around off a reflective
+// edge going up and down, then left and right (see wave::sim::TURNED)
+constant ushort ACTIVATED = 256;
+constant ushort TURNED_VERTICAL = 512;
+constant ushort TURNED_HORIZONTAL = 1024;
+constant ushort TURNED = TURNED_VERTICAL | TURNED_HORIZONTAL;
 
-fragment float4 water_frag(WaterFragment in [[ stage_in ]],
-                           texture2d<float, access::sample> waterTexture [[ texture(0) ]],
-                           sampler sam [[ sampler(0) ]])
-{
-    return waterTexture.sample(sam, in.textureCoords);
+bool isActivated(ushort tile) {
+    return (tile & ACTIVATED) != 0;
+}
+
+// the directions the wave on a tile moves in (see wave::sim::get_directions)
+uchar tileDirections(uchar directions, ushort tile) {
+    if ((tile & TURNED_VERTICAL) != 0) {
+        directions = (directions & ~3) | ((directions & 1) << 1) | ((directions & 2) >> 1);
+    }
+    if ((tile & TURNED_HORIZONTAL) != 0) {
+        directions = (directions & ~12) | ((directions & 4) << 1) | ((directions & 8) >> 1);
+    }
+    return directions;
+}
+
+// how a wave moving in directions is turned on pos, having come onto it turned the way turned
+// says: back into the grid on reflective edges it would move off of (see wave::sim::turn)
+ushort turn(constant SolverSettings &settings, uchar directions, ushort turned, uint2 pos) {
+    if (settings.boundary != REFLECTIVE) {
+        return turned;
+    }
+    // up moves to lower rows and down to higher ones
+    bool lower = (directions & 1) != 0;
+    bool higher = (directions & 2) != 0;
+    if (lower != higher && pos.y == 0) {
+        turned = (turned & ~TURNED_VERTICAL) | (lower ? TURNED_VERTICAL : 0);
+    } else if (lower != higher && pos.y == settings.height - 1) {
+        turned = (turned & ~TURNED_VERTICAL) | (higher ? TURNED_VERTICAL : 0);
+    }
+    // right moves to lower columns and left to higher ones
+    lower = (directions & 8) != 0;
+    higher = (directions & 4) != 0;
+    if (lower != higher && pos.x == 0) {
+        turned = (turned & ~TURNED_HORIZONTAL) | (lower ? TURNED_HORIZONTAL : 0);
+    } else if (lower != higher && pos.x == settings.width - 1) {
+        turned = (turned & ~TURNED_HORIZONTAL) | (higher ? TURNED_HORIZONTAL : 0);
+    }
+    return turned;
+}
+
+// where a ripple's wavefront seems to come from, and the wave as it seems to move from there
+// (see wave::sim::RippleSource)
+struct RippleSource {
+    float2 from;
+    Wave wave;
+    bool mirrored;
 };
 
-// max 4 waves at once (on a given pixel); 1 for R, G, B, and A channels,
-// it is UNDEFINED BEHAVIOR to have a channel have a value other than 0 or 1 in the first byte
-// in each channel is also stored the wave's tick - how long has it been here
-// [index] [tick]; ticks should NEVER overflow into indices (wavelength < 256) and there should
-// be checks to stop ticks and toggle the boolean whenever ticks hit wavelength cap
-// propagation bitwise storage: up | 1, down | 2, left | 4, right | 8
-kernel void process_water(constant Wave *waves [[ buffer(0) ]],
-                          texture2d<ushort, access::read> heightMap [[ texture(0) ]],
-                          texture2d<ushort, access::write> newHeightMap [[ texture(1) ]],
-                          uint2 gid [[ thread_position_in_grid ]])
-{
-    ushort4 currentTile = heightMap.read(gid);
+// the ripple itself, and with reflective edges its mirror images across every edge and corner,
+// through the edge tiles (see wave::sim::ripple_sources)
+uint rippleSourceCount(constant SolverSettings &settings) {
+    return settings.boundary == REFLECTIVE ? 9 : 1;
+}
+
+RippleSource rippleSource(constant SolverSettings &settings, Ripple ripple, Wave wave, uint image) {
+    float2 from = float2(ripple.x, ripple.y);
+    float2 far = 2.0 * float2(settings.width - 1, settings.height - 1) - from;
+    uint column = image % 3;
+    uint row = image / 3;
+    RippleSource source;
+    source.from = float2(column == 0 ? from.x : (column == 1 ? -from.x : far.x),
+                         row == 0 ? from.y : (row == 1 ? -from.y : far.y));
+    source.wave = wave;
+    if (column != 0) {
+        source.wave.heading = 180.0 - source.wave.heading;
+    }
+    if (row != 0) {
+        source.wave.heading = -source.wave.heading;
+    }
+    source.mirrored = column != 0 || row != 0;
+    return source;
+}
 
-    ushort4 above = heightMap.read(uint2(gid.x, gid.y + 1));
+// how far a ripple's wavefront has to go to reach pos: out from where it was placed, or forwards
+// along its heading for plane waves (see wave::sim::travelled)
+float rippleTravelled(RippleSource source, uint2 pos) {
+    float2 offset = float2(pos) - source.from;
+    if (source.wave.kind == PLANE) {
+        float heading = source.wave.heading * (M_PI_F / 180.0);
+        return dot(offset, -float2(cos(heading), sin(heading)));
+    }
+    return length(offset);
+}
 
-    if (((currentTile.r >> 8) & 255) != 1 && ((above.r >> 8) & 255) == 1 && (waves[0].directions & 1) == 1) {
-        currentTile.r = 256;
+// a mirrored wavefront crosses the grid mirrored before it bounces back onto it
+float mirror(RippleSource source, float at, float size) {
+    if (source.mirrored && at < 0.0) {
+        return -at;
     }
-    if (((currentTile.g >> 8) & 255) != 1 && ((above.g >> 8) & 255) == 1 && (waves[1].directions & 1) == 1) {
-        currentTile.g = 256;
+    if (source.mirrored && at >= size) {
+        return 2.0 * (size - 1.0) - at;
     }
-    if (((currentTile.b >> 8) & 255) != 1 && ((above.b >> 8) & 255) == 1 && (waves[2].directions & 1) == 1) {
-        currentTile.b = 256;
+    return at;
+}
+
+// how a ripple's wavefront reaches pos from one of its sources, in a straight line, or along the
+// heading from the line a plane wave was placed on: how far it is, and in delay the lags of the
+// tiles it crosses added up; false if land is in the way (see wave::sim::ripple_path)
+bool ripplePath(constant SolverSettings &settings,
+                device const uchar *obstacles,
+                device const uchar *lags,
+                RippleSource source,
+                uint2 pos,
+                thread float &travelled,
+                thread uint &delay)
+{
+    travelled = rippleTravelled(source, pos);
+    delay = 0;
+    if (obstacles[pos.y * settings.width + pos.x] != 0) {
+        return false;
     }
-    if (((currentTile.a >> 8) & 255) != 1 && ((above.a >> 8) & 255) == 1 && (waves[3].directions & 1) == 1) {
-        currentTile.a = 256;
+    float2 start = source.from;
+    if (source.wave.kind == PLANE) {
+        float heading = source.wave.heading * (M_PI_F / 180.0);
+        start = float2(pos) + float2(cos(heading), sin(heading)) * travelled;
     }
+    float2 offset = float2(pos) - start;
+    uint steps = uint(ceil(max(abs(offset.x), abs(offset.y))));
+    // every tile on the way, not counting the one it starts on
+    for (uint step = 1; step <= steps; step++) {
+        float2 tile = floor(start + offset * (float(step) / float(steps)) + 0.5);
+        tile = float2(mirror(source, tile.x, float(settings.width)),
+                      mirror(source, tile.y, float(settings.height)));
+        // plane waves can come from off the grid, where nothing is in the way
+        if (any(tile < 0.0) || tile.x >= float(settings.width) || tile.y >= float(settings.height)) {
+            continue;
+        }
+        uint index = uint(tile.y) * settings.width + uint(tile.x);
+        if (obstacles[index] != 0) {
+            return false;
+        }
+        delay += lags[index];
+    }
+    return true;
+}
 
-    ushort4 below = heightMap.read(uint2(gid.x, gid.y - 1));
-    
-    if (((currentTile.r >> 8) & 255) != 1 && ((below.r >> 8) & 255) == 1 && (waves[0].directions & 2) == 2) {
-        currentTile.r = 256;
+// how high the water is on the vertex at cell, x along its row and y the row; land sticks out
+// at LAND_HEIGHT (see wave::mesh::surface_heights)
+float surfaceHeight(device const Wave *waves,
+                    uint slotCount,
+                    device const ushort *heightMap,
+                    uint solverMode,
+                    device const float *field,
+                    constant SolverSettings &settings,
+                    device const uchar *obstacles,
+                    device const Ripple *ripples,
+                    uint rippleCount,
+                    device const uint *travel,
+                    device const uchar *lags,
+                    uint2 cell)
+{
+    // vertex rows go the other way from tile rows (see wave::grid::Grid::tile_of_vertex)
+    uint2 texturedPos = uint2(cell.x, settings.height - 1 - cell.y);
+    uint index = texturedPos.y * settings.width + texturedPos.x;
+
+    // blocked tiles are land, and stick out of the water
+    if (obstacles[index] != 0) {
+        return LAND_HEIGHT;
     }
-    if (((currentTile.g >> 8) & 255) != 1 && ((below.g >> 8) & 255) == 1 && (waves[1].directions & 2) == 2) {
-        currentTile.g = 256;
+    if (solverMode == WAVE_EQUATION_MODE) {
+        return field[index];
+    }
+
+    float amplitude = 0;
+    for (uint slot = 0; slot < slotCount; slot++) {
+        ushort encodedInfo = heightMap[tileIndex(settings, slot, texturedPos)];
+        if (isActivated(encodedInfo)) {
+            ushort tickPos = encodedInfo & 255;
+            uint tileTravel = travel[tileIndex(settings, slot, texturedPos)];
+            amplitude += waves[slot].amplitude * sin(float(tickPos) * (M_PI_F / float(waves[slot].wavelength)))
+                * attenuation(waves[slot], tileTravel & 65535, float(tileTravel >> 16));
+        }
     }
-    if (((currentTile.b >> 8) & 255) != 1 && ((below.b >> 8) & 255) == 1 && (waves[2].directions & 2) == 2) {
-        currentTile.b = 256;
+    // radial waves spread out in circles and plane waves move forwards along their heading, one
+    // tile per update, slower over shallows (see wave::sim::ripple_height)
+    for (uint i = 0; i < rippleCount; i++) {
+        Ripple ripple = ripples[i];
+        // a ripple in a slot the waves don't reach yet has no wave to read
+        if (ripple.slot >= slotCount) {
+            continue;
+        }
+        Wave wave = waves[ripple.slot];
+        for (uint image = 0; image < rippleSourceCount(settings); image++) {
+            RippleSource source = rippleSource(settings, ripple, wave, image);
+            float travelled;
+            uint delay;
+            if (!ripplePath(settings, obstacles, lags, source, texturedPos, travelled, delay)) {
+                continue;
+            }
+            float tick = float(ripple.age) - travelled - float(delay);
+            if (tick >= 0.0 && tick <= float(wave.wavelength)) {
+                amplitude += wave.amplitude * sin(tick * (M_PI_F / float(wave.wavelength)))
+                    * attenuation(wave, ripple.age, abs(travelled));
+            }
+        }
     }
-    if (((currentTile.a >> 8) & 255) != 1 && ((below.a >> 8) & 255) == 1 && (waves[3].directions & 2) == 2) {
-        currentTile.a = 256;
+    // waves fade out across an absorbing sponge
+    return amplitude * (1.0 - sponge(settings, texturedPos));
+}
+
+// colormap::Mapping; the water is textured and lit with NONE
+struct Mapping {
+    uint quantity;
+    uint slot;
+    float low;
+    float high;
+};
+
+// colormap::Quantity
+constant uint NONE = 0;
+constant uint ACTIVATION = 2;
+constant uint TICK = 3;
+
+// how far through its half sine the wave in slot is on the vertex at cell, from its layer or
+// the furthest of its ripples; -1 where it isn't on it (see Solver::slot_tick)
+float slotTick(device const Wave *waves,
+               uint slotCount,
+               device const ushort *heightMap,
+               constant SolverSettings &settings,
+               device const uchar *obstacles,
+               device const uchar *lags,
+               device const Ripple *ripples,
+               uint rippleCount,
+               uint slot,
+               uint2 cell)
+{
+    uint2 texturedPos = uint2(cell.x, settings.height - 1 - cell.y);
+    if (slot >= slotCount) {
+        return -1.0;
     }
-    
-    ushort4 left = heightMap.read(uint2(gid.x - 1, gid.y));
-    
-    if (((currentTile.r >> 8) & 255) != 1 && ((left.r >> 8) & 255) == 1 && (waves[0].directions & 4) == 4) {
-        currentTile.r = 256;
+    float furthest = -1.0;
+    ushort encodedInfo = heightMap[tileIndex(settings, slot, texturedPos)];
+    if (isActivated(encodedInfo)) {
+        furthest = float(encodedInfo & 255);
     }
-    if (((currentTile.g >> 8) & 255) != 1 && ((left.g >> 8) & 255) == 1 && (waves[1].directions & 4) == 4) {
-        currentTile.g = 256;
+    Wave wave = waves[slot];
+    for (uint i = 0; i < rippleCount; i++) {
+        if (ripples[i].slot != slot) {
+            continue;
+        }
+        for (uint image = 0; image < rippleSourceCount(settings); image++) {
+            RippleSource source = rippleSource(settings, ripples[i], wave, image);
+            float travelled;
+            uint delay;
+            if (!ripplePath(settings, obstacles, lags, source, texturedPos, travelled, delay)) {
+                continue;
+            }
+            float tick = float(ripples[i].age) - travelled - float(delay);
+            if (tick >= 0.0 && tick <= float(wave.wavelength)) {
+                furthest = max(furthest, tick);
+            }
+        }
     }
-    if (((currentTile.b >> 8) & 255) != 1 && ((left.b >> 8) & 255) == 1 && (waves[2].directions & 4) == 4) {
-        currentTile.b = 256;
+    return furthest;
+}
+
+// the height of the water on every vertex, row by row like the vertices, worked out once a frame
+// for water_vert to take its own and its neighbours' from (see wave::mesh::surface_heights)
+kernel void surface_heights(device const Wave *waves [[ buffer(0) ]],
+                            constant uint &slotCount [[ buffer(1) ]],
+                            device const ushort *heightMap [[ buffer(2) ]],
+                            constant uint &solverMode [[ buffer(3) ]],
+                            device const float *field [[ buffer(4) ]],
+                            constant SolverSettings &settings [[ buffer(5) ]],
+                            device const uchar *obstacles [[ buffer(6) ]],
+                            device const Ripple *ripples [[ buffer(7) ]],
+                            constant uint &rippleCount [[ buffer(8) ]],
+                            device const uint *travel [[ buffer(9) ]],
+                            device const uchar *lags [[ buffer(10) ]],
+                            device float *surface [[ buffer(11) ]],
+                            uint2 gid [[ thread_position_in_grid ]])
+{
+    // the grid needn't be a whole number of threadgroups
+    if (gid.x >= settings.width || gid.y >= settings.height) {
+        return;
     }
-    if (((currentTile.a >> 8) & 255) != 1 && ((left.a >> 8) & 255) == 1 && (waves[3].directions & 4) == 4) {
-        currentTile.a = 256;
+    surface[gid.y * settings.width + gid.x] = surfaceHeight(waves, slotCount, heightMap, solverMode, field, settings, obstacles, ripples, rippleCount, travel, lags, gid);
+}
+
+vertex WaterFragment water_vert(device WaterVertex *vertexArray [[ buffer(0) ]],
+                                constant float4x4 &projection [[ buffer(1) ]],
+                                constant float4x4 &view [[ buffer(2) ]],
+                                device const Wave *waves [[ buffer(3) ]],
+                                constant uint &slotCount [[ buffer(4) ]],
+                                device const ushort *heightMap [[ buffer(5) ]],
+                                constant uint &solverMode [[ buffer(6) ]],
+                                device const float *surface [[ buffer(7) ]],
+                                constant SolverSettings &settings [[ buffer(8) ]],
+                                device const uchar *obstacles [[ buffer(9) ]],
+                                device const Ripple *ripples [[ buffer(10) ]],
+                                constant uint &rippleCount [[ buffer(11) ]],
+                                constant Mapping &mapping [[ buffer(12) ]],
+                                device const uchar *lags [[ buffer(13) ]],
+                                uint vid [[ vertex_id ]])
+{
+    float2 pos = vertexArray[vid].position;
+    uint2 cell = uint2(vid % settings.width, vid / settings.width);
+    uint index = (settings.height - 1 - cell.y) * settings.width + cell.x;
+
+    // surface_heights has already worked out every vertex's height
+    #define HEIGHT_AT(v) surface[(v).y * settings.width + (v).x]
+    float amplitude = HEIGHT_AT(cell);
+
+    // the slopes between the neighbours, or the vertex itself past the edges
+    // (see wave::mesh::vertex_normals)
+    uint2 low = uint2(max(cell, uint2(1)) - 1);
+    uint2 high = min(cell + 1, uint2(settings.width, settings.height) - 1);
+    float dx = (HEIGHT_AT(uint2(high.x, cell.y)) - HEIGHT_AT(uint2(low.x, cell.y)))
+        / (float(max(high.x - low.x, 1u)) * settings.cellSize);
+    float dz = (HEIGHT_AT(uint2(cell.x, high.y)) - HEIGHT_AT(uint2(cell.x, low.y)))
+        / (float(max(high.y - low.y, 1u)) * settings.cellSize);
+    #undef HEIGHT_AT
+
+    float4 finalPosition = float4(pos.x, amplitude, pos.y, 1.0);
+
+    WaterFragment out;
+    out.position = projection * view * finalPosition;
+    out.textureCoords = ((finalPosition.xz / (float2(settings.width, settings.height) * settings.cellSize)) + 1.0) / 2.0;
+    out.land = obstacles[index] != 0 ? 1.0 : 0.0;
+    float value = amplitude;
+    if (mapping.quantity == ACTIVATION || mapping.quantity == TICK) {
+        // the wave equation has no slots, and no waves in them
+        float tick = -1.0;
+        if (solverMode != WAVE_EQUATION_MODE) {
+            tick = slotTick(waves, slotCount, heightMap, settings, obstacles, lags, ripples, rippleCount, mapping.slot, cell);
+        }
+        value = mapping.quantity == ACTIVATION ? float(tick >= 0.0) : max(tick, 0.0);
     }
-    
-    ushort4 right = heightMap.read(uint2(gid.x + 1, gid.y));
+    out.value = (value - mapping.low) / (mapping.high - mapping.low);
+    out.normal = normalize(float3(-dx, 1.0, -dz));
+    out.world = finalPosition.xyz;
+    return out;
+};
 
-    if (((currentTile.r >> 8) & 255) != 1 && ((right.r >> 8) & 255) == 1 && (waves[0].directions & 8) == 8) {
-        currentTile.r = 256;
+// a render::Surface from the CPU, already displaced, with a texture coordinate, whether it's
+// land, a normal and a value for the colormap for every vertex
+vertex WaterFragment surface_vert(device const packed_float3 *positions [[ buffer(0) ]],
+                                  constant float4x4 &projection [[ buffer(1) ]],
+                                  constant float4x4 &view [[ buffer(2) ]],
+                                  device const float2 *textureCoords [[ buffer(3) ]],
+                                  device const uchar *land [[ buffer(4) ]],
+                                  device const packed_float3 *normals [[ buffer(5) ]],
+                                  device const float *values [[ buffer(6) ]],
+                                  constant Mapping &mapping [[ buffer(7) ]],
+                                  uint vid [[ vertex_id ]])
+{
+    WaterFragment out;
+    out.position = projection * view * float4(positions[vid], 1.0);
+    out.textureCoords = textureCoords[vid];
+    out.land = float(land[vid]);
+    out.value = (values[vid] - mapping.low) / (mapping.high - mapping.low);
+    out.normal = normals[vid];
+    out.world = positions[vid];
+    return out;
+};
+
+// render::Light, with where the eye is
+struct Light {
+    // normalised, towards the light
+    float4 direction;
+    float4 colour;
+    float4 eye;
+    float ambient;
+    float diffuse;
+    float specular;
+    float shininess;
+    float fresnel;
+};
+
+// the texture lit by the light, with a glint towards the eye and the light's colour seen edge
+// on; render::Light::shade is the reference for this. With a mapping, the value through the
+// colormap instead, a strip from low to high (see colormap::Colormap::strip)
+fragment float4 water_frag(WaterFragment in [[ stage_in ]],
+                           constant Light &light [[ buffer(0) ]],
+                           constant Mapping &mapping [[ buffer(1) ]],
+                           texture2d<float, access::sample> waterTexture [[ texture(0) ]],
+                           texture2d<float, access::sample> colormap [[ texture(1) ]],
+                           sampler sam [[ sampler(0) ]])
+{
+    if (in.land >= 0.5) {
+        return LAND_COLOUR;
     }
-    if (((currentTile.g >> 8) & 255) != 1 && ((right.g >> 8) & 255) == 1 && (waves[1].directions & 8) == 8) {
-        currentTile.g = 256;
+    if (mapping.quantity != NONE) {
+        return colormap.sample(sam, float2(clamp(in.value, 0.0, 1.0), 0.5));
     }
-    if (((currentTile.b >> 8) & 255) != 1 && ((right.b >> 8) & 255) == 1 && (waves[2].directions & 8) == 8) {
-        currentTile.b = 256;
+    float4 colour = waterTexture.sample(sam, in.textureCoords);
+    float3 n = normalize(in.normal);
+    float3 l = light.direction.xyz;
+    float3 v = normalize(light.eye.xyz - in.world);
+    float lambert = max(dot(n, l), 0.0);
+    float fresnel = light.fresnel * pow(1.0 - max(dot(n, v), 0.0), 5.0);
+    float specular = 0.0;
+    if (lambert > 0.0) {
+        specular = light.specular * pow(max(dot(n, normalize(l + v)), 0.0), light.shininess);
     }
-    if (((currentTile.a >> 8) & 255) != 1 && ((right.a >> 8) & 255) == 1 && (waves[3].directions & 8) == 8) {
-        currentTile.a = 256;
+    float3 lit = colour.rgb * (light.ambient + light.diffuse * lambert) * light.colour.rgb;
+    lit = mix(lit, light.colour.rgb, fresnel) + light.colour.rgb * specular;
+    return float4(lit, colour.a);
+};
+
+// waves only come in from past the edges if they're periodic; reflective ones turn them around
+// on the edge tiles instead (see turn)
+// the neighbour and its travel are written to neighbour and neighbourTravel when it is there
+bool readNeighbour(constant SolverSettings &settings, device const ushort *heightMap, device const uint *travel, uint slot, int2 pos, thread ushort &neighbour, thread uint &neighbourTravel) {
+    uint2 tile;
+    bool inside = pos.x >= 0 && pos.y >= 0 && pos.x < int(settings.width) && pos.y < int(settings.height);
+    if (!inside && settings.boundary != PERIODIC) {
+        return false;
     }
+    if (!resolve(settings, pos, tile)) {
+        return false;
+    }
+    neighbour = heightMap[tileIndex(settings, slot, tile)];
+    neighbourTravel = travel[tileIndex(settings, slot, tile)];
+    return true;
+}
 
-    if (((currentTile.r >> 8) & 255) == 1) {
-        if ((currentTile.r & 255) < waves[0].wavelength) {
-            currentTile.r += 1;
-        } else {
-            currentTile.r = 0;
-        }
+// reads every tile from heightMap and writes it, updated, to newHeightMap; wave::sim::Simulation
+// is the reference for this kernel
+// propagation bitwise storage: up | 1, down | 2, left | 4, right | 8
+kernel void process_water(device const Wave *waves [[ buffer(0) ]],
+                          constant uint &slotCount [[ buffer(1) ]],
+                          device const ushort *heightMap [[ buffer(2) ]],
+                          device ushort *newHeightMap [[ buffer(3) ]],
+                          constant SolverSettings &settings [[ buffer(4) ]],
+                          device const uchar *obstacles [[ buffer(5) ]],
+                          device const uchar *lags [[ buffer(6) ]],
+                          device const uint *travel [[ buffer(7) ]],
+                          device uint *newTravel [[ buffer(8) ]],
+                          uint2 gid [[ thread_position_in_grid ]])
+{
+    // the grid needn't be a whole number of threadgroups
+    if (gid.x >= settings.width || gid.y >= settings.height) {
+        return;
     }
-    if (((currentTile.g >> 8) & 255) == 1) {
-        if ((currentTile.g & 255) < waves[1].wavelength) {
-            currentTile.g += 1;
-        } else {
-            currentTile.g = 0;
+    int2 pos = int2(gid);
+    uchar lag = lags[gid.y * settings.width + gid.x];
+    // blocked tiles never hold a wave
+    bool blocked = obstacles[gid.y * settings.width + gid.x] != 0;
+    for (uint slot = 0; slot < slotCount; slot++) {
+        Wave wave = waves[slot];
+        ushort currentTile = heightMap[tileIndex(settings, slot, gid)];
+        uint tileTravel = travel[tileIndex(settings, slot, gid)];
+
+        // a wave reaching the tile waits on it for the lag before it moves on, and keeps its
+        // neighbour's travel, one tile further, and the way it has turned
+        if (!isActivated(currentTile)) {
+            if (currentTile == 0) {
+                const int2 offsets[4] = { int2(0, 1), int2(0, -1), int2(-1, 0), int2(1, 0) };
+                for (uint i = 0; i < 4; i++) {
+                    ushort neighbour;
+                    uint neighbourTravel;
+                    uchar direction = 1 << i;
+                    if (readNeighbour(settings, heightMap, travel, slot, pos + offsets[i], neighbour, neighbourTravel)
+                        && isActivated(neighbour)
+                        && (tileDirections(wave.directions, neighbour) & direction) != 0) {
+                        currentTile = 1 | turn(settings, wave.directions, neighbour & TURNED, gid);
+                        tileTravel = packTravel(neighbourTravel & 65535, (neighbourTravel >> 16) + 1);
+                        break;
+                    }
+                }
+            } else {
+                currentTile += 1;
+                tileTravel = packTravel((tileTravel & 65535) + 1, tileTravel >> 16);
+            }
+            if ((currentTile & 255) > lag) {
+                currentTile = ACTIVATED | (currentTile & TURNED);
+            }
         }
-    }
-    if (((currentTile.b >> 8) & 255) == 1) {
-        if ((currentTile.b & 255) < waves[2].wavelength) {
-            currentTile.b += 1;
-        } else {
-            currentTile.b = 0;
+
+        if (isActivated(currentTile)) {
+            uint age = (tileTravel & 65535) + 1;
+            if ((currentTile & 255) < wave.wavelength && !isExpired(wave, age)) {
+                currentTile += 1;
+                tileTravel = packTravel(age, tileTravel >> 16);
+            } else {
+                currentTile = 0;
+                tileTravel = 0;
+            }
+        }
+
+        if (blocked) {
+            currentTile = 0;
+            tileTravel = 0;
         }
+
+        newTravel[tileIndex(settings, slot, gid)] = tileTravel;
+        newHeightMap[tileIndex(settings, slot, gid)] = currentTile;
+    }
+};
+
+struct ForcedTile {
+    uint x;
+    uint y;
+    float height;
+};
+
+// what is past the edges depends on the boundary; with a fixed one, it is flat
+float fieldAt(constant SolverSettings &settings, device const float *field, int2 pos) {
+    uint2 tile;
+    if (!resolve(settings, pos, tile)) {
+        return 0.0;
     }
-    if (((currentTile.a >> 8) & 255) == 1) {
-        if ((currentTile.a & 255) < waves[3].wavelength) {
-            currentTile.a += 1;
-        } else {
-            currentTile.a = 0;
+    return field[tile.y * settings.width + tile.x];
+}
+
+// one leapfrog update of the damped wave equation, from field and previousField into nextField;
+// wave::physics::WaveEquation is the reference for this kernel
+kernel void process_wave_equation(constant SolverSettings &settings [[ buffer(0) ]],
+                                  device const float *previousField [[ buffer(1) ]],
+                                  device const float *field [[ buffer(2) ]],
+                                  device float *nextField [[ buffer(3) ]],
+                                  device const ForcedTile *forced [[ buffer(4) ]],
+                                  constant uint &forcedCount [[ buffer(5) ]],
+                                  device const uchar *obstacles [[ buffer(6) ]],
+                                  device const float *speeds [[ buffer(7) ]],
+                                  uint2 gid [[ thread_position_in_grid ]])
+{
+    if (gid.x >= settings.width || gid.y >= settings.height) {
+        return;
+    }
+    int2 pos = int2(gid);
+    uint index = gid.y * settings.width + gid.x;
+    float courant = pow(settings.speed * speeds[index] * settings.timestep, 2.0);
+    float friction = (settings.damping + settings.spongeDamping * sponge(settings, gid)) * settings.timestep / 2.0;
+
+    float here = field[index];
+    float laplacian = fieldAt(settings, field, pos + int2(0, 1)) + fieldAt(settings, field, pos - int2(0, 1))
+        + fieldAt(settings, field, pos - int2(1, 0)) + fieldAt(settings, field, pos + int2(1, 0)) - 4.0 * here;
+    float next = (2.0 * here - (1.0 - friction) * previousField[index] + courant * laplacian)
+        / (1.0 + friction);
+
+    for (uint i = 0; i < forcedCount; i++) {
+        if (forced[i].x == gid.x && forced[i].y == gid.y) {
+            next = forced[i].height;
         }
     }
 
-    newHeightMap.write(currentTile, gid);
+    // blocked tiles stay flat, even under a driver
+    if (obstacles[index] != 0) {
+        next = 0.0;
+    }
+
+    nextField[index] = next;
 };
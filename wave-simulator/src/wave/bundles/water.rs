@@ -1,7 +1,14 @@
+use crate::wave::bathymetry::Bathymetry;
 use crate::wave::bundles::basemetal::BaseMetalBundle;
-use crate::wave::constants::VERTEX_COUNT;
+use crate::wave::grid::Grid;
+use crate::wave::obstacles::ObstacleMask;
+use crate::wave::physics::ForcedTile;
+use crate::wave::render::colormap::Quantity;
+use crate::wave::render::{Light, Shading};
+use crate::wave::sim::{Ripple, SolverSettings, Wave, WaveRegistry};
+use cgmath::{InnerSpace, Vector3};
 use cull_canyon::{
-    MTLBuffer, MTLComputePipelineState, MTLRenderPipelineColorAttachmentDescriptor,
+    MTLBuffer, MTLComputePipelineState, MTLDevice, MTLRenderPipelineColorAttachmentDescriptor,
     MTLRenderPipelineDescriptor, MTLRenderPipelineState, MTLSamplerDescriptor, MTLSamplerState,
     MTLTexture, MTLTextureDescriptor, MTLVertexDescriptor,
 };
@@ -9,68 +16,59 @@ use std::os::raw::c_void;
 
 pub struct WaterBundle {
     pub render_pipeline: MTLRenderPipelineState,
+    // draws a render::Surface uploaded from the CPU in place of the simulation
+    pub surface_pipeline: MTLRenderPipelineState,
     pub compute_pipeline: MTLComputePipelineState,
+    pub wave_equation_pipeline: MTLComputePipelineState,
+    // works out surface_heights once a frame, before water_vert reads them
+    pub heights_pipeline: MTLComputePipelineState,
     pub water_buffer: MTLBuffer,
     pub water_indices: MTLBuffer,
     pub indices_count: usize,
     pub water_surface: MTLTexture,
-    pub texture: MTLTexture,
-    pub crosshair: MTLTexture,
+    // the size of every buffer below, and of the mesh
+    pub grid: Grid,
+    // one width x height layer of u16 tiles per wave slot, slot after slot
+    // (see wave::sim); process_water reads one buffer and writes the other
+    pub heights: [MTLBuffer; 2],
+    // laid out like the heights, u32 per tile (see wave::sim::pack_travel); swapped with them
+    pub travel: [MTLBuffer; 2],
+    pub current: usize,
+    pub slots: usize,
+    // one wave per slot; slots past the registry's end hold empty waves
+    pub waves: MTLBuffer,
+    registry: WaveRegistry,
+    // the wave equation's width x height f32 heights, row by row; the previous,
+    // current and next fields rotate through these (see wave::physics)
+    pub field: [MTLBuffer; 3],
+    pub current_field: usize,
+    // a width x height u8 mask, row by row; 1 where the tile is land
+    pub obstacles: MTLBuffer,
+    // the bathymetry, row by row: u8 lags for process_water, f32 speeds for
+    // process_wave_equation
+    pub lags: MTLBuffer,
+    pub speeds: MTLBuffer,
+    // the height of the water on every vertex, f32 row by row like the vertices
+    pub surface_heights: MTLBuffer,
     pub sampler: MTLSamplerState,
 }
 
-#[repr(C)]
-#[derive(Clone, Copy)]
-pub struct Wave {
-    // x | 1 == up
-    // x | 2 == down
-    // x | 4 == left
-    // x | 8 == right
-    pub directions: u8,
-    // in vertices
-    pub wavelength: u8,
-    // amplitude of the wave; may be negative
-    // amplitude is calculated in the vertex shader
-    pub amplitude_factor: f32,
-}
-
-impl Wave {
-    pub fn empty() -> Wave {
-        Wave {
-            directions: 0,
-            wavelength: 0,
-            amplitude_factor: 0.0,
-        }
-    }
-}
-
 impl WaterBundle {
-    pub unsafe fn generate_water(bundle: &BaseMetalBundle) -> WaterBundle {
+    pub unsafe fn generate_water(bundle: &BaseMetalBundle, grid: Grid) -> WaterBundle {
         // row by row generation
-        const HL: f32 = VERTEX_COUNT as f32 / 2.0;
-        const DIMENSIONS: usize = 2;
-
-        let vertices: [f32; (2 * VERTEX_COUNT * VERTEX_COUNT) as usize] = *((0..VERTEX_COUNT)
-            .map(|z: u32| {
-                (0..VERTEX_COUNT)
-                    .map(|x: u32| [x as f32 - HL, z as f32 - HL])
-                    .collect::<Vec<[f32; DIMENSIONS]>>()
-            })
-            .collect::<Vec<Vec<[f32; DIMENSIONS]>>>()
-            .into_iter()
-            .flatten()
-            .collect::<Vec<[f32; DIMENSIONS]>>()
-            .as_ptr()
-            as *const [f32; (2 * VERTEX_COUNT * VERTEX_COUNT) as usize]);
-
-        const INDICES_COUNT: usize = (6 * (VERTEX_COUNT - 1) * (VERTEX_COUNT - 1)) as usize;
-        let mut indices: [u32; INDICES_COUNT] = [0; INDICES_COUNT];
+        let vertices = (0..grid.height)
+            .flat_map(|z| (0..grid.width).map(move |x| grid.vertex_position(x, z)))
+            .flat_map(|(x, z)| vec![x, z])
+            .collect::<Vec<f32>>();
+
+        let indices_count = (6 * (grid.width - 1) * (grid.height - 1)) as usize;
+        let mut indices = vec![0u32; indices_count];
         let mut pointer = 0;
-        (0..VERTEX_COUNT - 1).for_each(|z| {
-            (0..VERTEX_COUNT - 1).for_each(|x| {
-                let top_left = z * VERTEX_COUNT + x;
+        (0..grid.height - 1).for_each(|z| {
+            (0..grid.width - 1).for_each(|x| {
+                let top_left = z * grid.width + x;
                 let top_right = top_left + 1;
-                let bottom_left = (z + 1) * VERTEX_COUNT + x;
+                let bottom_left = (z + 1) * grid.width + x;
                 let bottom_right = bottom_left + 1;
                 indices[pointer] = top_left;
                 pointer += 1;
@@ -87,71 +85,12 @@ impl WaterBundle {
             });
         });
 
-        let render_pipeline = bundle
-            .device
-            .new_render_pipeline_state_with_descriptor({
-                let desc = MTLRenderPipelineDescriptor::new();
-                desc.get_color_attachments()
-                    .set_object_at_indexed_subscript(
-                        {
-                            let desc = MTLRenderPipelineColorAttachmentDescriptor::new();
-                            desc.set_pixel_format(80); // bgra8unorm
-                            desc
-                        },
-                        0,
-                    );
-                desc.set_vertex_function(
-                    bundle.library.new_function_with_name("water_vert").unwrap(),
-                );
-                desc.set_fragment_function(
-                    bundle.library.new_function_with_name("water_frag").unwrap(),
-                );
-                desc.set_depth_attachment_pixel_format(252); // depth 32 float
-                desc.set_vertex_descriptor(MTLVertexDescriptor::new());
-                desc
-            })
-            .unwrap();
-
-        let compute_pipeline = bundle
-            .device
-            .new_compute_pipeline_state_with_function(
-                bundle
-                    .library
-                    .new_function_with_name("process_water")
-                    .unwrap(),
-            )
-            .unwrap();
+        let render_pipeline = new_water_pipeline(bundle, "water_vert");
+        let surface_pipeline = new_water_pipeline(bundle, "surface_vert");
 
-        let texture = bundle.device.new_texture_with_descriptor({
-            let desc = MTLTextureDescriptor::new();
-            desc.set_width(VERTEX_COUNT as u64);
-            desc.set_height(VERTEX_COUNT as u64);
-            desc.set_pixel_format(113); // 113 = rgba16uint; 63 = rg16uint
-            desc.set_texture_type(2); // 2d
-            desc.set_usage(0x0001 | 0x0002); // shader read + write
-            desc
-        });
-        texture.replace_region(
-            (0, 0, VERTEX_COUNT as u64, VERTEX_COUNT as u64),
-            0,
-            [[0u16, 0, 0, 0]; VERTEX_COUNT as usize * VERTEX_COUNT as usize].as_ptr()
-                as *mut c_void,
-            VERTEX_COUNT as u64 * 8,
-        );
-
-        let crosshair = bundle.device.new_texture_with_descriptor({
-            let desc = MTLTextureDescriptor::new();
-            desc.set_width(5);
-            desc.set_height(5);
-            desc.set_pixel_format(70); // rgba8unorm
-            desc.set_texture_type(2);
-            desc
-        });
-        let decoder = png::Decoder::new(include_bytes!("crosshair.png") as &[u8]);
-        let (info, mut reader) = decoder.read_info().unwrap();
-        let mut img = vec![0; info.buffer_size()];
-        reader.next_frame(&mut img).unwrap();
-        crosshair.replace_region((0, 0, 5, 5), 0, img.as_ptr() as *mut c_void, 20);
+        let compute_pipeline = new_compute_pipeline(bundle, "process_water");
+        let wave_equation_pipeline = new_compute_pipeline(bundle, "process_wave_equation");
+        let heights_pipeline = new_compute_pipeline(bundle, "surface_heights");
 
         let surface = bundle.device.new_texture_with_descriptor({
             let desc = MTLTextureDescriptor::new();
@@ -167,9 +106,15 @@ impl WaterBundle {
         reader.next_frame(&mut img).unwrap();
         surface.replace_region((0, 0, 384, 384), 0, img.as_ptr() as *mut c_void, 3 * 512);
 
+        let flat = Bathymetry::flat(grid.width, grid.height);
+        let len = grid.get_len();
+
         WaterBundle {
             render_pipeline,
+            surface_pipeline,
             compute_pipeline,
+            wave_equation_pipeline,
+            heights_pipeline,
             water_buffer: bundle.device.new_buffer_with_bytes(
                 vertices.as_ptr() as *const c_void,
                 vertices.len() as u64 * 4,
@@ -180,13 +125,484 @@ impl WaterBundle {
                 indices.len() as u64 * 4,
                 0,
             ),
-            indices_count: INDICES_COUNT,
+            indices_count,
             water_surface: surface,
-            texture,
-            crosshair,
+            grid,
+            heights: [
+                new_height_buffer(&bundle.device, len, 0, None),
+                new_height_buffer(&bundle.device, len, 0, None),
+            ],
+            travel: [
+                new_travel_buffer(&bundle.device, len, 0, None),
+                new_travel_buffer(&bundle.device, len, 0, None),
+            ],
+            current: 0,
+            slots: 0,
+            waves: new_wave_buffer(&bundle.device, &[], 0),
+            registry: WaveRegistry::new(),
+            field: [
+                new_field_buffer(&bundle.device, len),
+                new_field_buffer(&bundle.device, len),
+                new_field_buffer(&bundle.device, len),
+            ],
+            current_field: 0,
+            obstacles: new_obstacle_buffer(
+                &bundle.device,
+                &ObstacleMask::open(grid.width, grid.height),
+            ),
+            lags: new_lag_buffer(&bundle.device, &flat),
+            speeds: new_speed_buffer(&bundle.device, &flat),
+            surface_heights: new_field_buffer(&bundle.device, len),
             sampler: bundle
                 .device
                 .new_sampler_state_with_descriptor(MTLSamplerDescriptor::new()),
         }
     }
+
+    pub fn get_heights(&self) -> MTLBuffer {
+        self.heights[self.current].clone()
+    }
+
+    pub fn get_next_heights(&self) -> MTLBuffer {
+        self.heights[1 - self.current].clone()
+    }
+
+    pub fn get_travel_buffer(&self) -> MTLBuffer {
+        self.travel[self.current].clone()
+    }
+
+    pub fn get_next_travel_buffer(&self) -> MTLBuffer {
+        self.travel[1 - self.current].clone()
+    }
+
+    /// Makes the buffers `process_water` last wrote into the current ones.
+    pub fn swap(&mut self) {
+        self.current = 1 - self.current;
+    }
+
+    pub fn get_previous_field(&self) -> MTLBuffer {
+        self.field[(self.current_field + 2) % 3].clone()
+    }
+
+    pub fn get_field(&self) -> MTLBuffer {
+        self.field[self.current_field].clone()
+    }
+
+    pub fn get_next_field(&self) -> MTLBuffer {
+        self.field[(self.current_field + 1) % 3].clone()
+    }
+
+    /// Makes the field `process_wave_equation` last wrote into the current one.
+    pub fn rotate_fields(&mut self) {
+        self.current_field = (self.current_field + 1) % 3;
+    }
+
+    pub unsafe fn get_field_height(&self, x: u64, y: u64) -> Option<f32> {
+        if !self.contains(x, y) {
+            return None;
+        }
+        let contents = self.get_field().get_contents() as *const f32;
+        Some(*contents.add((y * self.grid.width as u64 + x) as usize))
+    }
+
+    /// The automaton's tiles and travel, every slot's layer one after the other.
+    pub unsafe fn read_layers(&self) -> (Vec<u16>, Vec<u32>) {
+        let len = self.slots * self.grid.get_len();
+        let tiles = self.get_heights().get_contents() as *const u16;
+        let travel = self.get_travel_buffer().get_contents() as *const u32;
+        (
+            std::slice::from_raw_parts(tiles, len).to_vec(),
+            std::slice::from_raw_parts(travel, len).to_vec(),
+        )
+    }
+
+    /// Replaces the automaton's tiles and travel; slots past the end of `tiles` are cleared.
+    pub unsafe fn write_layers(&self, tiles: &[u16], travel: &[u32]) {
+        let len = self.slots * self.grid.get_len();
+        let heights =
+            std::slice::from_raw_parts_mut(self.get_heights().get_contents() as *mut u16, len);
+        let travel_buffer = std::slice::from_raw_parts_mut(
+            self.get_travel_buffer().get_contents() as *mut u32,
+            len,
+        );
+        heights
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, tile)| *tile = tiles.get(i).copied().unwrap_or(0));
+        travel_buffer
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, t)| *t = travel.get(i).copied().unwrap_or(0));
+    }
+
+    /// The wave equation's current and previous fields.
+    pub unsafe fn read_fields(&self) -> (Vec<f32>, Vec<f32>) {
+        let len = self.grid.get_len();
+        let read = |field: MTLBuffer| {
+            std::slice::from_raw_parts(field.get_contents() as *const f32, len).to_vec()
+        };
+        (read(self.get_field()), read(self.get_previous_field()))
+    }
+
+    pub unsafe fn write_fields(&self, heights: &[f32], previous: &[f32]) {
+        let len = self.grid.get_len();
+        let write = |field: MTLBuffer, values: &[f32]| {
+            std::slice::from_raw_parts_mut(field.get_contents() as *mut f32, len)
+                .copy_from_slice(values)
+        };
+        write(self.get_field(), heights);
+        write(self.get_previous_field(), previous);
+    }
+
+    /// Uploads a mask the size of the grid, and removes the water already on blocked tiles.
+    pub unsafe fn set_obstacles(&mut self, device: &MTLDevice, mask: &ObstacleMask) {
+        (0..self.grid.height as u64).for_each(|y| {
+            (0..self.grid.width as u64)
+                .filter(|x| mask.is_blocked(*x as u32, y as u32))
+                .for_each(|x| {
+                    (0..self.slots).for_each(|slot| self.set_tile(slot, x, y, 0));
+                    self.field.iter().for_each(|field| {
+                        let contents = field.get_contents() as *mut f32;
+                        *contents.add((y * self.grid.width as u64 + x) as usize) = 0.0;
+                    });
+                });
+        });
+        self.obstacles = new_obstacle_buffer(device, mask);
+    }
+
+    /// Uploads a depth map the size of the grid.
+    pub unsafe fn set_bathymetry(&mut self, device: &MTLDevice, bathymetry: &Bathymetry) {
+        self.lags = new_lag_buffer(device, bathymetry);
+        self.speeds = new_speed_buffer(device, bathymetry);
+    }
+
+    pub unsafe fn is_blocked(&self, x: u64, y: u64) -> bool {
+        if !self.contains(x, y) {
+            return false;
+        }
+        let contents = self.obstacles.get_contents() as *const u8;
+        *contents.add((y * self.grid.width as u64 + x) as usize) != 0
+    }
+
+    /// Adds empty height layers up to `slots`, keeping the ones there.
+    pub unsafe fn grow_slots(&mut self, device: &MTLDevice, slots: usize) {
+        if slots > self.slots {
+            let (len, current) = (self.grid.get_len(), self.get_heights());
+            self.heights = [
+                new_height_buffer(device, len, slots, Some((&current, self.slots))),
+                new_height_buffer(device, len, slots, Some((&current, self.slots))),
+            ];
+            let current = self.get_travel_buffer();
+            self.travel = [
+                new_travel_buffer(device, len, slots, Some((&current, self.slots))),
+                new_travel_buffer(device, len, slots, Some((&current, self.slots))),
+            ];
+            self.current = 0;
+            self.slots = slots;
+            // the kernels read a wave for every layer
+            self.waves = new_wave_buffer(device, self.registry.as_slice(), slots);
+        }
+    }
+
+    /// Uploads the registry, adding height layers for any new slots.
+    pub unsafe fn set_waves(&mut self, device: &MTLDevice, waves: &WaveRegistry) {
+        self.registry = waves.clone();
+        self.grow_slots(device, waves.len());
+        self.waves = new_wave_buffer(device, waves.as_slice(), self.slots);
+    }
+
+    pub fn contains(&self, x: u64, y: u64) -> bool {
+        x < self.grid.width as u64 && y < self.grid.height as u64
+    }
+
+    fn layer_index(&self, slot: usize, x: u64, y: u64) -> usize {
+        slot * self.grid.get_len() + (y * self.grid.width as u64 + x) as usize
+    }
+
+    pub unsafe fn get_tile(&self, slot: usize, x: u64, y: u64) -> Option<u16> {
+        if slot >= self.slots || !self.contains(x, y) {
+            return None;
+        }
+        let contents = self.get_heights().get_contents() as *const u16;
+        Some(*contents.add(self.layer_index(slot, x, y)))
+    }
+
+    pub unsafe fn get_travel(&self, slot: usize, x: u64, y: u64) -> Option<u32> {
+        if slot >= self.slots || !self.contains(x, y) {
+            return None;
+        }
+        let contents = self.get_travel_buffer().get_contents() as *const u32;
+        Some(*contents.add(self.layer_index(slot, x, y)))
+    }
+
+    // a tile that is set starts its travel over
+    pub unsafe fn set_tile(&self, slot: usize, x: u64, y: u64, value: u16) {
+        if slot < self.slots && self.contains(x, y) {
+            let contents = self.get_heights().get_contents() as *mut u16;
+            *contents.add(self.layer_index(slot, x, y)) = value;
+            let contents = self.get_travel_buffer().get_contents() as *mut u32;
+            *contents.add(self.layer_index(slot, x, y)) = 0;
+        }
+    }
+
+    /// Empties a tile in every slot's layer and flattens the wave equation's field on it, like
+    /// `Solver::clear` does.
+    pub unsafe fn clear_tile(&self, x: u64, y: u64) {
+        if !self.contains(x, y) {
+            return;
+        }
+        (0..self.slots).for_each(|slot| self.set_tile(slot, x, y, 0));
+        let index = (y * self.grid.width as u64 + x) as usize;
+        *(self.get_field().get_contents() as *mut f32).add(index) = 0.0;
+        *(self.get_previous_field().get_contents() as *mut f32).add(index) = 0.0;
+    }
+}
+
+// Metal won't make empty buffers, so there is always room for at least one slot of `layer_len`
+// tiles
+unsafe fn new_height_buffer(
+    device: &MTLDevice,
+    layer_len: usize,
+    slots: usize,
+    old: Option<(&MTLBuffer, usize)>,
+) -> MTLBuffer {
+    let len = slots.max(1) * layer_len;
+    let mut tiles = vec![0u16; len];
+    if let Some((old, old_slots)) = old {
+        let old_len = old_slots * layer_len;
+        std::ptr::copy_nonoverlapping(
+            old.get_contents() as *const u16,
+            tiles.as_mut_ptr(),
+            old_len,
+        );
+    }
+    device.new_buffer_with_bytes(tiles.as_ptr() as *const c_void, len as u64 * 2, 0)
+}
+
+unsafe fn new_travel_buffer(
+    device: &MTLDevice,
+    layer_len: usize,
+    slots: usize,
+    old: Option<(&MTLBuffer, usize)>,
+) -> MTLBuffer {
+    let len = slots.max(1) * layer_len;
+    let mut travel = vec![0u32; len];
+    if let Some((old, old_slots)) = old {
+        let old_len = old_slots * layer_len;
+        std::ptr::copy_nonoverlapping(
+            old.get_contents() as *const u32,
+            travel.as_mut_ptr(),
+            old_len,
+        );
+    }
+    device.new_buffer_with_bytes(travel.as_ptr() as *const c_void, len as u64 * 4, 0)
+}
+
+unsafe fn new_obstacle_buffer(device: &MTLDevice, mask: &ObstacleMask) -> MTLBuffer {
+    device.new_buffer_with_bytes(
+        mask.tiles.as_ptr() as *const c_void,
+        mask.tiles.len() as u64,
+        0,
+    )
+}
+
+unsafe fn new_lag_buffer(device: &MTLDevice, bathymetry: &Bathymetry) -> MTLBuffer {
+    let lags = bathymetry.get_lags();
+    device.new_buffer_with_bytes(lags.as_ptr() as *const c_void, lags.len() as u64, 0)
+}
+
+unsafe fn new_speed_buffer(device: &MTLDevice, bathymetry: &Bathymetry) -> MTLBuffer {
+    device.new_buffer_with_bytes(
+        bathymetry.speeds.as_ptr() as *const c_void,
+        bathymetry.speeds.len() as u64 * 4,
+        0,
+    )
+}
+
+unsafe fn new_compute_pipeline(bundle: &BaseMetalBundle, kernel: &str) -> MTLComputePipelineState {
+    bundle
+        .device
+        .new_compute_pipeline_state_with_function(
+            bundle.library.new_function_with_name(kernel).unwrap(),
+        )
+        .unwrap()
+}
+
+/// A pipeline drawing the water's triangles with `vertex_function` and `water_frag`.
+unsafe fn new_water_pipeline(
+    bundle: &BaseMetalBundle,
+    vertex_function: &str,
+) -> MTLRenderPipelineState {
+    bundle
+        .device
+        .new_render_pipeline_state_with_descriptor({
+            let desc = MTLRenderPipelineDescriptor::new();
+            desc.get_color_attachments()
+                .set_object_at_indexed_subscript(
+                    {
+                        let desc = MTLRenderPipelineColorAttachmentDescriptor::new();
+                        desc.set_pixel_format(80); // bgra8unorm
+                        desc
+                    },
+                    0,
+                );
+            desc.set_vertex_function(
+                bundle
+                    .library
+                    .new_function_with_name(vertex_function)
+                    .unwrap(),
+            );
+            desc.set_fragment_function(
+                bundle.library.new_function_with_name("water_frag").unwrap(),
+            );
+            desc.set_depth_attachment_pixel_format(252); // depth 32 float
+            desc.set_vertex_descriptor(MTLVertexDescriptor::new());
+            desc
+        })
+        .unwrap()
+}
+
+pub unsafe fn new_ripple_buffer(device: &MTLDevice, ripples: &[Ripple]) -> MTLBuffer {
+    let mut ripples = ripples.to_vec();
+    if ripples.is_empty() {
+        ripples.push(Ripple {
+            x: 0,
+            y: 0,
+            slot: 0,
+            age: 0,
+        });
+    }
+    device.new_buffer_with_bytes(
+        ripples.as_ptr() as *const c_void,
+        (ripples.len() * std::mem::size_of::<Ripple>()) as u64,
+        0,
+    )
+}
+
+unsafe fn new_wave_buffer(device: &MTLDevice, waves: &[Wave], slots: usize) -> MTLBuffer {
+    let mut waves = waves.to_vec();
+    waves.resize(waves.len().max(slots).max(1), Wave::default());
+    device.new_buffer_with_bytes(
+        waves.as_ptr() as *const c_void,
+        (waves.len() * std::mem::size_of::<Wave>()) as u64,
+        0,
+    )
+}
+
+unsafe fn new_field_buffer(device: &MTLDevice, len: usize) -> MTLBuffer {
+    let field = vec![0f32; len];
+    device.new_buffer_with_bytes(field.as_ptr() as *const c_void, field.len() as u64 * 4, 0)
+}
+
+/// The solver settings and the grid, as the kernels and `water_vert` read them.
+#[repr(C)]
+pub struct SolverUniform {
+    pub speed: f32,
+    pub damping: f32,
+    pub timestep: f32,
+    pub boundary: u32,
+    pub sponge_width: u32,
+    pub sponge_damping: f32,
+    pub width: u32,
+    pub height: u32,
+    pub cell_size: f32,
+}
+
+impl SolverUniform {
+    pub fn new(settings: &SolverSettings, grid: &Grid) -> SolverUniform {
+        SolverUniform {
+            speed: settings.speed,
+            damping: settings.damping,
+            timestep: settings.timestep,
+            boundary: settings.boundary as u32,
+            sponge_width: settings.sponge_width,
+            sponge_damping: settings.sponge_damping,
+            width: grid.width,
+            height: grid.height,
+            cell_size: grid.cell_size,
+        }
+    }
+}
+
+/// A `render::Light` and where the eye is, as `water_frag` reads them.
+#[repr(C)]
+pub struct LightUniform {
+    // normalised; the w of all three is unused
+    pub direction: [f32; 4],
+    pub colour: [f32; 4],
+    pub eye: [f32; 4],
+    pub ambient: f32,
+    pub diffuse: f32,
+    pub specular: f32,
+    pub shininess: f32,
+    pub fresnel: f32,
+    // Metal rounds the struct up to 16 bytes
+    _padding: [f32; 3],
+}
+
+impl LightUniform {
+    pub fn new(light: &Light, eye: Vector3<f32>) -> LightUniform {
+        let direction = light.direction.normalize();
+        LightUniform {
+            direction: direction.extend(0.0).into(),
+            colour: [light.colour[0], light.colour[1], light.colour[2], 1.0],
+            eye: eye.extend(1.0).into(),
+            ambient: light.ambient,
+            diffuse: light.diffuse,
+            specular: light.specular,
+            shininess: light.shininess,
+            fresnel: light.fresnel,
+            _padding: [0.0; 3],
+        }
+    }
+}
+
+/// How the water shaders colour the water: quantity 0 is textured and lit, and the others are
+/// a `colormap::Quantity` over its range.
+#[repr(C)]
+pub struct MappingUniform {
+    pub quantity: u32,
+    pub slot: u32,
+    pub low: f32,
+    pub high: f32,
+}
+
+impl MappingUniform {
+    pub fn new(shading: &Shading) -> MappingUniform {
+        match shading {
+            Shading::Water(_) => MappingUniform {
+                quantity: 0,
+                slot: 0,
+                low: 0.0,
+                high: 1.0,
+            },
+            Shading::Mapped(mapping) => MappingUniform {
+                quantity: match mapping.quantity {
+                    Quantity::Height => 1,
+                    Quantity::Activation => 2,
+                    Quantity::Tick => 3,
+                },
+                slot: mapping.slot as u32,
+                low: mapping.range.0,
+                high: mapping.range.1,
+            },
+        }
+    }
+}
+
+// Metal won't make empty buffers either; the kernel is told how many tiles are real
+pub unsafe fn new_forced_buffer(device: &MTLDevice, forced: &[ForcedTile]) -> MTLBuffer {
+    let mut forced = forced.to_vec();
+    if forced.is_empty() {
+        forced.push(ForcedTile {
+            x: 0,
+            y: 0,
+            height: 0.0,
+        });
+    }
+    device.new_buffer_with_bytes(
+        forced.as_ptr() as *const c_void,
+        (forced.len() * std::mem::size_of::<ForcedTile>()) as u64,
+        0,
+    )
 }
@@ -1,4 +1,4 @@
-use crate::wave::constants::{WINDOW_HEIGHT, WINDOW_WIDTH};
+use crate::wave::config::Config;
 use winit::dpi::PhysicalSize;
 use winit::event_loop::EventLoop;
 use winit::platform::macos::WindowBuilderExtMacOS;
@@ -9,12 +9,12 @@ pub struct WindowBundle {
 }
 
 impl WindowBundle {
-    pub fn new(event_loop: &EventLoop<()>) -> WindowBundle {
+    pub fn new(event_loop: &EventLoop<()>, config: &Config) -> WindowBundle {
         WindowBundle {
             window: WindowBuilder::new()
                 .with_titlebar_transparent(true)
                 .with_title("Wave Simulator")
-                .with_inner_size(PhysicalSize::new(WINDOW_WIDTH, WINDOW_HEIGHT))
+                .with_inner_size(PhysicalSize::new(config.window_width, config.window_height))
                 .with_resizable(true)
                 .build(&event_loop)
                 .unwrap(),
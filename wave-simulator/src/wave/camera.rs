@@ -1,5 +1,10 @@
-use cgmath::{Matrix4, Rad, SquareMatrix, Vector3};
+use crate::wave::grid::Grid;
+use cgmath::{Matrix4, Rad, SquareMatrix, Transform, Vector3};
+use serde::{Deserialize, Serialize};
 
+/// Where the camera is, in world units, and where it looks, in radians.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(default)]
 pub struct Camera {
     pub x: f32,
     pub y: f32,
@@ -10,6 +15,18 @@ pub struct Camera {
 }
 
 impl Camera {
+    /// Above the front edge of the grid, looking down over all of it.
+    pub fn overview(grid: &Grid) -> Camera {
+        let extent = grid.width.max(grid.height) as f32 * grid.cell_size;
+        let (height, distance) = (extent * 0.6, extent * 0.9);
+        Camera {
+            y: height,
+            z: distance,
+            pitch: (height / distance).atan(),
+            ..Camera::default()
+        }
+    }
+
     pub fn get_matrix(&self) -> cgmath::Matrix4<f32> {
         let mat: Matrix4<f32> = Matrix4::identity();
         let mat = mat
@@ -39,13 +56,41 @@ impl Camera {
                 },
                 Rad(self.roll),
             );
-        let mat = mat
-            * Matrix4::from_translation(Vector3 {
-                x: -self.x,
-                y: -self.y,
-                z: -self.z,
-            });
-
-        mat
+        mat * Matrix4::from_translation(Vector3 {
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        })
     }
+    /// Which way the pixel at `cursor` of a window `size` pixels big is from the camera, in the
+    /// world. The ray isn't normalised; it's one unit long along the way the camera looks.
+    pub fn ray_through(
+        &self,
+        projection: Matrix4<f32>,
+        cursor: (f64, f64),
+        size: (u32, u32),
+    ) -> Vector3<f32> {
+        let (x, y) = to_clip(cursor, size);
+        let eye = projection
+            .invert()
+            .unwrap()
+            .transform_vector(Vector3 { x, y, z: -1.0 });
+        self.get_matrix()
+            .invert()
+            .unwrap()
+            .transform_vector(Vector3 {
+                x: eye.x,
+                y: eye.y,
+                z: -1.0,
+            })
+    }
+}
+
+/// Where a pixel of a window `size` pixels big is in clip space, from -1 to 1 left to right and
+/// bottom to top.
+pub fn to_clip(cursor: (f64, f64), size: (u32, u32)) -> (f32, f32) {
+    (
+        (cursor.0 * 2.0 / size.0 as f64 - 1.0) as f32,
+        -(cursor.1 * 2.0 / size.1 as f64 - 1.0) as f32,
+    )
 }
@@ -0,0 +1,339 @@
+//! The command lines of the `wave-simulator` and `wave-sim-headless` binaries.
+//!
+//! Both take the same options and run without a window the same way; only what goes between
+//! them differs. Everything here works without a window, so headless runs work on any platform.
+
+use crate::wave::camera::Camera;
+use crate::wave::config::Config;
+use crate::wave::export::{Exporter, Format};
+use crate::wave::grid::Grid;
+use crate::wave::mesh::Mesh;
+use crate::wave::recording::Recording;
+use crate::wave::render::software::SoftwareRenderer;
+use crate::wave::scene::Scene;
+use crate::wave::sim::{Solver, SolverMode, WaveRegistry};
+use crate::wave::snapshot::Snapshot;
+use std::path::{Path, PathBuf};
+
+const OPTIONS: &str = "[--config <file>] [--set <key>=<value>]... \
+                       [--solver automaton | wave-equation] [--grid <width>x<height>] \
+                       [--cell-size <size>] [--replay <recording.toml>] [--out <dir>] \
+                       [--every <n>] [--at <tick>,<tick>,...] [--format txt,png,npy] \
+                       [--range <height>] [--mesh <file.obj | .ply | .glb>] \
+                       [--render <file.png>] [--snapshot <file>]";
+
+pub fn usage() -> String {
+    format!(
+        "usage: wave-simulator [scene.toml [recording.toml]] [--paused] [--headless <ticks>] {}",
+        OPTIONS
+    )
+}
+
+pub fn headless_usage() -> String {
+    format!(
+        "usage: wave-sim-headless <scene.toml | snapshot> <ticks> {}",
+        OPTIONS
+    )
+}
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Options {
+    // a scene, or for headless runs a snapshot to resume
+    pub scene: Option<PathBuf>,
+    // a recording to replay on the scene
+    pub replay: Option<PathBuf>,
+    pub config: Option<PathBuf>,
+    // `key=value` pairs to go over the config file, in order
+    pub overrides: Vec<String>,
+    // these go over the scene's
+    pub solver: Option<SolverMode>,
+    pub grid: Option<(u32, u32)>,
+    pub cell_size: Option<f32>,
+    pub paused: bool,
+    // runs this many ticks without a window
+    pub headless: Option<u64>,
+    // where exports go; the working directory if not given
+    pub out: Option<PathBuf>,
+    // the ticks a headless run exports; the last one if neither is given
+    pub every: Option<u64>,
+    pub at: Vec<u64>,
+    // txt if none are given
+    pub formats: Vec<Format>,
+    pub range: Option<f32>,
+    // the water surface, a picture of it, and a snapshot, after the last tick
+    pub mesh: Option<PathBuf>,
+    pub render: Option<PathBuf>,
+    pub snapshot: Option<PathBuf>,
+}
+
+impl Options {
+    /// `wave-simulator`'s command line: up to a scene and a recording, and the options.
+    pub fn parse(args: &[String]) -> Result<Options, String> {
+        let (mut options, positional) = Options::parse_options(args)?;
+        if positional.len() > 2 || (positional.len() == 2 && options.replay.is_some()) {
+            return Err(usage());
+        }
+        let mut positional = positional.into_iter().map(PathBuf::from);
+        options.scene = positional.next();
+        if let Some(replay) = positional.next() {
+            options.replay = Some(replay);
+        }
+        Ok(options)
+    }
+
+    /// `wave-sim-headless`'s command line: a scene or snapshot and a tick count, and the
+    /// options.
+    pub fn parse_headless(args: &[String]) -> Result<Options, String> {
+        let (mut options, positional) = Options::parse_options(args)?;
+        if positional.len() != 2 || options.headless.is_some() {
+            return Err(headless_usage());
+        }
+        options.scene = Some(PathBuf::from(&positional[0]));
+        options.headless = Some(
+            positional[1]
+                .parse::<u64>()
+                .map_err(|_| format!("Invalid tick count {}.", positional[1]))?,
+        );
+        Ok(options)
+    }
+
+    // the options, and what's between them in order
+    fn parse_options(args: &[String]) -> Result<(Options, Vec<String>), String> {
+        let mut options = Options::default();
+        let mut positional = vec![];
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| {
+                args.next()
+                    .ok_or_else(|| format!("{} needs a value.", name))
+                    .map(|s| s.to_string())
+            };
+            match arg.as_str() {
+                "--config" => options.config = Some(PathBuf::from(value("--config")?)),
+                "--set" => options.overrides.push(value("--set")?),
+                "--out" => options.out = Some(PathBuf::from(value("--out")?)),
+                "--replay" => options.replay = Some(PathBuf::from(value("--replay")?)),
+                "--mesh" => options.mesh = Some(PathBuf::from(value("--mesh")?)),
+                "--render" => options.render = Some(PathBuf::from(value("--render")?)),
+                "--snapshot" => options.snapshot = Some(PathBuf::from(value("--snapshot")?)),
+                "--format" => options.formats = Format::parse_list(&value("--format")?)?,
+                "--paused" => options.paused = true,
+                "--solver" => {
+                    options.solver = Some(match value("--solver")?.as_str() {
+                        "automaton" => SolverMode::Automaton,
+                        "wave-equation" => SolverMode::WaveEquation,
+                        mode => {
+                            return Err(format!(
+                                "Unknown solver {}; use automaton or wave-equation.",
+                                mode
+                            ))
+                        }
+                    })
+                }
+                "--grid" => {
+                    let size = value("--grid")?;
+                    let parsed = size
+                        .split_once('x')
+                        .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)));
+                    match parsed {
+                        Some(grid) => options.grid = Some(grid),
+                        None => return Err(format!("Invalid grid {}; give it like 160x90.", size)),
+                    }
+                }
+                "--cell-size" => {
+                    let size = value("--cell-size")?;
+                    options.cell_size = Some(
+                        size.parse::<f32>()
+                            .map_err(|_| format!("Invalid cell size {}.", size))?,
+                    );
+                }
+                "--headless" => {
+                    let n = value("--headless")?;
+                    options.headless = Some(
+                        n.parse::<u64>()
+                            .map_err(|_| format!("Invalid tick count {}.", n))?,
+                    );
+                }
+                "--range" => {
+                    let r = value("--range")?;
+                    options.range = match r.parse::<f32>() {
+                        Ok(r) if r > 0.0 && r.is_finite() => Some(r),
+                        _ => return Err(format!("Invalid range {}.", r)),
+                    }
+                }
+                "--every" => {
+                    let n = value("--every")?;
+                    options.every = match n.parse::<u64>() {
+                        Ok(0) | Err(_) => return Err(format!("Invalid interval {}.", n)),
+                        Ok(n) => Some(n),
+                    }
+                }
+                "--at" => {
+                    for tick in value("--at")?.split(',') {
+                        options.at.push(
+                            tick.trim()
+                                .parse::<u64>()
+                                .map_err(|_| format!("Invalid tick {}.", tick))?,
+                        );
+                    }
+                }
+                _ if arg.starts_with("--") => return Err(format!("Unknown option {}.", arg)),
+                _ => positional.push(arg.clone()),
+            }
+        }
+        Ok((options, positional))
+    }
+
+    /// The config file, if any, with every `--set` over it.
+    pub fn get_config(&self) -> Result<Config, String> {
+        let mut config = match &self.config {
+            Some(path) => Config::load(path)?,
+            None => Config::default(),
+        };
+        for assignment in self.overrides.iter() {
+            config.set(assignment)?;
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// The scene to start with, with the solver and grid given on the command line over its
+    /// own. Without a scene file, it's an empty one.
+    pub fn get_scene(&self) -> Result<Scene, String> {
+        let (mut scene, dir) = match &self.scene {
+            Some(path) => {
+                let src = std::fs::read_to_string(path)
+                    .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+                let dir = path.parent().unwrap_or_else(|| Path::new(""));
+                (Scene::from_toml(&src)?, dir.to_path_buf())
+            }
+            None => (Scene::default(), PathBuf::new()),
+        };
+        if let Some(mode) = self.solver {
+            scene.solver.mode = mode;
+        }
+        if let Some((width, height)) = self.grid {
+            scene.grid.width = width;
+            scene.grid.height = height;
+        }
+        if let Some(cell_size) = self.cell_size {
+            scene.grid.cell_size = cell_size;
+        }
+        // the obstacles and depth map must fit the grid the scene ends up with
+        scene.validate()?;
+        scene.load_obstacles(&dir)?;
+        scene.load_bathymetry(&dir)?;
+        Ok(scene)
+    }
+
+    pub fn get_replay(&self) -> Result<Option<Recording>, String> {
+        self.replay.as_ref().map(Recording::load).transpose()
+    }
+
+    pub fn get_out(&self) -> PathBuf {
+        self.out.clone().unwrap_or_else(|| PathBuf::from("."))
+    }
+
+    pub fn get_formats(&self) -> Vec<Format> {
+        match self.formats.is_empty() {
+            true => vec![Format::Txt],
+            false => self.formats.clone(),
+        }
+    }
+
+    /// Whether a headless run ending on `last` exports `tick`; with neither `--every` nor
+    /// `--at`, only the last tick is.
+    pub fn is_selected(&self, tick: u64, last: u64) -> bool {
+        if self.every.is_none() && self.at.is_empty() {
+            return tick == last;
+        }
+        self.every.is_some_and(|n| tick.is_multiple_of(n)) || self.at.contains(&tick)
+    }
+
+    /// Runs the scene, or carries on from the snapshot, for `ticks` ticks, replaying the
+    /// recording if there is one. The selected ticks are exported to the output directory,
+    /// and the mesh, render and snapshot written after the last one; returns every file
+    /// written, in order.
+    pub fn run_headless(&self, ticks: u64) -> Result<Vec<PathBuf>, String> {
+        let Start {
+            mut sim,
+            mut waves,
+            first,
+            grid,
+            camera,
+        } = self.start()?;
+        let last = first + ticks;
+        let recording = self.get_replay()?.unwrap_or_default();
+        let exporter = Exporter {
+            dir: self.get_out(),
+            formats: self.get_formats(),
+            range: self.range,
+        };
+
+        let mut written = vec![];
+        for tick in first..=last {
+            recording.apply(tick, sim.as_mut(), &mut waves)?;
+            if self.is_selected(tick, last) {
+                written.extend(exporter.export(tick, sim.as_ref(), &waves)?);
+            }
+            if tick < last {
+                sim.step(&waves);
+            }
+        }
+
+        if let Some(path) = &self.mesh {
+            Mesh::water(sim.as_ref(), &waves, grid.cell_size).save(path)?;
+            written.push(path.clone());
+        }
+        if let Some(path) = &self.render {
+            // seen like the app's window would, from the scene's camera if it has one
+            let config = self.get_config()?;
+            let size = (config.window_width, config.window_height);
+            SoftwareRenderer::render(sim.as_ref(), &waves, &grid, camera, size).save(path)?;
+            written.push(path.clone());
+        }
+        if let Some(path) = &self.snapshot {
+            sim.snapshot(&waves, last).save(path)?;
+            written.push(path.clone());
+        }
+        Ok(written)
+    }
+
+    // a snapshot carries on from its tick, and the scene's options don't go over it; a scene
+    // starts at 0
+    fn start(&self) -> Result<Start, String> {
+        if let Some(path) = &self.scene {
+            let bytes = std::fs::read(path)
+                .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+            if Snapshot::is_snapshot(&bytes) {
+                let snapshot = Snapshot::from_bytes(&bytes)
+                    .map_err(|e| format!("{}: {}", path.display(), e))?;
+                return Ok(Start {
+                    sim: snapshot.restore(),
+                    waves: snapshot.waves.clone(),
+                    first: snapshot.time,
+                    grid: Grid::new(snapshot.width, snapshot.height),
+                    camera: None,
+                });
+            }
+        }
+        let scene = self.get_scene()?;
+        Ok(Start {
+            sim: scene.instantiate(),
+            waves: scene.get_waves(),
+            first: 0,
+            grid: scene.grid,
+            camera: scene.camera,
+        })
+    }
+}
+
+// what a headless run starts from. Snapshots don't keep the cell size, or a camera.
+struct Start {
+    sim: Box<dyn Solver>,
+    waves: WaveRegistry,
+    first: u64,
+    grid: Grid,
+    camera: Option<Camera>,
+}
@@ -0,0 +1,246 @@
+//! The commands typed into the app's console, like `wave 2 amp=3 len=20 dir=up,right` or
+//! `place 2`. Parsing doesn't look at the app, so a command can still fail when it's run.
+
+use crate::wave::bathymetry::Bathymetry;
+use crate::wave::export::Format;
+use crate::wave::render::colormap::{Colormap, Mapping, Quantity};
+use crate::wave::scene::{Direction, WaveDefinition};
+use crate::wave::sim::{WaveKind, WaveRegistry};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+pub const HELP: [&str; 14] = [
+    "wave <slot> amp=<a> len=<n> [dir=up,down,left,right | dir=all | heading=<deg>]",
+    "     [decay=<d>] [falloff=<f>] [life=<updates>]   fills a wave slot",
+    "place <slot> [<x> <y>]   places a wave under the mouse, or on a tile",
+    "clear [<x> <y>]   removes the waves under the mouse, or on a tile",
+    "obstacles <file.png | clear>   dark pixels are land",
+    "depth <file.png | flat | shoal>   black is the shallowest water",
+    "scene save [file.toml]   saves over the scene loaded if no file is given",
+    "snapshot save <file> | snapshot load <file>",
+    "recording save <file.toml>",
+    "export <every> <txt,png,npy> [dir] | export stop",
+    "mesh <file.obj | .ply | .glb>",
+    "view water | view <height | activation | tick> [slot=<n>] [map=viridis | blue-red]",
+    "     [range=<low>,<high>]   colours the water by a value, in place of its texture",
+    "help",
+];
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum DepthMap {
+    Flat,
+    Shoal,
+    File(PathBuf),
+}
+
+impl DepthMap {
+    pub fn load(&self, width: u32, height: u32) -> Result<Bathymetry, String> {
+        match self {
+            DepthMap::Flat => Ok(Bathymetry::flat(width, height)),
+            DepthMap::Shoal => Ok(Bathymetry::shoal(width, height)),
+            DepthMap::File(path) => Bathymetry::load(path, width, height),
+        }
+    }
+}
+
+// what `view` colours the water by; anything left out is picked to suit the quantity, and the
+// slot is the one left clicks place
+#[derive(Clone, PartialEq, Debug)]
+pub struct View {
+    pub quantity: Quantity,
+    pub slot: Option<usize>,
+    pub colormap: Option<Colormap>,
+    pub range: Option<(f32, f32)>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Command {
+    Wave(WaveDefinition),
+    // on the tile under the crosshair if none is given
+    Place {
+        slot: usize,
+        tile: Option<(u32, u32)>,
+    },
+    // under the crosshair too if no tile is given
+    Clear(Option<(u32, u32)>),
+    // no file clears every obstacle
+    Obstacles(Option<PathBuf>),
+    Depth(DepthMap),
+    SaveScene(Option<PathBuf>),
+    SaveSnapshot(PathBuf),
+    LoadSnapshot(PathBuf),
+    SaveRecording(PathBuf),
+    Export {
+        every: u64,
+        formats: Vec<Format>,
+        dir: Option<PathBuf>,
+    },
+    StopExport,
+    Mesh(PathBuf),
+    // the textured water with None
+    View(Option<View>),
+    Help,
+}
+
+fn parse_number<T: std::str::FromStr>(what: &str, value: &str) -> Result<T, String> {
+    value
+        .parse::<T>()
+        .map_err(|_| format!("Invalid {} {}.", what, value))
+}
+
+fn parse_wave(args: &[&str]) -> Result<WaveDefinition, String> {
+    let (slot, settings) = match args.split_first() {
+        Some((slot, settings)) => (parse_number("wave slot", slot)?, settings),
+        None => return Err("Give the wave a slot, like \"wave 0 amp=1 len=8\".".to_string()),
+    };
+    let mut wave = WaveDefinition {
+        slot,
+        amplitude: f32::NAN,
+        wavelength: 0,
+        directions: vec![],
+        kind: WaveKind::Directional,
+        heading: 0.0,
+        decay: 0.0,
+        falloff: 0.0,
+        lifetime: 0,
+    };
+    let mut heading = None;
+    for setting in settings.iter() {
+        let (key, value) = setting
+            .split_once('=')
+            .ok_or_else(|| format!("{} isn't a key=value pair.", setting))?;
+        match key {
+            "amp" => wave.amplitude = parse_number("amplitude", value)?,
+            "len" => wave.wavelength = parse_number("wavelength", value)?,
+            "heading" => heading = Some(parse_number::<f32>("heading", value)?),
+            "decay" => wave.decay = parse_number("decay", value)?,
+            "falloff" => wave.falloff = parse_number("falloff", value)?,
+            "life" => wave.lifetime = parse_number("lifetime", value)?,
+            "dir" if value == "all" => wave.kind = WaveKind::Radial,
+            "dir" => {
+                for direction in value.split(',') {
+                    wave.directions.push(match direction {
+                        "up" => Direction::Up,
+                        "down" => Direction::Down,
+                        "left" => Direction::Left,
+                        "right" => Direction::Right,
+                        _ => return Err(format!("Unknown direction {}.", direction)),
+                    });
+                }
+            }
+            _ => return Err(format!("Unknown wave setting {}.", key)),
+        }
+    }
+    if wave.amplitude.is_nan() || wave.wavelength == 0 {
+        return Err("A wave needs an amplitude and a wavelength, like amp=1 len=8.".to_string());
+    }
+    if let Some(heading) = heading {
+        if wave.kind == WaveKind::Radial || !wave.directions.is_empty() {
+            return Err("A wave can't have both directions and a heading.".to_string());
+        }
+        wave.kind = WaveKind::Plane;
+        wave.heading = heading;
+    }
+    wave.validate()?;
+    Ok(wave)
+}
+
+impl View {
+    /// The mapping to colour the water with, of `slot` unless the view has one.
+    pub fn to_mapping(&self, slot: usize, waves: &WaveRegistry) -> Mapping {
+        let mut mapping = Mapping::new(self.quantity, self.slot.unwrap_or(slot), waves);
+        mapping.colormap = self.colormap.unwrap_or(mapping.colormap);
+        mapping.range = self.range.unwrap_or(mapping.range);
+        mapping
+    }
+}
+
+fn parse_view(quantity: &str, settings: &[&str]) -> Result<View, String> {
+    let mut view = View {
+        quantity: Quantity::parse(quantity)?,
+        slot: None,
+        colormap: None,
+        range: None,
+    };
+    for setting in settings.iter() {
+        let (key, value) = setting
+            .split_once('=')
+            .ok_or_else(|| format!("{} isn't a key=value pair.", setting))?;
+        match key {
+            "slot" => view.slot = Some(parse_number("wave slot", value)?),
+            "map" => view.colormap = Some(Colormap::parse(value)?),
+            "range" => {
+                let (low, high) = value
+                    .split_once(',')
+                    .ok_or_else(|| format!("Invalid range {}; it's <low>,<high>.", value))?;
+                let range: (f32, f32) = (parse_number("range", low)?, parse_number("range", high)?);
+                if !(range.0 < range.1 && range.0.is_finite() && range.1.is_finite()) {
+                    return Err(format!("Invalid range {}; low < high.", value));
+                }
+                view.range = Some(range);
+            }
+            _ => return Err(format!("Unknown view setting {}.", key)),
+        }
+    }
+    Ok(view)
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Command, String> {
+        let words = line.split_whitespace().collect::<Vec<&str>>();
+        let path = |word: &str| PathBuf::from(word);
+        match words[..] {
+            [] => Err("Type a command, or \"help\".".to_string()),
+            ["help"] => Ok(Command::Help),
+            ["wave", ref args @ ..] => parse_wave(args).map(Command::Wave),
+            ["place", slot] => Ok(Command::Place {
+                slot: parse_number("wave slot", slot)?,
+                tile: None,
+            }),
+            ["place", slot, x, y] => Ok(Command::Place {
+                slot: parse_number("wave slot", slot)?,
+                tile: Some((parse_number("tile", x)?, parse_number("tile", y)?)),
+            }),
+            ["clear"] => Ok(Command::Clear(None)),
+            ["clear", x, y] => Ok(Command::Clear(Some((
+                parse_number("tile", x)?,
+                parse_number("tile", y)?,
+            )))),
+            ["obstacles", "clear"] => Ok(Command::Obstacles(None)),
+            ["obstacles", file] => Ok(Command::Obstacles(Some(path(file)))),
+            ["depth", "flat"] => Ok(Command::Depth(DepthMap::Flat)),
+            ["depth", "shoal"] => Ok(Command::Depth(DepthMap::Shoal)),
+            ["depth", file] => Ok(Command::Depth(DepthMap::File(path(file)))),
+            ["scene", "save"] => Ok(Command::SaveScene(None)),
+            ["scene", "save", file] => Ok(Command::SaveScene(Some(path(file)))),
+            ["snapshot", "save", file] => Ok(Command::SaveSnapshot(path(file))),
+            ["snapshot", "load", file] => Ok(Command::LoadSnapshot(path(file))),
+            ["recording", "save", file] => Ok(Command::SaveRecording(path(file))),
+            ["export", "stop"] => Ok(Command::StopExport),
+            ["export", every, formats, ref dir @ ..] if dir.len() <= 1 => {
+                let every = match parse_number("interval", every)? {
+                    0 => return Err("Invalid interval 0.".to_string()),
+                    every => every,
+                };
+                Ok(Command::Export {
+                    every,
+                    formats: Format::parse_list(formats)?,
+                    dir: dir.first().map(|dir| path(dir)),
+                })
+            }
+            ["mesh", file] => Ok(Command::Mesh(path(file))),
+            ["view", "water"] => Ok(Command::View(None)),
+            ["view", quantity, ref settings @ ..] => {
+                parse_view(quantity, settings).map(|view| Command::View(Some(view)))
+            }
+            [name, ..] => match HELP
+                .iter()
+                .find(|usage| usage.split(' ').next() == Some(name))
+            {
+                Some(usage) => Err(format!("Usage: {}", usage)),
+                None => Err(format!("Unknown command {}; try \"help\".", name)),
+            },
+        }
+    }
+}
@@ -0,0 +1,178 @@
+//! Settings for the windowed app, read from a TOML file at startup and overridden on the
+//! command line with `--set key=value`. Anything left out keeps its default.
+
+use crate::wave::render::Light;
+use cgmath::Deg;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub window_width: u32,
+    pub window_height: u32,
+    pub fps: f32,
+    pub vsync: bool,
+    // degrees
+    pub fov: f32,
+    pub near_plane: f32,
+    pub far_plane: f32,
+    // world units per frame
+    pub camera_speed: f32,
+    // the water updates once every this many frames; the lower the more frequent
+    pub frames_per_update: u64,
+    // how far along the camera's ray to look for the water, and in how many steps
+    pub raycast_distance: u64,
+    pub raycast_steps: u64,
+    // how close a point on the ray has to be to the water to hit it
+    pub raycast_tolerance: f32,
+    // draws the water's triangles as lines; `R` toggles it
+    pub wireframe: bool,
+    // the sun on the water: towards it, its colour, and how it shades (see render::Light)
+    pub light_direction: [f32; 3],
+    pub light_colour: [f32; 3],
+    pub ambient: f32,
+    pub diffuse: f32,
+    pub specular: f32,
+    pub shininess: f32,
+    pub fresnel: f32,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            window_width: 1280,
+            window_height: 720,
+            fps: 60.0,
+            vsync: true,
+            fov: 70.0,
+            near_plane: 0.1,
+            far_plane: 100.0,
+            camera_speed: 0.1,
+            frames_per_update: 5,
+            raycast_distance: 100,
+            raycast_steps: 100,
+            raycast_tolerance: 0.5,
+            wireframe: false,
+            light_direction: [0.3, 0.8, -0.5],
+            light_colour: [1.0, 0.98, 0.92],
+            ambient: 0.4,
+            diffuse: 0.6,
+            specular: 0.5,
+            shininess: 48.0,
+            fresnel: 0.5,
+        }
+    }
+}
+
+impl Config {
+    pub fn from_toml(src: &str) -> Result<Config, String> {
+        let config: Config = toml::from_str(src).map_err(|e| e.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Config, String> {
+        let path = path.as_ref();
+        let src = std::fs::read_to_string(path)
+            .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+        Config::from_toml(&src).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn to_toml(&self) -> Result<String, String> {
+        toml::to_string_pretty(self).map_err(|e| e.to_string())
+    }
+
+    /// Overrides one setting from a `key=value` pair, with the value written as in the file.
+    /// The result isn't validated, so that overrides can go through an invalid state.
+    pub fn set(&mut self, assignment: &str) -> Result<(), String> {
+        let (key, value) = match assignment.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => return Err(format!("{} isn't a key=value pair.", assignment)),
+        };
+        let mut settings = toml::Value::try_from(*self).map_err(|e| e.to_string())?;
+        let setting = settings
+            .get_mut(key)
+            .ok_or_else(|| format!("Unknown setting {}.", key))?;
+        let mut parsed: toml::value::Table = toml::from_str(&format!("value = {}", value))
+            .map_err(|_| format!("Invalid value {} for {}.", value, key))?;
+        *setting = parsed.remove("value").unwrap();
+        *self = settings
+            .try_into()
+            .map_err(|e| format!("Invalid value {} for {}: {}", value, key, e))?;
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.window_width == 0 || self.window_height == 0 {
+            return Err("The window must be at least 1x1.".to_string());
+        }
+        if !(self.fps > 0.0 && self.fps.is_finite()) {
+            return Err(format!("FPS {} is invalid; it must be above 0.", self.fps));
+        }
+        if !(self.fov > 0.0 && self.fov < 180.0) {
+            return Err(format!("FOV {} is invalid; 0 < fov < 180.", self.fov));
+        }
+        if !(self.near_plane > 0.0
+            && self.near_plane < self.far_plane
+            && self.far_plane.is_finite())
+        {
+            return Err(format!(
+                "Planes {} and {} are invalid; 0 < near_plane < far_plane.",
+                self.near_plane, self.far_plane
+            ));
+        }
+        if !(self.camera_speed >= 0.0 && self.camera_speed.is_finite()) {
+            return Err(format!(
+                "Camera speed {} is invalid; it can't be negative.",
+                self.camera_speed
+            ));
+        }
+        if self.frames_per_update == 0 {
+            return Err("frames_per_update must be at least 1.".to_string());
+        }
+        let tolerance = self.raycast_tolerance > 0.0 && self.raycast_tolerance.is_finite();
+        if self.raycast_distance == 0 || self.raycast_steps == 0 || !tolerance {
+            return Err("The raycast distance, steps and tolerance must be above 0.".to_string());
+        }
+        let direction = self.light_direction;
+        if !direction.iter().all(|d| d.is_finite()) || direction == [0.0; 3] {
+            return Err(format!("Light direction {:?} is invalid.", direction));
+        }
+        let terms = [
+            self.ambient,
+            self.diffuse,
+            self.specular,
+            self.fresnel,
+            self.light_colour[0],
+            self.light_colour[1],
+            self.light_colour[2],
+        ];
+        if !terms.iter().all(|t| *t >= 0.0 && t.is_finite()) {
+            return Err("The light's colour and terms can't be negative.".to_string());
+        }
+        if !(self.shininess > 0.0 && self.shininess.is_finite()) || self.fresnel > 1.0 {
+            return Err(format!(
+                "Shininess {} and fresnel {} are invalid; shininess > 0, fresnel <= 1.",
+                self.shininess, self.fresnel
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn light(&self) -> Light {
+        Light {
+            direction: self.light_direction.into(),
+            colour: self.light_colour,
+            ambient: self.ambient,
+            diffuse: self.diffuse,
+            specular: self.specular,
+            shininess: self.shininess,
+            fresnel: self.fresnel,
+        }
+    }
+
+    pub fn projection_matrix(&self, aspect_ratio: f32) -> cgmath::Matrix4<f32> {
+        cgmath::perspective(Deg(self.fov), aspect_ratio, self.near_plane, self.far_plane)
+    }
+}
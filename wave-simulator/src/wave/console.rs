@@ -0,0 +1,168 @@
+//! The text console drawn over the app: what's typed into it, what it printed, and the commands
+//! given before. It draws itself into a plain RGBA image, so none of it needs a window.
+
+use crate::wave::font::{is_lit, GLYPH_HEIGHT, GLYPH_WIDTH};
+
+// the size of one character on screen, with the space around it, in pixels
+pub const CELL_WIDTH: usize = GLYPH_WIDTH + 1;
+pub const CELL_HEIGHT: usize = GLYPH_HEIGHT + 2;
+// lines printed before these are forgotten
+const LOG_LENGTH: usize = 200;
+
+const BACKGROUND: [u8; 4] = [0, 0, 0, 160];
+const TEXT: [u8; 4] = [255, 255, 255, 255];
+const ERROR: [u8; 4] = [255, 96, 96, 255];
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Line {
+    pub text: String,
+    pub error: bool,
+}
+
+#[derive(Default)]
+pub struct Console {
+    pub open: bool,
+    pub input: String,
+    pub log: Vec<Line>,
+    pub history: Vec<String>,
+    // how many commands back from the last one, while going through the history
+    browsing: Option<usize>,
+    // a key that opens the console types its character right after; that one is dropped
+    swallow: bool,
+}
+
+impl Console {
+    /// Opens the console with `input` already typed. `by_character` says the key that opened
+    /// it types a character, which isn't to end up in the input.
+    pub fn open(&mut self, input: &str, by_character: bool) {
+        self.open = true;
+        self.input = input.to_string();
+        self.browsing = None;
+        self.swallow = by_character;
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+        self.swallow = false;
+    }
+
+    pub fn type_char(&mut self, c: char) {
+        if std::mem::replace(&mut self.swallow, false) || !self.open || c.is_control() {
+            return;
+        }
+        self.input.push(c);
+    }
+
+    pub fn backspace(&mut self) {
+        self.input.pop();
+    }
+
+    /// Takes the input as a command, and adds it to the history; None if nothing was typed.
+    pub fn submit(&mut self) -> Option<String> {
+        let command = std::mem::take(&mut self.input).trim().to_string();
+        self.browsing = None;
+        if command.is_empty() {
+            return None;
+        }
+        self.print(&format!("> {}", command));
+        if self.history.last() != Some(&command) {
+            self.history.push(command.clone());
+        }
+        Some(command)
+    }
+
+    /// Puts the command before the one shown, or the last one, in the input.
+    pub fn previous(&mut self) {
+        if self.history.is_empty() {
+            return;
+        }
+        let back = match self.browsing {
+            Some(back) => (back + 1).min(self.history.len() - 1),
+            None => 0,
+        };
+        self.browsing = Some(back);
+        self.input = self.history[self.history.len() - 1 - back].clone();
+    }
+
+    /// Puts the command after the one shown in the input, or clears it past the last one.
+    pub fn next(&mut self) {
+        match self.browsing {
+            Some(0) | None => {
+                self.browsing = None;
+                self.input.clear();
+            }
+            Some(back) => {
+                self.browsing = Some(back - 1);
+                self.input = self.history[self.history.len() - back].clone();
+            }
+        }
+    }
+
+    pub fn print(&mut self, text: &str) {
+        self.push(text, false);
+    }
+
+    pub fn error(&mut self, text: &str) {
+        self.push(text, true);
+    }
+
+    fn push(&mut self, text: &str, error: bool) {
+        self.log.extend(text.lines().map(|line| Line {
+            text: line.to_string(),
+            error,
+        }));
+        if self.log.len() > LOG_LENGTH {
+            self.log.drain(..self.log.len() - LOG_LENGTH);
+        }
+    }
+
+    /// The console as `rows` lines of `columns` characters: the last of the log, wrapped, then
+    /// the input on the bottom line. The image is `columns * CELL_WIDTH` by
+    /// `rows * CELL_HEIGHT` RGBA pixels, row by row from the top.
+    pub fn render(&self, columns: usize, rows: usize) -> Vec<u8> {
+        let columns = columns.max(3);
+        let mut lines = self
+            .log
+            .iter()
+            .flat_map(|line| {
+                let chars = line.text.chars().collect::<Vec<char>>();
+                let wrapped = match chars.is_empty() {
+                    true => vec![vec![]],
+                    false => chars.chunks(columns).map(|c| c.to_vec()).collect(),
+                };
+                wrapped.into_iter().map(move |chars| (chars, line.error))
+            })
+            .collect::<Vec<(Vec<char>, bool)>>();
+        // the end of the input is where the typing happens
+        let input = format!("> {}_", self.input).chars().collect::<Vec<char>>();
+        let shown = input.len().saturating_sub(columns);
+        lines.push((input[shown..].to_vec(), false));
+        let first = lines.len().saturating_sub(rows);
+        // short of lines, they sit at the bottom, by the input
+        let top = rows - (lines.len() - first);
+
+        let width = columns * CELL_WIDTH;
+        let mut pixels = BACKGROUND
+            .iter()
+            .copied()
+            .cycle()
+            .take(width * rows * CELL_HEIGHT * 4)
+            .collect::<Vec<u8>>();
+        for (row, (chars, error)) in lines[first..].iter().enumerate() {
+            let colour = if *error { ERROR } else { TEXT };
+            for (column, c) in chars.iter().enumerate() {
+                for y in 0..GLYPH_HEIGHT {
+                    for x in 0..GLYPH_WIDTH {
+                        if is_lit(*c, x, y) {
+                            let px = column * CELL_WIDTH + x;
+                            let py = (top + row) * CELL_HEIGHT + 1 + y;
+                            let at = (py * width + px) * 4;
+                            pixels[at..at + 4].copy_from_slice(&colour);
+                        }
+                    }
+                }
+            }
+        }
+        pixels
+    }
+}
@@ -1,23 +1,13 @@
-use cgmath::Deg;
-
-pub const WINDOW_WIDTH: u32 = 1280;
-pub const WINDOW_HEIGHT: u32 = 720;
-pub const FPS: f32 = 60.0;
-pub const VSYNC: bool = true;
-pub const FOV: f32 = 70.0; // degrees
-pub const FAR_PLANE: f32 = 100.0;
-pub const NEAR_PLANE: f32 = 0.1;
-pub const CAMERA_SPEED: f32 = 0.1;
-pub const VERTEX_COUNT: u32 = 100;
-
-pub static mut FILL_MODE: u64 = 0; // 0 = triangles, 1 = lines
-
-pub const FREQ_OF_UPDATES: u64 = 5; // the lower the more frequent
-pub const MAX_RAYCAST_DISTANCE: u64 = 100;
-pub const RAYCAST_RES: u64 = 100;
-pub const RAYCAST_CLOSENESS_REQ: f32 = 0.5;
-
-pub fn new_projection_matrix(aspect_ratio: f32) -> cgmath::Matrix4<f32> {
-    let persp = cgmath::perspective(Deg(FOV), aspect_ratio, NEAR_PLANE, FAR_PLANE);
-    persp
-}
+// settings for the windowed app are in wave::config
+pub const VERTEX_COUNT: u32 = 100; // tiles across and down in the default grid
+pub const MAX_GRID_SIZE: u32 = 4096;
+pub const MAX_SLOTS: usize = 64; // wave slots, each a layer of the grid on the CPU and the GPU
+pub const LAND_HEIGHT: f32 = 1.0; // how far blocked tiles stick out of the water
+
+// defaults for the wave equation solver
+pub const WAVE_SPEED: f32 = 1.0; // tiles per unit of time
+pub const WAVE_DAMPING: f32 = 0.02;
+pub const WAVE_TIMESTEP: f32 = 0.5; // units of time per update
+pub const SPONGE_WIDTH: u32 = 10; // tiles
+pub const SPONGE_DAMPING: f32 = 1.0; // added to the damping on the very edge
+pub const MIN_DEPTH_SPEED: f32 = 0.1; // how slow waves get in the shallowest water
@@ -0,0 +1,180 @@
+//! Writing height fields out for analysis: text rows, 16-bit grayscale PNGs, or NumPy arrays.
+//!
+//! Heights come from `Solver::height_at`, so they follow the same formula as `water_vert`. Every
+//! format is row by row, with `y` as the row and `x` as the column.
+
+use crate::wave::sim::{Solver, WaveRegistry};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Format {
+    // one row of heights per line, separated by spaces
+    Txt,
+    // heights from -range to range spread over 0 to 65535
+    Png,
+    // a (height, width) array of little-endian f32
+    Npy,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Result<Format, String> {
+        match name.trim() {
+            "txt" => Ok(Format::Txt),
+            "png" => Ok(Format::Png),
+            "npy" => Ok(Format::Npy),
+            name => Err(format!("Unknown format {}; use txt, png or npy.", name)),
+        }
+    }
+
+    /// Formats separated by commas, like "png,npy".
+    pub fn parse_list(names: &str) -> Result<Vec<Format>, String> {
+        names.split(',').map(Format::from_name).collect()
+    }
+
+    pub fn get_extension(&self) -> &'static str {
+        match self {
+            Format::Txt => "txt",
+            Format::Png => "png",
+            Format::Npy => "npy",
+        }
+    }
+}
+
+/// Every height of the grid, row by row.
+pub fn heights(sim: &dyn Solver, waves: &WaveRegistry) -> Vec<f32> {
+    let (width, height) = sim.get_size();
+    (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| sim.height_at(x, y, waves))
+        .collect()
+}
+
+/// The highest a tile can get when every wave is on it at its peak.
+pub fn height_range(waves: &WaveRegistry) -> f32 {
+    let range = waves
+        .iter()
+        .map(|wave| wave.amplitude_factor.abs())
+        .sum::<f32>();
+    if range > 0.0 {
+        range
+    } else {
+        1.0
+    }
+}
+
+pub fn write_txt<W: Write>(mut writer: W, width: u32, heights: &[f32]) -> Result<(), String> {
+    for row in heights.chunks(width as usize) {
+        let row = row
+            .iter()
+            .map(|height| height.to_string())
+            .collect::<Vec<String>>()
+            .join(" ");
+        writeln!(writer, "{}", row).map_err(|e| e.to_string())?;
+    }
+    writer.flush().map_err(|e| e.to_string())
+}
+
+/// Encodes heights as a 16-bit grayscale PNG; -range is black, 0 is mid gray and range is white,
+/// and anything past them is clamped.
+pub fn write_png16<W: Write>(
+    writer: W,
+    width: u32,
+    height: u32,
+    heights: &[f32],
+    range: f32,
+) -> Result<(), String> {
+    let pixels = heights
+        .iter()
+        .flat_map(|h| {
+            let level = ((h / range + 1.0) / 2.0).clamp(0.0, 1.0);
+            ((level * u16::MAX as f32).round() as u16).to_be_bytes()
+        })
+        .collect::<Vec<u8>>();
+    let mut encoder = png::Encoder::new(writer, width, height);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Sixteen);
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    writer.write_image_data(&pixels).map_err(|e| e.to_string())
+}
+
+/// Encodes heights as a version 1.0 `.npy` file that `numpy.load` reads back as a
+/// `(height, width)` array of `float32`.
+pub fn write_npy<W: Write>(
+    mut writer: W,
+    width: u32,
+    height: u32,
+    heights: &[f32],
+) -> Result<(), String> {
+    let mut header = format!(
+        "{{'descr': '<f4', 'fortran_order': False, 'shape': ({}, {}), }}",
+        height, width
+    );
+    // the magic, version and header length take 10 bytes, and the data starts 64-byte aligned
+    while (10 + header.len() + 1) % 64 != 0 {
+        header.push(' ');
+    }
+    header.push('\n');
+
+    let mut out = b"\x93NUMPY\x01\x00".to_vec();
+    out.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    out.extend_from_slice(header.as_bytes());
+    heights
+        .iter()
+        .for_each(|h| out.extend_from_slice(&h.to_le_bytes()));
+    writer.write_all(&out).map_err(|e| e.to_string())?;
+    writer.flush().map_err(|e| e.to_string())
+}
+
+/// Writes the heights of a tick to `<dir>/tick_NNNNNN.<format>` in every format chosen.
+pub struct Exporter {
+    pub dir: PathBuf,
+    pub formats: Vec<Format>,
+    // the PNG range; the waves' `height_range` if not given
+    pub range: Option<f32>,
+}
+
+impl Exporter {
+    pub fn export(
+        &self,
+        tick: u64,
+        sim: &dyn Solver,
+        waves: &WaveRegistry,
+    ) -> Result<Vec<PathBuf>, String> {
+        std::fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Could not create {}: {}", self.dir.display(), e))?;
+        let (width, height) = sim.get_size();
+        let heights = heights(sim, waves);
+        let range = self.range.unwrap_or_else(|| height_range(waves));
+        self.formats
+            .iter()
+            .map(|format| {
+                let path = self
+                    .dir
+                    .join(format!("tick_{:06}.{}", tick, format.get_extension()));
+                write(&path, *format, width, height, &heights, range)?;
+                Ok(path)
+            })
+            .collect()
+    }
+}
+
+fn write(
+    path: &Path,
+    format: Format,
+    width: u32,
+    height: u32,
+    heights: &[f32],
+    range: f32,
+) -> Result<(), String> {
+    let file = BufWriter::new(
+        File::create(path).map_err(|e| format!("Could not write {}: {}", path.display(), e))?,
+    );
+    match format {
+        Format::Txt => write_txt(file, width, heights),
+        Format::Png => write_png16(file, width, height, heights, range),
+        Format::Npy => write_npy(file, width, height, heights),
+    }
+    .map_err(|e| format!("{}: {}", path.display(), e))
+}
@@ -0,0 +1,92 @@
+//! The size of the water: how many tiles it has across and down, and how big each one is.
+
+use crate::wave::constants::{MAX_GRID_SIZE, VERTEX_COUNT};
+use serde::{Deserialize, Serialize};
+
+/// A `width` by `height` grid of tiles, drawn as a mesh with a vertex per tile, `cell_size`
+/// apart and centred on the origin. Vertex rows go along z the other way from tile rows.
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct Grid {
+    pub width: u32,
+    pub height: u32,
+    pub cell_size: f32,
+}
+
+impl Default for Grid {
+    fn default() -> Grid {
+        Grid {
+            width: VERTEX_COUNT,
+            height: VERTEX_COUNT,
+            cell_size: 1.0,
+        }
+    }
+}
+
+impl Grid {
+    pub fn new(width: u32, height: u32) -> Grid {
+        Grid {
+            width,
+            height,
+            ..Grid::default()
+        }
+    }
+
+    /// How many tiles there are.
+    pub fn get_len(&self) -> usize {
+        self.width as usize * self.height as usize
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let sizes = 2..=MAX_GRID_SIZE;
+        if !sizes.contains(&self.width) || !sizes.contains(&self.height) {
+            return Err(format!(
+                "A {}x{} grid is invalid; 2 <= width, height <= {}.",
+                self.width, self.height, MAX_GRID_SIZE
+            ));
+        }
+        if !(self.cell_size > 0.0 && self.cell_size.is_finite()) {
+            return Err(format!(
+                "Cell size {} is invalid; it must be above 0.",
+                self.cell_size
+            ));
+        }
+        Ok(())
+    }
+
+    /// Where vertex `(x, z)` is in the world, before the water moves it up or down.
+    pub fn vertex_position(&self, x: u32, z: u32) -> (f32, f32) {
+        (
+            (x as f32 - self.width as f32 / 2.0) * self.cell_size,
+            (z as f32 - self.height as f32 / 2.0) * self.cell_size,
+        )
+    }
+
+    /// The tile vertex `(x, z)` shows.
+    pub fn tile_of_vertex(&self, x: u32, z: u32) -> (u32, u32) {
+        (x, self.height - 1 - z)
+    }
+
+    /// The tile of the cell `(x, z)` in the world is in, if it's on the grid; a vertex is at
+    /// the corner of its cell with the lowest x and z.
+    pub fn tile_at(&self, x: f32, z: f32) -> Option<(u32, u32)> {
+        let vertex_x = (x / self.cell_size + self.width as f32 / 2.0).floor();
+        let vertex_z = (z / self.cell_size + self.height as f32 / 2.0).floor();
+        if vertex_x < 0.0
+            || vertex_z < 0.0
+            || vertex_x >= self.width as f32
+            || vertex_z >= self.height as f32
+        {
+            return None;
+        }
+        Some(self.tile_of_vertex(vertex_x as u32, vertex_z as u32))
+    }
+
+    /// The texture coordinates `water_vert` gives a point in the world.
+    pub fn texture_coords(&self, x: f32, z: f32) -> [f32; 2] {
+        [
+            (x / (self.width as f32 * self.cell_size) + 1.0) / 2.0,
+            (z / (self.height as f32 * self.cell_size) + 1.0) / 2.0,
+        ]
+    }
+}
@@ -0,0 +1,260 @@
+//! The displaced water surface as a triangle mesh, to bring a state of the simulation into other
+//! 3D tools as OBJ, PLY or binary glTF.
+//!
+//! The mesh is the one `WaterBundle::generate_water` builds, displaced the way `water_vert` does:
+//! vertices are laid out and show tiles as the `Grid` says, land sticks out at `LAND_HEIGHT`, and
+//! the UVs are its `textureCoords`.
+
+use crate::wave::constants::LAND_HEIGHT;
+use crate::wave::grid::Grid;
+use crate::wave::sim::{Solver, WaveRegistry};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+pub struct Mesh {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub uvs: Vec<[f32; 2]>,
+    // counter-clockwise seen from above
+    pub indices: Vec<u32>,
+}
+
+/// How high every vertex of the water is, row by row.
+pub fn surface_heights(sim: &dyn Solver, waves: &WaveRegistry) -> Vec<f32> {
+    let (width, height) = sim.get_size();
+    let grid = Grid::new(width, height);
+    let obstacles = sim.get_obstacles();
+    (0..height)
+        .flat_map(|z| (0..width).map(move |x| grid.tile_of_vertex(x, z)))
+        .map(|(x, y)| match obstacles.is_blocked(x, y) {
+            true => LAND_HEIGHT,
+            false => sim.height_at(x, y, waves),
+        })
+        .collect()
+}
+
+/// Normals of a grid of heights `cell_size` apart, from the slopes between each vertex's
+/// neighbours; edges use the vertex itself in place of the missing neighbour.
+pub fn vertex_normals(width: u32, height: u32, cell_size: f32, heights: &[f32]) -> Vec<[f32; 3]> {
+    let at = |x: u32, z: u32| heights[(z * width + x) as usize];
+    (0..height)
+        .flat_map(|z| (0..width).map(move |x| (x, z)))
+        .map(|(x, z)| {
+            let (left, right) = (x.saturating_sub(1), (x + 1).min(width - 1));
+            let (back, front) = (z.saturating_sub(1), (z + 1).min(height - 1));
+            let dx = (at(right, z) - at(left, z)) / ((right - left).max(1) as f32 * cell_size);
+            let dz = (at(x, front) - at(x, back)) / ((front - back).max(1) as f32 * cell_size);
+            let length = (dx * dx + 1.0 + dz * dz).sqrt();
+            [-dx / length, 1.0 / length, -dz / length]
+        })
+        .collect()
+}
+
+impl Mesh {
+    /// The water surface as the solver has it now, with tiles `cell_size` apart.
+    pub fn water(sim: &dyn Solver, waves: &WaveRegistry, cell_size: f32) -> Mesh {
+        let (width, height) = sim.get_size();
+        let grid = Grid {
+            width,
+            height,
+            cell_size,
+        };
+        Mesh::from_heights(&grid, &surface_heights(sim, waves))
+    }
+
+    pub fn from_heights(grid: &Grid, heights: &[f32]) -> Mesh {
+        let (width, height) = (grid.width, grid.height);
+        let mut positions = Vec::with_capacity(heights.len());
+        let mut uvs = Vec::with_capacity(heights.len());
+        (0..height)
+            .flat_map(|z| (0..width).map(move |x| (x, z)))
+            .zip(heights.iter())
+            .for_each(|((x, z), h)| {
+                let (px, pz) = grid.vertex_position(x, z);
+                positions.push([px, *h, pz]);
+                uvs.push(grid.texture_coords(px, pz));
+            });
+
+        let mut indices = Vec::with_capacity(6 * (width as usize - 1) * (height as usize - 1));
+        (0..height - 1).for_each(|z| {
+            (0..width - 1).for_each(|x| {
+                let top_left = z * width + x;
+                let top_right = top_left + 1;
+                let bottom_left = (z + 1) * width + x;
+                let bottom_right = bottom_left + 1;
+                indices.extend_from_slice(&[
+                    top_left,
+                    bottom_left,
+                    top_right,
+                    top_right,
+                    bottom_left,
+                    bottom_right,
+                ]);
+            });
+        });
+
+        Mesh {
+            positions,
+            normals: vertex_normals(width, height, grid.cell_size, heights),
+            uvs,
+            indices,
+        }
+    }
+
+    /// Writes the mesh in the format its extension names: `.obj`, `.ply` or `.glb`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase());
+        let write: fn(&Mesh, &mut BufWriter<File>) -> std::io::Result<()> =
+            match extension.as_deref() {
+                Some("obj") => Mesh::write_obj,
+                Some("ply") => Mesh::write_ply,
+                Some("glb") => Mesh::write_glb,
+                _ => {
+                    return Err(format!(
+                        "Can't tell the format of {}; use .obj, .ply or .glb.",
+                        path.display()
+                    ))
+                }
+            };
+        File::create(path)
+            .and_then(|file| {
+                let mut file = BufWriter::new(file);
+                write(self, &mut file)?;
+                file.flush()
+            })
+            .map_err(|e| format!("Could not write {}: {}", path.display(), e))
+    }
+
+    pub fn write_obj<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writeln!(writer, "o water")?;
+        for [x, y, z] in self.positions.iter() {
+            writeln!(writer, "v {} {} {}", x, y, z)?;
+        }
+        for [u, v] in self.uvs.iter() {
+            writeln!(writer, "vt {} {}", u, v)?;
+        }
+        for [x, y, z] in self.normals.iter() {
+            writeln!(writer, "vn {} {} {}", x, y, z)?;
+        }
+        // OBJ counts from 1
+        for face in self.indices.chunks(3) {
+            let [a, b, c] = [face[0] + 1, face[1] + 1, face[2] + 1];
+            writeln!(writer, "f {0}/{0}/{0} {1}/{1}/{1} {2}/{2}/{2}", a, b, c)?;
+        }
+        Ok(())
+    }
+
+    pub fn write_ply<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        write!(
+            writer,
+            "ply\nformat binary_little_endian 1.0\nelement vertex {}\n\
+             property float x\nproperty float y\nproperty float z\n\
+             property float nx\nproperty float ny\nproperty float nz\n\
+             property float s\nproperty float t\n\
+             element face {}\nproperty list uchar uint vertex_indices\nend_header\n",
+            self.positions.len(),
+            self.indices.len() / 3
+        )?;
+        let mut out = vec![];
+        for ((position, normal), uv) in self
+            .positions
+            .iter()
+            .zip(self.normals.iter())
+            .zip(self.uvs.iter())
+        {
+            position
+                .iter()
+                .chain(normal.iter())
+                .chain(uv.iter())
+                .for_each(|v| out.extend_from_slice(&v.to_le_bytes()));
+        }
+        for face in self.indices.chunks(3) {
+            out.push(3);
+            face.iter()
+                .for_each(|i| out.extend_from_slice(&i.to_le_bytes()));
+        }
+        writer.write_all(&out)
+    }
+
+    /// A binary glTF 2.0 file with one buffer: positions, normals, UVs, then indices.
+    pub fn write_glb<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        let mut bin = vec![];
+        let mut put = |values: &mut dyn Iterator<Item = [u8; 4]>| {
+            let offset = bin.len();
+            values.for_each(|v| bin.extend_from_slice(&v));
+            (offset, bin.len() - offset)
+        };
+        let views = [
+            put(&mut self.positions.iter().flatten().map(|v| v.to_le_bytes())),
+            put(&mut self.normals.iter().flatten().map(|v| v.to_le_bytes())),
+            put(&mut self.uvs.iter().flatten().map(|v| v.to_le_bytes())),
+            put(&mut self.indices.iter().map(|i| i.to_le_bytes())),
+        ];
+
+        let (mut min, mut max) = ([f32::MAX; 3], [f32::MIN; 3]);
+        for position in self.positions.iter() {
+            (0..3).for_each(|i| {
+                min[i] = min[i].min(position[i]);
+                max[i] = max[i].max(position[i]);
+            });
+        }
+        let count = self.positions.len();
+        let view = |i: usize, target: u32| {
+            format!(
+                "{{\"buffer\":0,\"byteOffset\":{},\"byteLength\":{},\"target\":{}}}",
+                views[i].0, views[i].1, target
+            )
+        };
+        // 5126 is float, 5125 unsigned int; 34962 is vertex data, 34963 indices
+        let mut json = format!(
+            "{{\"asset\":{{\"version\":\"2.0\",\"generator\":\"wave-simulator\"}},\
+             \"scene\":0,\"scenes\":[{{\"nodes\":[0]}}],\"nodes\":[{{\"mesh\":0,\"name\":\"water\"}}],\
+             \"meshes\":[{{\"primitives\":[{{\"attributes\":\
+             {{\"POSITION\":0,\"NORMAL\":1,\"TEXCOORD_0\":2}},\"indices\":3}}]}}],\
+             \"buffers\":[{{\"byteLength\":{}}}],\
+             \"bufferViews\":[{},{},{},{}],\
+             \"accessors\":[\
+             {{\"bufferView\":0,\"componentType\":5126,\"count\":{},\"type\":\"VEC3\",\
+             \"min\":[{},{},{}],\"max\":[{},{},{}]}},\
+             {{\"bufferView\":1,\"componentType\":5126,\"count\":{},\"type\":\"VEC3\"}},\
+             {{\"bufferView\":2,\"componentType\":5126,\"count\":{},\"type\":\"VEC2\"}},\
+             {{\"bufferView\":3,\"componentType\":5125,\"count\":{},\"type\":\"SCALAR\"}}]}}",
+            bin.len(),
+            view(0, 34962),
+            view(1, 34962),
+            view(2, 34962),
+            view(3, 34963),
+            count,
+            min[0],
+            min[1],
+            min[2],
+            max[0],
+            max[1],
+            max[2],
+            count,
+            count,
+            self.indices.len()
+        );
+        // chunks are 4-byte aligned
+        while json.len() % 4 != 0 {
+            json.push(' ');
+        }
+
+        let length = 12 + 8 + json.len() + 8 + bin.len();
+        let mut out = b"glTF".to_vec();
+        out.extend_from_slice(&2u32.to_le_bytes());
+        out.extend_from_slice(&(length as u32).to_le_bytes());
+        out.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        out.extend_from_slice(b"JSON");
+        out.extend_from_slice(json.as_bytes());
+        out.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        out.extend_from_slice(b"BIN\0");
+        out.extend_from_slice(&bin);
+        writer.write_all(&out)
+    }
+}
@@ -1,72 +1,166 @@
+#[cfg(target_os = "macos")]
 use crate::app::Application;
+#[cfg(target_os = "macos")]
 use crate::behavior::Behavior;
-use crate::wave::bundles::basemetal::BaseMetalBundle;
-use crate::wave::bundles::debug::DebugBundle;
+use crate::wave::backend::Backend;
+use crate::wave::bathymetry::Bathymetry;
+#[cfg(target_os = "macos")]
+use crate::wave::bundles::frame::MetalBackend;
 use crate::wave::bundles::matrix::MatrixBundle;
-use crate::wave::bundles::ui::UiBundle;
-use crate::wave::bundles::water::{WaterBundle, Wave};
+#[cfg(target_os = "macos")]
 use crate::wave::bundles::window::WindowBundle;
-use crate::wave::constants::FPS;
+use crate::wave::config::Config;
+use crate::wave::console::Console;
+use crate::wave::export::Exporter;
+use crate::wave::grid::Grid;
 use crate::wave::keyboard::Keyboard;
+use crate::wave::obstacles::ObstacleMask;
+use crate::wave::physics::Driver;
+use crate::wave::recording::Recording;
+use crate::wave::render::colormap::Mapping;
+use crate::wave::render::Image;
+use crate::wave::scene::{Scene, Source};
+use crate::wave::sim::{Ripple, SolverSettings, WaveRegistry};
 use cgmath::Vector3;
+use std::path::PathBuf;
+#[cfg(target_os = "macos")]
 use std::time::{Duration, Instant};
+#[cfg(target_os = "macos")]
 use winit::event::{Event, StartCause, WindowEvent};
+#[cfg(target_os = "macos")]
 use winit::event_loop::{ControlFlow, EventLoop};
 
+pub mod backend;
+pub mod bathymetry;
 pub mod behavior;
+pub mod boundary;
 pub mod bundles;
 pub mod camera;
+pub mod cli;
+pub mod command;
+pub mod config;
+pub mod console;
 pub mod constants;
+pub mod export;
+pub mod font;
+pub mod grid;
 pub mod keyboard;
+pub mod mesh;
+pub mod obstacles;
+pub mod physics;
 pub mod raycaster;
+pub mod recording;
+pub mod render;
+pub mod scene;
+pub mod sim;
+pub mod snapshot;
 pub mod util;
 pub mod widget;
 
-pub struct WaveApp {
+/// The app, drawn and updated by `B`: Metal in the window on macOS, or the CPU anywhere.
+pub struct WaveApp<B: Backend> {
+    pub config: Config,
     pub keyboard: Keyboard,
-    pub window_bundle: Option<WindowBundle>,
-    pub base_metal_bundle: Option<BaseMetalBundle>,
+    // takes the keyboard while it's open
+    pub console: Console,
+    pub backend: Option<B>,
+    pub window_size: (u32, u32),
     pub matrix_bundle: Option<MatrixBundle>,
-    pub ui_bundle: Option<UiBundle>,
-    pub debug_bundle: Option<DebugBundle>,
-    pub water: Option<WaterBundle>,
+    // the size the water is made at; a scene or snapshot can change it
+    pub grid: Grid,
     pub current_ray_pos: Vector3<f32>,
-    pub waves: [Wave; 4],
+    // the slot a left click places, picked with the number keys
+    pub active_slot: usize,
+    pub waves: WaveRegistry,
+    pub solver: SolverSettings,
+    pub drivers: Vec<Driver>,
+    pub ripples: Vec<Ripple>,
+    // every wave placed so far, to save in a scene
+    pub sources: Vec<Source>,
+    // the scene to start with, until the water is made
+    pub scene: Option<Scene>,
+    // the file the scene came from, and where `K` saves it
+    pub scene_path: Option<PathBuf>,
+    // every wave defined or placed so far, saved with `J`
+    pub recording: Recording,
+    // a recording to play back as the water updates
+    pub replay: Option<Recording>,
+    // how often, in updates, and where to write the heights, picked with `X`
+    pub export: Option<(u64, Exporter)>,
+    // where exports go unless given a path of their own
+    pub out: PathBuf,
+    pub obstacles: ObstacleMask,
+    pub bathymetry: Bathymetry,
     pub time: u64,
+    // updates of the water so far; `time` counts frames
+    pub updates: u64,
     pub mouse_pos: (f64, f64),
+    // drawn on the mouse
+    pub crosshair: Image,
     pub paused: bool,
+    // starts as the config's, and `R` toggles it
+    pub wireframe: bool,
+    // what the water is coloured by in place of its texture, picked with `T` or `view`
+    pub mapping: Option<Mapping>,
 }
 
-impl Application for WaveApp {
-    fn new() -> Self {
+impl<B: Backend> Default for WaveApp<B> {
+    fn default() -> Self {
         WaveApp {
+            config: Config::default(),
             keyboard: Keyboard { keys: [false; 300] },
-            window_bundle: None,
-            base_metal_bundle: None,
+            console: Console::default(),
+            backend: None,
+            window_size: (0, 0),
             matrix_bundle: None,
-            ui_bundle: None,
-            debug_bundle: None,
-            water: None,
+            grid: Grid::default(),
             current_ray_pos: Vector3 {
                 x: 0.0,
                 y: 0.0,
                 z: 0.0,
             },
-            waves: [Wave::empty(); 4],
+            active_slot: 0,
+            waves: WaveRegistry::new(),
+            solver: SolverSettings::default(),
+            drivers: vec![],
+            ripples: vec![],
+            sources: vec![],
+            scene: None,
+            scene_path: None,
+            recording: Recording::default(),
+            replay: None,
+            export: None,
+            out: PathBuf::from("."),
+            obstacles: ObstacleMask::open(Grid::default().width, Grid::default().height),
+            bathymetry: Bathymetry::flat(Grid::default().width, Grid::default().height),
             time: 0,
+            updates: 0,
             mouse_pos: (0.0, 0.0),
+            crosshair: Image::crosshair(),
             paused: false,
+            wireframe: false,
+            mapping: None,
         }
     }
+}
+
+#[cfg(target_os = "macos")]
+impl Application for WaveApp<MetalBackend> {
+    fn new() -> Self {
+        WaveApp::default()
+    }
 
     fn execute(mut self, event_loop: EventLoop<()>) {
-        self.window_bundle = Some(WindowBundle::new(&event_loop));
+        let window = WindowBundle::new(&event_loop, &self.config);
+        let size = window.window.inner_size();
+        self.window_size = (size.width, size.height);
+        self.wireframe = self.config.wireframe;
+        self.backend = Some(unsafe { MetalBackend::new(window, self.config.vsync, self.grid) });
 
-        let mut current_behavior: Box<dyn Behavior<Self>> =
-            Box::new(behavior::loader::BaseLoaderBehavior);
+        let mut current_behavior: Box<dyn Behavior<Self>> = Box::new(behavior::main::MainBehavior);
         current_behavior.init(&mut self);
 
-        let duration = Duration::from_millis((1000.0 / FPS) as u64);
+        let duration = Duration::from_millis((1000.0 / self.config.fps) as u64);
         let mut now = Instant::now();
         event_loop.run(move |event, _, control_flow| {
             *control_flow = ControlFlow::WaitUntil(now + duration);
@@ -77,6 +171,7 @@ impl Application for WaveApp {
                         requested_resume: _,
                     } => {
                         let nb = current_behavior.update(&mut self);
+                        self.backend.as_ref().unwrap().window.window.request_redraw();
                         match nb {
                             None => {}
                             Some(t) => {
@@ -100,6 +195,7 @@ impl Application for WaveApp {
                         *control_flow = ControlFlow::Exit;
                     }
                     WindowEvent::Resized(size) => {
+                        self.window_size = (size.width, size.height);
                         current_behavior.on_resize(&mut self, (size.width, size.height));
                     }
                     WindowEvent::KeyboardInput {
@@ -116,14 +212,17 @@ impl Application for WaveApp {
                             input.state,
                         );
                     }
+                    WindowEvent::ReceivedCharacter(c) => {
+                        current_behavior.on_character(&mut self, c);
+                    }
                     #[allow(deprecated)]
                     WindowEvent::MouseInput {
                         device_id: _,
-                        state: _,
-                        button: _,
+                        state,
+                        button,
                         modifiers: _,
                     } => {
-                        // TODO add calls here
+                        current_behavior.on_mouse_input(&mut self, button, state);
                     }
                     #[allow(deprecated)]
                     WindowEvent::CursorMoved {
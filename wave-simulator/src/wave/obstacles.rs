@@ -0,0 +1,81 @@
+//! Land, breakwaters and anything else the water can't go through.
+
+use crate::wave::util::{read_grayscale_png, write_grayscale_png};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// One byte per tile, row by row like the height buffers: 1 where the tile is blocked, 0 where
+/// it's water. Both solvers hold blocked tiles flat and never let a wave onto them.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct ObstacleMask {
+    pub width: u32,
+    pub height: u32,
+    pub tiles: Vec<u8>,
+}
+
+impl ObstacleMask {
+    /// A mask with nothing in the way.
+    pub fn open(width: u32, height: u32) -> ObstacleMask {
+        ObstacleMask {
+            width,
+            height,
+            tiles: vec![0; width as usize * height as usize],
+        }
+    }
+
+    /// Reads a mask from a PNG the size of the grid; dark pixels are land.
+    pub fn from_png<R: Read>(reader: R, width: u32, height: u32) -> Result<ObstacleMask, String> {
+        let (png_width, png_height, pixels) = read_grayscale_png(reader)?;
+        if (png_width, png_height) != (width, height) {
+            return Err(format!(
+                "The obstacle mask is {}x{}, but the grid is {}x{}.",
+                png_width, png_height, width, height
+            ));
+        }
+        Ok(ObstacleMask {
+            width,
+            height,
+            tiles: pixels.iter().map(|p| (*p < 128) as u8).collect(),
+        })
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P, width: u32, height: u32) -> Result<ObstacleMask, String> {
+        let file = File::open(path.as_ref())
+            .map_err(|e| format!("Could not read {}: {}", path.as_ref().display(), e))?;
+        ObstacleMask::from_png(file, width, height)
+            .map_err(|e| format!("{}: {}", path.as_ref().display(), e))
+    }
+
+    /// Writes the mask as a PNG that `from_png` reads back: black for land, white for water.
+    pub fn to_png<W: Write>(&self, writer: W) -> Result<(), String> {
+        let pixels = self
+            .tiles
+            .iter()
+            .map(|blocked| if *blocked != 0 { 0 } else { 255 })
+            .collect::<Vec<u8>>();
+        write_grayscale_png(writer, self.width, self.height, &pixels)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let file = File::create(path.as_ref())
+            .map_err(|e| format!("Could not write {}: {}", path.as_ref().display(), e))?;
+        self.to_png(file)
+            .map_err(|e| format!("{}: {}", path.as_ref().display(), e))
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.tiles.iter().all(|blocked| *blocked == 0)
+    }
+
+    // tiles outside of the mask are never blocked
+    pub fn is_blocked(&self, x: u32, y: u32) -> bool {
+        x < self.width && y < self.height && self.tiles[(y * self.width + x) as usize] != 0
+    }
+
+    pub fn set_blocked(&mut self, x: u32, y: u32, blocked: bool) {
+        if x < self.width && y < self.height {
+            self.tiles[(y * self.width + x) as usize] = blocked as u8;
+        }
+    }
+}
@@ -0,0 +1,316 @@
+//! The physically based solver: the damped 2D wave equation
+//!
+//! ```text
+//! h_tt + damping * h_t = speed^2 * (h_xx + h_yy)
+//! ```
+//!
+//! integrated with central differences on the tile grid, one tile apart. This is the reference
+//! for the `process_wave_equation` kernel in `bundles/shaders.metal`.
+
+use crate::wave::bathymetry::Bathymetry;
+use crate::wave::boundary::Boundary;
+use crate::wave::obstacles::ObstacleMask;
+use crate::wave::sim::{
+    attenuation, is_expired, travelled, Solver, SolverSettings, Wave, WaveKind, WaveRegistry, DOWN,
+    LEFT, RIGHT, UP,
+};
+use crate::wave::snapshot::Snapshot;
+use std::f32::consts::PI;
+
+/// A placed wave. Once the wave has come as far as its tile, at `start`, the tile is held at the
+/// same half sine an automaton tile goes through, one tick per update, and is let go once it has
+/// been through a wavelength.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Driver {
+    pub x: u32,
+    pub y: u32,
+    pub slot: usize,
+    // updates since the wave was placed
+    pub tick: u16,
+    pub start: u16,
+    // where the wave was placed, and how far from there the tile is, for its falloff
+    pub from: (u32, u32),
+    pub distance: f32,
+}
+
+/// The drivers placing a wave on `(x, y)` starts, leaving out land: one for the tile, and, for a
+/// directional wave, one for every tile along each of its directions up to the edge or land,
+/// started as the wave gets there at `speed` tiles per update, which sends it out that way. A
+/// plane wave has one for every tile on the line through `(x, y)` across its heading instead,
+/// which sends straight wavefronts out on both sides.
+pub fn place_drivers(
+    (x, y): (u32, u32),
+    slot: usize,
+    wave: Option<&Wave>,
+    obstacles: &ObstacleMask,
+    speed: f32,
+) -> Vec<Driver> {
+    let (width, height) = (obstacles.width, obstacles.height);
+    let driver = |(tx, ty), distance: f32| Driver {
+        x: tx,
+        y: ty,
+        slot,
+        tick: 0,
+        start: (distance / speed).round().min(u16::MAX as f32) as u16,
+        from: (x, y),
+        distance,
+    };
+    let drivers = match wave {
+        Some(wave) if wave.kind == WaveKind::Plane => (0..height)
+            .flat_map(|ty| (0..width).map(move |tx| (tx, ty)))
+            .filter(|(tx, ty)| travelled(wave, (x as f32, y as f32), *tx, *ty).abs() < 0.5)
+            .map(|tile| driver(tile, 0.0))
+            .collect(),
+        Some(wave) if wave.kind == WaveKind::Directional && speed > 0.0 => {
+            // up moves to lower rows and left to higher columns, like in the automaton
+            let steps = [(UP, 0, -1), (DOWN, 0, 1), (LEFT, 1, 0), (RIGHT, -1, 0)];
+            let mut drivers = vec![driver((x, y), 0.0)];
+            steps
+                .iter()
+                .filter(|(direction, _, _)| wave.directions & direction != 0)
+                .for_each(|(_, dx, dy)| {
+                    let tiles = (1..).map(|step| (x as i64 + dx * step, y as i64 + dy * step));
+                    drivers.extend(
+                        tiles
+                            .take_while(|(tx, ty)| {
+                                (0..width as i64).contains(tx) && (0..height as i64).contains(ty)
+                            })
+                            .map(|(tx, ty)| (tx as u32, ty as u32))
+                            .take_while(|(tx, ty)| !obstacles.is_blocked(*tx, *ty))
+                            .enumerate()
+                            .map(|(step, tile)| driver(tile, step as f32 + 1.0)),
+                    );
+                });
+            drivers
+        }
+        _ => vec![driver((x, y), 0.0)],
+    };
+    drivers
+        .into_iter()
+        .filter(|driver| !obstacles.is_blocked(driver.x, driver.y))
+        .collect()
+}
+
+/// The height a driver holds its tile at for one update, as laid out for the GPU.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ForcedTile {
+    pub x: u32,
+    pub y: u32,
+    pub height: f32,
+}
+
+/// Advances every driver by one tick and returns the tiles the started ones hold this update,
+/// attenuated by the wave's decay and falloff. Drivers that are done, whose wave has expired, or
+/// whose slot is empty, are removed.
+///
+/// A wave's lifetime only lets go of its drivers, including the ones it hasn't reached yet; what
+/// they have already sent through the field carries on until the damping wears it down.
+pub fn drive(drivers: &mut Vec<Driver>, waves: &WaveRegistry) -> Vec<ForcedTile> {
+    drivers.retain(|driver| {
+        waves.get(driver.slot).is_some_and(|wave| {
+            let end = driver.start as u32 + wave.wavelength as u32;
+            (driver.tick as u32) < end && !is_expired(wave, driver.tick as u32 + 1)
+        })
+    });
+    drivers
+        .iter_mut()
+        .filter_map(|driver| {
+            let wave = waves.get(driver.slot).unwrap();
+            driver.tick += 1;
+            let tick = driver
+                .tick
+                .checked_sub(driver.start)
+                .filter(|tick| *tick > 0)?;
+            Some(ForcedTile {
+                x: driver.x,
+                y: driver.y,
+                height: wave.amplitude_factor
+                    * (tick as f32 * (PI / wave.wavelength as f32)).sin()
+                    * attenuation(wave, driver.tick as u32, driver.distance),
+            })
+        })
+        .collect()
+}
+
+pub struct WaveEquation {
+    pub width: u32,
+    pub height: u32,
+    pub settings: SolverSettings,
+    pub heights: Vec<f32>,
+    pub previous: Vec<f32>,
+    pub drivers: Vec<Driver>,
+    pub obstacles: ObstacleMask,
+    pub bathymetry: Bathymetry,
+}
+
+impl WaveEquation {
+    pub fn new(width: u32, height: u32, settings: SolverSettings) -> WaveEquation {
+        let len = width as usize * height as usize;
+        WaveEquation {
+            width,
+            height,
+            settings,
+            heights: vec![0.0; len],
+            previous: vec![0.0; len],
+            drivers: vec![],
+            obstacles: ObstacleMask::open(width, height),
+            bathymetry: Bathymetry::flat(width, height),
+        }
+    }
+
+    /// Advances every tile by one update, with `forced` held where it says in place of this
+    /// solver's own drivers, like the GPU does with the app's.
+    pub fn step_forced(&mut self, forced: &[ForcedTile]) {
+        let mut next = vec![0.0; self.heights.len()];
+        integrate(
+            &self.settings,
+            (self.width, self.height),
+            &self.bathymetry.speeds,
+            &self.previous,
+            &self.heights,
+            &mut next,
+        );
+        forced.iter().for_each(|tile| {
+            next[(tile.y * self.width + tile.x) as usize] = tile.height;
+        });
+        // blocked tiles stay flat, even under a driver
+        self.obstacles
+            .tiles
+            .iter()
+            .zip(next.iter_mut())
+            .filter(|(blocked, _)| **blocked != 0)
+            .for_each(|(_, height)| *height = 0.0);
+        self.previous = std::mem::replace(&mut self.heights, next);
+    }
+}
+
+/// Computes the next heights from the current and previous ones, with the wave speed scaled by
+/// `speeds` on every tile. What is past the edges depends on the boundary; with a fixed one, it
+/// is flat.
+pub fn integrate(
+    settings: &SolverSettings,
+    (width, height): (u32, u32),
+    speeds: &[f32],
+    previous: &[f32],
+    current: &[f32],
+    next: &mut [f32],
+) {
+    let bounds = (width as i64, height as i64);
+    let read = |x: i64, y: i64| match settings.boundary.resolve(x, y, bounds.0, bounds.1) {
+        Some((x, y)) => current[(y * bounds.0 + x) as usize],
+        None => 0.0,
+    };
+
+    (0..height).for_each(|y| {
+        (0..width).for_each(|x| {
+            let sponge = settings
+                .boundary
+                .sponge(x, y, width, height, settings.sponge_width);
+            let friction =
+                (settings.damping + settings.sponge_damping * sponge) * settings.timestep / 2.0;
+            let index = (y * width + x) as usize;
+            let courant = (settings.speed * speeds[index] * settings.timestep).powi(2);
+            let here = current[index];
+            let (x, y) = (x as i64, y as i64);
+            let laplacian =
+                read(x, y + 1) + read(x, y - 1) + read(x - 1, y) + read(x + 1, y) - 4.0 * here;
+            next[index] = (2.0 * here - (1.0 - friction) * previous[index] + courant * laplacian)
+                / (1.0 + friction);
+        });
+    });
+}
+
+impl Solver for WaveEquation {
+    fn get_size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    // radial waves spread out in circles from their tile; directional ones drive a line of
+    // tiles along each direction, and plane waves one across their heading
+    fn place(&mut self, x: u32, y: u32, wave_id: usize, waves: &WaveRegistry) {
+        if x < self.width && y < self.height && !self.obstacles.is_blocked(x, y) {
+            let speed = self.settings.speed * self.settings.timestep;
+            self.drivers.extend(place_drivers(
+                (x, y),
+                wave_id,
+                waves.get(wave_id),
+                &self.obstacles,
+                speed,
+            ));
+        }
+    }
+
+    // the tile is flattened, and drivers on it or placed from it let go
+    fn clear(&mut self, x: u32, y: u32) {
+        if x < self.width && y < self.height {
+            let index = (y * self.width + x) as usize;
+            self.heights[index] = 0.0;
+            self.previous[index] = 0.0;
+            self.drivers
+                .retain(|driver| (driver.x, driver.y) != (x, y) && driver.from != (x, y));
+        }
+    }
+
+    fn step(&mut self, waves: &WaveRegistry) {
+        let forced = drive(&mut self.drivers, waves);
+        self.step_forced(&forced);
+    }
+
+    fn get_obstacles(&self) -> &ObstacleMask {
+        &self.obstacles
+    }
+
+    fn set_obstacles(&mut self, obstacles: ObstacleMask) {
+        self.drivers
+            .retain(|driver| !obstacles.is_blocked(driver.x, driver.y));
+        obstacles
+            .tiles
+            .iter()
+            .enumerate()
+            .filter(|(_, blocked)| **blocked != 0)
+            .for_each(|(index, _)| {
+                self.heights[index] = 0.0;
+                self.previous[index] = 0.0;
+            });
+        self.obstacles = obstacles;
+    }
+
+    fn set_bathymetry(&mut self, bathymetry: Bathymetry) {
+        self.bathymetry = bathymetry;
+    }
+
+    fn set_boundary(&mut self, boundary: Boundary) {
+        self.settings.boundary = boundary;
+    }
+
+    fn snapshot(&self, waves: &WaveRegistry, time: u64) -> Snapshot {
+        Snapshot {
+            time,
+            waves: waves.clone(),
+            solver: self.settings,
+            width: self.width,
+            height: self.height,
+            obstacles: self.obstacles.clone(),
+            bathymetry: self.bathymetry.clone(),
+            heights: self.heights.clone(),
+            previous: self.previous.clone(),
+            drivers: self.drivers.clone(),
+            ..Snapshot::default()
+        }
+    }
+
+    // the wave equation has a single height field, no matter the slots
+    fn height_at(&self, x: u32, y: u32, _waves: &WaveRegistry) -> f32 {
+        if x < self.width && y < self.height {
+            self.heights[(y * self.width + x) as usize]
+        } else {
+            0.0
+        }
+    }
+
+    // nor any waves in slots to be on a tile
+    fn slot_tick(&self, _slot: usize, _x: u32, _y: u32, _waves: &WaveRegistry) -> Option<f32> {
+        None
+    }
+}
@@ -1,90 +1,75 @@
+use crate::wave::backend::Backend;
 use crate::wave::camera::Camera;
-use crate::wave::constants::{MAX_RAYCAST_DISTANCE, RAYCAST_CLOSENESS_REQ, RAYCAST_RES};
+use crate::wave::config::Config;
+use crate::wave::constants::LAND_HEIGHT;
+use crate::wave::grid::Grid;
+use crate::wave::sim::{ripple_height, tile_height, SolverMode};
 use crate::wave::WaveApp;
-use cgmath::{SquareMatrix, Transform, Vector3};
-use cull_canyon::MTLTexture;
-use std::f32::consts::PI;
-use std::os::raw::c_void;
+use cgmath::Vector3;
 
-pub fn cast_ray(
+pub fn cast_ray<B: Backend>(
     projection_matrix: cgmath::Matrix4<f32>,
     camera: &Camera,
-    water: MTLTexture,
-    state: &WaveApp,
+    state: &WaveApp<B>,
 ) -> Option<Vector3<f32>> {
-    // let clip_coords = Vector3 {
-    //     x: ((mouse_pos.0 * 2.0) as f32 / display_size.0 as f32) - 1.0,
-    //     y: -(((mouse_pos.1 * 2.0) as f32 / display_size.1 as f32) - 1.0),
-    //     z: -1.0,
-    // };
-    let clip_coords = Vector3 {
-        x: 0.0,
-        y: 0.0,
-        z: -1.0,
-    };
-    let inverted_proj = projection_matrix.invert().unwrap();
-    let eye_coords = inverted_proj.transform_vector(clip_coords);
-    let eye_coords = Vector3 {
-        x: eye_coords.x,
-        y: eye_coords.y,
-        z: -1.0,
-    };
-    let inverted_view = camera.get_matrix().invert().unwrap();
-    let ray = inverted_view.transform_vector(eye_coords);
-    search(ray, camera, water, state)
+    let ray = camera.ray_through(projection_matrix, state.mouse_pos, state.window_size);
+    let lags = state.bathymetry.get_lags();
+    search(ray, camera, &state.grid, &state.config, |x, y| {
+        water_height(state, &lags, x, y)
+    })
 }
 
-fn search(
+/// The nearest of `raycast_steps` points along the ray, up to `raycast_distance` from the camera,
+/// that's within `raycast_tolerance` of the water; `height_at` gives the height of a tile.
+pub fn search<F: Fn(u32, u32) -> f32>(
     ray: Vector3<f32>,
     cam: &Camera,
-    water: MTLTexture,
-    state: &WaveApp,
+    grid: &Grid,
+    config: &Config,
+    height_at: F,
 ) -> Option<Vector3<f32>> {
-    let mut the_point: Option<Vector3<f32>> = None;
-    (0..RAYCAST_RES).for_each(|index| {
+    (0..config.raycast_steps).find_map(|index| {
         let point = get_point_on_ray(
             cam,
             ray,
-            (index * MAX_RAYCAST_DISTANCE) as f32 / RAYCAST_RES as f32,
+            (index * config.raycast_distance) as f32 / config.raycast_steps as f32,
         );
-        if (point.x < -50.0 || point.x >= 50.0) || (point.z < -50.0 || point.z > 50.0) {
-            return;
+        let (x, y) = grid.tile_at(point.x, point.z)?;
+        if (point.y - height_at(x, y)).abs() <= config.raycast_tolerance {
+            Some(point)
+        } else {
+            None
         }
-        let norm = ((point.x + 50.0) as u64, 100 - (point.z + 50.0) as u64);
-        let height = unsafe {
-            let mut b = [0u16; 4];
-            water.get_bytes(
-                b.as_mut_ptr() as *mut c_void,
-                800,
-                (norm.0, norm.1, 1, 1),
-                0,
-            );
-            b
-        };
-        let activated: Vec<u16> = height.iter().map(|el| el & 256).collect();
-        let ticks: Vec<u16> = height.iter().map(|el| el & 255).collect();
-        let heights: Vec<f32> = activated
-            .iter()
-            .enumerate()
-            .map(|is| {
-                if (*is.1 >> 8) == 1 {
-                    let pos = ticks[is.0];
-                    state.waves[is.0].amplitude_factor
-                        * (pos as f32 * (PI / state.waves[is.0].wavelength as f32)).sin()
-                } else {
-                    0.0
-                }
-            })
-            .collect();
-        let height = heights[0] + heights[1] + heights[2] + heights[3];
+    })
+}
 
-        if (point.y - height as f32).abs() <= RAYCAST_CLOSENESS_REQ {
-            the_point = Some(point);
-            return;
+fn water_height<B: Backend>(state: &WaveApp<B>, lags: &[u8], x: u32, y: u32) -> f32 {
+    let water = state.backend.as_ref().unwrap();
+    match state.solver.mode {
+        _ if state.obstacles.is_blocked(x, y) => LAND_HEIGHT,
+        SolverMode::Automaton => {
+            let tile = (0..water.get_slot_count())
+                .map(|slot| water.get_tile(slot, x, y).unwrap_or(0))
+                .collect::<Vec<u16>>();
+            let travel = (0..water.get_slot_count())
+                .map(|slot| water.get_travel(slot, x, y).unwrap_or(0))
+                .collect::<Vec<u32>>();
+            let boundary = state.solver.boundary;
+            let (width, height) = (state.grid.width, state.grid.height);
+            let sponge = boundary.sponge(x, y, width, height, state.solver.sponge_width);
+            let ripples: f32 = state
+                .ripples
+                .iter()
+                .filter_map(|r| {
+                    let wave = state.waves.get(r.slot as usize)?;
+                    let obstacles = &state.obstacles;
+                    Some(ripple_height(r, wave, boundary, x, y, obstacles, lags))
+                })
+                .sum();
+            (tile_height(&tile, &travel, &state.waves) + ripples) * (1.0 - sponge)
         }
-    });
-
-    the_point
+        SolverMode::WaveEquation => water.get_field_height(x, y).unwrap_or(0.0),
+    }
 }
 
 fn get_point_on_ray(cam: &Camera, ray: Vector3<f32>, distance: f32) -> Vector3<f32> {
@@ -0,0 +1,198 @@
+//! Input recordings: every wave definition, placement and clearing made in a session, every edge,
+//! obstacle and depth map change, and the update it was made on, so that the session can be
+//! replayed exactly on a fresh grid.
+
+use crate::wave::boundary::Boundary;
+use crate::wave::command::DepthMap;
+use crate::wave::constants::MAX_SLOTS;
+use crate::wave::obstacles::ObstacleMask;
+use crate::wave::scene::{Scene, WaveDefinition};
+use crate::wave::sim::{Solver, Wave, WaveRegistry};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Something done to the simulation after `tick` updates, and before the next one.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(tag = "event", rename_all = "lowercase")]
+pub enum Event {
+    /// A wave slot was filled, like the `wave` command does.
+    Define { tick: u64, wave: WaveDefinition },
+    /// A wave was placed on a tile of the height texture, like the `place` command does.
+    Place {
+        tick: u64,
+        x: u32,
+        y: u32,
+        slot: usize,
+    },
+    /// The waves on a tile were removed, like a right click does.
+    Clear { tick: u64, x: u32, y: u32 },
+    /// The edges were switched, like the `B` key does.
+    Boundary { tick: u64, boundary: Boundary },
+    /// Obstacles were loaded, or cleared if there is no file, like the `obstacles` command does.
+    Obstacles { tick: u64, file: Option<PathBuf> },
+    /// A depth map was loaded, like the `depth` command does.
+    Depth { tick: u64, map: DepthMap },
+}
+
+impl Event {
+    pub fn get_tick(&self) -> u64 {
+        match self {
+            Event::Define { tick, .. }
+            | Event::Place { tick, .. }
+            | Event::Clear { tick, .. }
+            | Event::Boundary { tick, .. }
+            | Event::Obstacles { tick, .. }
+            | Event::Depth { tick, .. } => *tick,
+        }
+    }
+
+    // only fails if the obstacles or depth map can't be loaded; slots are checked by `validate`
+    pub fn apply(&self, sim: &mut dyn Solver, waves: &mut WaveRegistry) -> Result<(), String> {
+        let (width, height) = sim.get_size();
+        match self {
+            Event::Define { wave, .. } => {
+                waves.set(wave.slot, wave.to_wave());
+            }
+            Event::Place { x, y, slot, .. } => sim.place(*x, *y, *slot, waves),
+            Event::Clear { x, y, .. } => sim.clear(*x, *y),
+            Event::Boundary { boundary, .. } => sim.set_boundary(*boundary),
+            Event::Obstacles { file, .. } => sim.set_obstacles(match file {
+                Some(file) => ObstacleMask::load(file, width, height)?,
+                None => ObstacleMask::open(width, height),
+            }),
+            Event::Depth { map, .. } => sim.set_bathymetry(map.load(width, height)?),
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct Recording {
+    #[serde(default)]
+    pub events: Vec<Event>,
+    // set once the solver is switched mid-session, which a replay can't follow
+    #[serde(skip)]
+    pub solver_switched: bool,
+}
+
+impl Recording {
+    pub fn from_toml(src: &str) -> Result<Recording, String> {
+        let recording: Recording = toml::from_str(src).map_err(|e| e.to_string())?;
+        recording.validate()?;
+        Ok(recording)
+    }
+
+    pub fn to_toml(&self) -> Result<String, String> {
+        toml::to_string_pretty(self).map_err(|e| e.to_string())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Recording, String> {
+        let src = std::fs::read_to_string(path.as_ref())
+            .map_err(|e| format!("Could not read {}: {}", path.as_ref().display(), e))?;
+        Recording::from_toml(&src).map_err(|e| format!("{}: {}", path.as_ref().display(), e))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        if self.solver_switched {
+            return Err(
+                "The solver was switched during this recording, so it can't be replayed; \
+                 load a snapshot to start a new one."
+                    .to_string(),
+            );
+        }
+        std::fs::write(path.as_ref(), self.to_toml()?)
+            .map_err(|e| format!("Could not write {}: {}", path.as_ref().display(), e))
+    }
+
+    // events are applied in order, so their ticks can't go back
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(pair) = self
+            .events
+            .windows(2)
+            .find(|pair| pair[1].get_tick() < pair[0].get_tick())
+        {
+            return Err(format!(
+                "Events are out of order: tick {} comes after tick {}.",
+                pair[1].get_tick(),
+                pair[0].get_tick()
+            ));
+        }
+        for event in self.events.iter() {
+            match event {
+                Event::Define { wave, .. } => wave.validate()?,
+                Event::Place { slot, .. } if *slot >= MAX_SLOTS => {
+                    return Err(format!(
+                        "Invalid wave slot {}; 0 <= slot < {}.",
+                        slot, MAX_SLOTS
+                    ));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    pub fn record_definition(&mut self, tick: u64, slot: usize, wave: &Wave) {
+        self.events.push(Event::Define {
+            tick,
+            wave: WaveDefinition::from_wave(slot, wave),
+        });
+    }
+
+    pub fn record_placement(&mut self, tick: u64, x: u32, y: u32, slot: usize) {
+        self.events.push(Event::Place { tick, x, y, slot });
+    }
+
+    pub fn record_clear(&mut self, tick: u64, x: u32, y: u32) {
+        self.events.push(Event::Clear { tick, x, y });
+    }
+
+    pub fn record_boundary(&mut self, tick: u64, boundary: Boundary) {
+        self.events.push(Event::Boundary { tick, boundary });
+    }
+
+    pub fn record_obstacles(&mut self, tick: u64, file: Option<PathBuf>) {
+        self.events.push(Event::Obstacles { tick, file });
+    }
+
+    pub fn record_depth(&mut self, tick: u64, map: DepthMap) {
+        self.events.push(Event::Depth { tick, map });
+    }
+
+    /// The events made after `tick` updates, in order.
+    pub fn events_at(&self, tick: u64) -> impl Iterator<Item = &Event> {
+        self.events
+            .iter()
+            .filter(move |event| event.get_tick() == tick)
+    }
+
+    /// Applies the events made after `tick` updates.
+    pub fn apply(
+        &self,
+        tick: u64,
+        sim: &mut dyn Solver,
+        waves: &mut WaveRegistry,
+    ) -> Result<(), String> {
+        self.events_at(tick)
+            .try_for_each(|event| event.apply(sim, waves))
+    }
+
+    /// Runs `ticks` updates of the scene with every event applied when it was made, and
+    /// returns the grid and the wave slots as they are at the end.
+    pub fn replay(
+        &self,
+        scene: &Scene,
+        ticks: u64,
+    ) -> Result<(Box<dyn Solver>, WaveRegistry), String> {
+        self.validate()?;
+        let mut waves = scene.get_waves();
+        let mut sim = scene.instantiate();
+        for tick in 0..=ticks {
+            self.apply(tick, sim.as_mut(), &mut waves)?;
+            if tick < ticks {
+                sim.step(&waves);
+            }
+        }
+        Ok((sim, waves))
+    }
+}
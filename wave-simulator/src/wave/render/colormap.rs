@@ -0,0 +1,229 @@
+//! Colouring the water by a number on every vertex in place of its texture, to read values off
+//! it: how high it is, or whether and how far on the wave in one slot is there. A legend says
+//! what the colours stand for.
+
+use crate::wave::font::{is_lit, GLYPH_HEIGHT, GLYPH_WIDTH};
+use crate::wave::grid::Grid;
+use crate::wave::mesh::surface_heights;
+use crate::wave::render::{to_rgba8, Image};
+use crate::wave::sim::{Solver, WaveRegistry};
+
+// how many colours the strip `water_frag` samples has
+pub const STRIP_WIDTH: u32 = 256;
+
+// viridis in 9 even steps, low to high
+const VIRIDIS: [[u8; 3]; 9] = [
+    [0x44, 0x01, 0x54],
+    [0x47, 0x2d, 0x7b],
+    [0x3b, 0x52, 0x8b],
+    [0x2c, 0x72, 0x8e],
+    [0x21, 0x90, 0x8c],
+    [0x27, 0xad, 0x81],
+    [0x5d, 0xc8, 0x63],
+    [0xaa, 0xdc, 0x32],
+    [0xfd, 0xe7, 0x25],
+];
+// ColorBrewer's RdBu the other way around: blue low, white in the middle, red high
+const BLUE_RED: [[u8; 3]; 5] = [
+    [0x05, 0x71, 0xb0],
+    [0x92, 0xc5, 0xde],
+    [0xf7, 0xf7, 0xf7],
+    [0xf4, 0xa5, 0x82],
+    [0xca, 0x00, 0x20],
+];
+
+const BACKGROUND: [u8; 4] = [0, 0, 0, 160];
+const TEXT: [u8; 4] = [255, 255, 255, 255];
+// the legend's size, in pixels
+const LEGEND_WIDTH: u32 = 128;
+const LEGEND_HEIGHT: u32 = 32;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Colormap {
+    Viridis,
+    // diverging around the middle of the range, for values either way of 0
+    BlueRed,
+}
+
+impl Colormap {
+    pub fn parse(name: &str) -> Result<Colormap, String> {
+        match name {
+            "viridis" => Ok(Colormap::Viridis),
+            "blue-red" => Ok(Colormap::BlueRed),
+            _ => Err(format!(
+                "Unknown colormap {}; it's viridis or blue-red.",
+                name
+            )),
+        }
+    }
+
+    fn stops(&self) -> &'static [[u8; 3]] {
+        match self {
+            Colormap::Viridis => &VIRIDIS,
+            Colormap::BlueRed => &BLUE_RED,
+        }
+    }
+
+    /// The colour `t` of the way from the low end to the high one; past the ends are the ends.
+    pub fn colour(&self, t: f32) -> [f32; 4] {
+        let stops = self.stops();
+        let along = t.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+        let i = (along as usize).min(stops.len() - 2);
+        let (from, to) = (stops[i], stops[i + 1]);
+        let mix = |c: usize| {
+            (from[c] as f32 + (to[c] as f32 - from[c] as f32) * (along - i as f32)) / 255.0
+        };
+        [mix(0), mix(1), mix(2), 1.0]
+    }
+
+    /// The colormap low to high, one pixel high, as `water_frag` samples it.
+    pub fn strip(&self) -> Image {
+        let mut image = Image::new(STRIP_WIDTH, 1, [0, 0, 0, 255]);
+        (0..STRIP_WIDTH).for_each(|x| {
+            let t = (x as f32 + 0.5) / STRIP_WIDTH as f32;
+            image.set(x, 0, to_rgba8(self.colour(t)));
+        });
+        image
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Quantity {
+    Height,
+    // 1 where the wave in the slot is, 0 elsewhere
+    Activation,
+    // how far through its half sine the wave in the slot is, in updates; 0 where it isn't
+    Tick,
+}
+
+impl Quantity {
+    pub fn parse(name: &str) -> Result<Quantity, String> {
+        match name {
+            "height" => Ok(Quantity::Height),
+            "activation" => Ok(Quantity::Activation),
+            "tick" => Ok(Quantity::Tick),
+            _ => Err(format!(
+                "Unknown quantity {}; it's height, activation or tick.",
+                name
+            )),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Quantity::Height => "height",
+            Quantity::Activation => "activation",
+            Quantity::Tick => "tick",
+        }
+    }
+}
+
+/// What the water is coloured by, and how.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Mapping {
+    pub quantity: Quantity,
+    // the wave slot activation and ticks are of
+    pub slot: usize,
+    pub colormap: Colormap,
+    // the values at the low and high ends of the colormap
+    pub range: (f32, f32),
+}
+
+impl Mapping {
+    /// `quantity` in the colormap that suits it, over all it can be: heights either way of the
+    /// largest amplitude in blue-red, and the rest in viridis, up to 1 or the slot's wavelength.
+    pub fn new(quantity: Quantity, slot: usize, waves: &WaveRegistry) -> Mapping {
+        let (colormap, range) = match quantity {
+            Quantity::Height => {
+                let largest = waves
+                    .iter()
+                    .map(|wave| wave.amplitude_factor.abs())
+                    .fold(0.0, f32::max);
+                let largest = if largest > 0.0 { largest } else { 1.0 };
+                (Colormap::BlueRed, (-largest, largest))
+            }
+            Quantity::Activation => (Colormap::Viridis, (0.0, 1.0)),
+            Quantity::Tick => {
+                let wavelength = waves.get(slot).map_or(0, |wave| wave.wavelength);
+                (Colormap::Viridis, (0.0, wavelength.max(1) as f32))
+            }
+        };
+        Mapping {
+            quantity,
+            slot,
+            colormap,
+            range,
+        }
+    }
+
+    /// The quantity on every vertex, row by row as the `Grid` lays vertices out.
+    pub fn values(&self, sim: &dyn Solver, waves: &WaveRegistry) -> Vec<f32> {
+        let (width, height) = sim.get_size();
+        let grid = Grid::new(width, height);
+        let tick = |x: u32, z: u32| {
+            let (x, y) = grid.tile_of_vertex(x, z);
+            sim.slot_tick(self.slot, x, y, waves)
+        };
+        let vertices = (0..height).flat_map(|z| (0..width).map(move |x| (x, z)));
+        match self.quantity {
+            Quantity::Height => surface_heights(sim, waves),
+            Quantity::Activation => vertices
+                .map(|(x, z)| tick(x, z).map_or(0.0, |_| 1.0))
+                .collect(),
+            Quantity::Tick => vertices.map(|(x, z)| tick(x, z).unwrap_or(0.0)).collect(),
+        }
+    }
+
+    /// How far from the low end of the range to the high one `value` is, 0 to 1 inside it.
+    pub fn position(&self, value: f32) -> f32 {
+        (value - self.range.0) / (self.range.1 - self.range.0)
+    }
+
+    pub fn colour(&self, value: f32) -> [f32; 4] {
+        self.colormap.colour(self.position(value))
+    }
+
+    /// What's shown, over the colormap with the values at its ends, to draw on the window.
+    pub fn legend(&self) -> Image {
+        let mut image = Image::new(LEGEND_WIDTH, LEGEND_HEIGHT, BACKGROUND);
+        let title = match self.quantity {
+            Quantity::Height => self.quantity.name().to_string(),
+            _ => format!("{}, slot {}", self.quantity.name(), self.slot),
+        };
+        write(&mut image, 3, 3, &title);
+        let (left, right) = (3, LEGEND_WIDTH - 3);
+        for x in left..right {
+            let t = (x - left) as f32 / (right - left - 1) as f32;
+            let colour = to_rgba8(self.colormap.colour(t));
+            (13..20).for_each(|y| image.set(x, y, colour));
+        }
+        let (low, high) = (label(self.range.0), label(self.range.1));
+        write(&mut image, left, 22, &low);
+        let high_width = high.len() as u32 * (GLYPH_WIDTH as u32 + 1) - 1;
+        write(&mut image, right.saturating_sub(high_width), 22, &high);
+        image
+    }
+}
+
+// at most 9 characters, so both ends fit under the bar
+fn label(value: f32) -> String {
+    match value.abs() < 1e4 {
+        true => format!("{:.2}", value),
+        false => format!("{:.3e}", value),
+    }
+}
+
+// text from its top left corner at (x, y), cut off at the edges of the image
+fn write(image: &mut Image, x: u32, y: u32, text: &str) {
+    for (i, c) in text.chars().enumerate() {
+        for dy in 0..GLYPH_HEIGHT {
+            for dx in 0..GLYPH_WIDTH {
+                let px = x + (i * (GLYPH_WIDTH + 1) + dx) as u32;
+                let py = y + dy as u32;
+                if is_lit(c, dx, dy) && px < image.width && py < image.height {
+                    image.set(px, py, TEXT);
+                }
+            }
+        }
+    }
+}
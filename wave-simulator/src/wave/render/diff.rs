@@ -0,0 +1,95 @@
+//! Telling whether two renders look the same. Pixels are compared by how different they look,
+//! the way pixelmatch does it, in YIQ with brightness weighed over hue, so renders made on
+//! other machines, a rounding off here and there, still pass.
+
+use crate::wave::render::Image;
+
+// the largest difference two colours can have, black against white
+const LARGEST: f32 = 35215.0;
+
+/// How much two images may differ and still count as the same.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Tolerance {
+    // how different a pixel may look, from 0 to 1, before it counts as changed
+    pub colour: f32,
+    // the fraction of the pixels that may change
+    pub pixels: f32,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Tolerance {
+            colour: 0.1,
+            pixels: 0.001,
+        }
+    }
+}
+
+pub struct Difference {
+    // the pixels that look changed
+    pub changed: usize,
+    pub total: usize,
+    /// The expected image faded out, with the changed pixels in red.
+    pub image: Image,
+}
+
+impl Difference {
+    pub fn is_within(&self, tolerance: &Tolerance) -> bool {
+        self.changed as f32 <= tolerance.pixels * self.total as f32
+    }
+}
+
+/// How different two colours look, from 0 to 1; the alpha blends them over white.
+pub fn perceived_difference(a: [u8; 4], b: [u8; 4]) -> f32 {
+    let yiq = |c: [u8; 4]| {
+        let alpha = c[3] as f32 / 255.0;
+        let [r, g, b] = [c[0], c[1], c[2]].map(|c| 255.0 + (c as f32 - 255.0) * alpha);
+        [
+            r * 0.298_895_3 + g * 0.586_622_5 + b * 0.114_482_2,
+            r * 0.595_978 - g * 0.274_176_2 - b * 0.321_801_8,
+            r * 0.211_470_2 - g * 0.522_617_2 + b * 0.311_147_1,
+        ]
+    };
+    let (a, b) = (yiq(a), yiq(b));
+    let (y, i, q) = (a[0] - b[0], a[1] - b[1], a[2] - b[2]);
+    ((0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q) / LARGEST)
+        .sqrt()
+        .min(1.0)
+}
+
+/// Compares what was rendered with what was expected, pixel by pixel; an error if they aren't
+/// the same size.
+pub fn compare(
+    expected: &Image,
+    actual: &Image,
+    tolerance: &Tolerance,
+) -> Result<Difference, String> {
+    if (expected.width, expected.height) != (actual.width, actual.height) {
+        return Err(format!(
+            "Expected a {}x{} image, not {}x{}.",
+            expected.width, expected.height, actual.width, actual.height
+        ));
+    }
+    let mut image = Image::new(expected.width, expected.height, [0, 0, 0, 255]);
+    let mut changed = 0;
+    for y in 0..expected.height {
+        for x in 0..expected.width {
+            let (a, b) = (expected.get(x, y), actual.get(x, y));
+            if perceived_difference(a, b) > tolerance.colour {
+                changed += 1;
+                image.set(x, y, [255, 0, 0, 255]);
+            } else {
+                // what was expected, in a light gray, to see where the changes are
+                let luma =
+                    (a[0] as f32 * 0.299 + a[1] as f32 * 0.587 + a[2] as f32 * 0.114) / 255.0;
+                let faded = (255.0 - (1.0 - luma) * 25.0).round() as u8;
+                image.set(x, y, [faded, faded, faded, 255]);
+            }
+        }
+    }
+    Ok(Difference {
+        changed,
+        total: (expected.width * expected.height) as usize,
+        image,
+    })
+}
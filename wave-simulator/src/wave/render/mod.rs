@@ -0,0 +1,288 @@
+//! Drawing the water. `Renderer` is what a frame needs from a graphics backend: the app draws
+//! with `bundles::frame::MetalFrame`, and `software::SoftwareRenderer` does it on the CPU into
+//! an RGBA image, so frames can be made and looked at on machines without Metal. The water is
+//! either textured and lit, or coloured by a `colormap::Mapping` to read values off it.
+
+use crate::wave::grid::Grid;
+use crate::wave::mesh::{surface_heights, vertex_normals};
+use crate::wave::render::colormap::Mapping;
+use crate::wave::sim::{Solver, WaveRegistry};
+use cgmath::{InnerSpace, Matrix4, Vector3};
+use std::fs::File;
+use std::io::{BufWriter, Read};
+use std::path::Path;
+
+pub mod colormap;
+pub mod diff;
+pub mod software;
+
+// LAND_COLOUR in shaders.metal
+pub const LAND_COLOUR: [f32; 4] = [0.76, 0.70, 0.50, 1.0];
+// what static_frag draws the marker in
+pub const MARKER_COLOUR: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+
+/// The corners of the marker, a unit cube around the origin, four to a face.
+pub const MARKER_CORNERS: [[f32; 3]; 24] = [
+    [-0.5, 0.5, -0.5],
+    [-0.5, -0.5, -0.5],
+    [0.5, -0.5, -0.5],
+    [0.5, 0.5, -0.5],
+    [-0.5, 0.5, 0.5],
+    [-0.5, -0.5, 0.5],
+    [0.5, -0.5, 0.5],
+    [0.5, 0.5, 0.5],
+    [0.5, 0.5, -0.5],
+    [0.5, -0.5, -0.5],
+    [0.5, -0.5, 0.5],
+    [0.5, 0.5, 0.5],
+    [-0.5, 0.5, -0.5],
+    [-0.5, -0.5, -0.5],
+    [-0.5, -0.5, 0.5],
+    [-0.5, 0.5, 0.5],
+    [-0.5, 0.5, 0.5],
+    [-0.5, 0.5, -0.5],
+    [0.5, 0.5, -0.5],
+    [0.5, 0.5, 0.5],
+    [-0.5, -0.5, 0.5],
+    [-0.5, -0.5, -0.5],
+    [0.5, -0.5, -0.5],
+    [0.5, -0.5, 0.5],
+];
+
+/// The marker's triangles, two to a face.
+pub const MARKER_INDICES: [u32; 36] = [
+    0, 1, 3, 3, 1, 2, 4, 5, 7, 7, 5, 6, 8, 9, 11, 11, 9, 10, 12, 13, 15, 15, 13, 14, 16, 17, 19,
+    19, 17, 18, 20, 21, 23, 23, 21, 22,
+];
+
+/// The water as it's drawn: how high every vertex is and whether it's land, row by row as the
+/// `Grid` lays vertices out.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Surface {
+    pub grid: Grid,
+    pub heights: Vec<f32>,
+    pub land: Vec<bool>,
+    // what a colormap shows on every vertex (see `Mapping::values`); the heights unless it's set
+    pub values: Vec<f32>,
+}
+
+impl Surface {
+    /// The water as the solver has it now, with tiles `cell_size` apart.
+    pub fn new(sim: &dyn Solver, waves: &WaveRegistry, cell_size: f32) -> Surface {
+        let (width, height) = sim.get_size();
+        let grid = Grid {
+            width,
+            height,
+            cell_size,
+        };
+        let obstacles = sim.get_obstacles();
+        let land = (0..height)
+            .flat_map(|z| (0..width).map(move |x| grid.tile_of_vertex(x, z)))
+            .map(|(x, y)| obstacles.is_blocked(x, y))
+            .collect();
+        let heights = surface_heights(sim, waves);
+        Surface {
+            grid,
+            values: heights.clone(),
+            heights,
+            land,
+        }
+    }
+
+    /// The surface's normal at every vertex, from the slopes between its neighbours.
+    pub fn normals(&self) -> Vec<[f32; 3]> {
+        vertex_normals(
+            self.grid.width,
+            self.grid.height,
+            self.grid.cell_size,
+            &self.heights,
+        )
+    }
+
+    /// Flat water everywhere.
+    pub fn flat(grid: Grid) -> Surface {
+        Surface {
+            grid,
+            heights: vec![0.0; grid.get_len()],
+            land: vec![false; grid.get_len()],
+            values: vec![0.0; grid.get_len()],
+        }
+    }
+}
+
+/// A directional light on the water, as `water_frag` shades it.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Light {
+    // towards the light
+    pub direction: Vector3<f32>,
+    pub colour: [f32; 3],
+    // how much of the colour is left in the shade, and how much more facing the light adds
+    pub ambient: f32,
+    pub diffuse: f32,
+    // the highlight where the light glints off towards the eye, and how tight it is
+    pub specular: f32,
+    pub shininess: f32,
+    // how far the water turns to the light's colour when seen edge on
+    pub fresnel: f32,
+}
+
+impl Light {
+    /// No shading at all; everything keeps its colour.
+    pub fn unlit() -> Light {
+        Light {
+            direction: Vector3::unit_y(),
+            colour: [1.0, 1.0, 1.0],
+            ambient: 1.0,
+            diffuse: 0.0,
+            specular: 0.0,
+            shininess: 1.0,
+            fresnel: 0.0,
+        }
+    }
+
+    /// `colour` lit on a surface facing `normal`, seen from `to_eye`; alpha is kept.
+    pub fn shade(&self, colour: [f32; 4], normal: Vector3<f32>, to_eye: Vector3<f32>) -> [f32; 4] {
+        let (n, l, v) = (
+            normal.normalize(),
+            self.direction.normalize(),
+            to_eye.normalize(),
+        );
+        let lambert = n.dot(l).max(0.0);
+        let brightness = self.ambient + self.diffuse * lambert;
+        // Schlick's approximation, scaled down
+        let fresnel = self.fresnel * (1.0 - n.dot(v).max(0.0)).powi(5);
+        let specular = match lambert > 0.0 {
+            true => self.specular * n.dot((l + v).normalize()).max(0.0).powf(self.shininess),
+            false => 0.0,
+        };
+        let channel = |i: usize| {
+            let lit = colour[i] * brightness * self.colour[i];
+            lit + (self.colour[i] - lit) * fresnel + self.colour[i] * specular
+        };
+        [channel(0), channel(1), channel(2), colour[3]]
+    }
+}
+
+/// How the water is coloured.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Shading {
+    /// Textured, and lit by the light.
+    Water(Light),
+    /// The surface's values through a colormap, unlit.
+    Mapped(Mapping),
+}
+
+/// What a frame of the water is drawn with, whatever draws it.
+pub trait Renderer {
+    /// Replaces the water drawn with `surface`.
+    fn upload_surface(&mut self, surface: &Surface);
+    /// Starts a frame filled with `colour`, with nothing in front of anything yet.
+    fn clear(&mut self, colour: [f32; 4]);
+    /// Draws the water, seen through `view` and `projection`, coloured by `shading`.
+    fn draw_water(&mut self, projection: Matrix4<f32>, view: Matrix4<f32>, shading: &Shading);
+    /// Draws the marker for what the mouse points at, centred on `position`.
+    fn draw_marker(&mut self, projection: Matrix4<f32>, view: Matrix4<f32>, position: Vector3<f32>);
+    /// Draws `image` over everything, blended by its alpha, on `[centre x, centre y, half width,
+    /// half height]` of clip space.
+    fn draw_quad(&mut self, quad: [f32; 4], image: &Image);
+}
+
+/// An RGBA image, 8 bits a channel, row by row from the top.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    pub fn new(width: u32, height: u32, colour: [u8; 4]) -> Image {
+        Image {
+            width,
+            height,
+            pixels: colour
+                .iter()
+                .copied()
+                .cycle()
+                .take(width as usize * height as usize * 4)
+                .collect(),
+        }
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> [u8; 4] {
+        let at = (y as usize * self.width as usize + x as usize) * 4;
+        [
+            self.pixels[at],
+            self.pixels[at + 1],
+            self.pixels[at + 2],
+            self.pixels[at + 3],
+        ]
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, colour: [u8; 4]) {
+        let at = (y as usize * self.width as usize + x as usize) * 4;
+        self.pixels[at..at + 4].copy_from_slice(&colour);
+    }
+
+    /// Decodes a PNG; gray and RGB images are made opaque RGBA.
+    pub fn read_png<R: Read>(reader: R) -> Result<Image, String> {
+        let decoder = png::Decoder::new(reader);
+        let (info, mut reader) = decoder.read_info().map_err(|e| e.to_string())?;
+        if info.bit_depth != png::BitDepth::Eight {
+            return Err("Only 8-bit PNGs can be read.".to_string());
+        }
+        let mut img = vec![0; info.buffer_size()];
+        reader.next_frame(&mut img).map_err(|e| e.to_string())?;
+        let pixels = img
+            .chunks(info.color_type.samples())
+            .flat_map(|pixel| match *pixel {
+                [gray] => [gray, gray, gray, 255],
+                [gray, alpha] => [gray, gray, gray, alpha],
+                [r, g, b] => [r, g, b, 255],
+                [r, g, b, a] => [r, g, b, a],
+                _ => [0, 0, 0, 255],
+            })
+            .collect();
+        Ok(Image {
+            width: info.width,
+            height: info.height,
+            pixels,
+        })
+    }
+
+    /// The crosshair drawn on the mouse.
+    pub fn crosshair() -> Image {
+        Image::read_png(include_bytes!("../bundles/crosshair.png") as &[u8])
+            .expect("crosshair.png is an 8-bit PNG")
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Image, String> {
+        let file = File::open(path.as_ref())
+            .map_err(|e| format!("Could not read {}: {}", path.as_ref().display(), e))?;
+        Image::read_png(file).map_err(|e| format!("{}: {}", path.as_ref().display(), e))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        let file =
+            File::create(path).map_err(|e| format!("Could not write {}: {}", path.display(), e))?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), self.width, self.height);
+        encoder.set_color(png::ColorType::RGBA);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&self.pixels))
+            .map_err(|e| format!("Could not write {}: {}", path.display(), e))
+    }
+}
+
+/// A colour from 0-1 channels to 0-255 ones.
+pub fn to_rgba8(colour: [f32; 4]) -> [u8; 4] {
+    let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+    [
+        channel(colour[0]),
+        channel(colour[1]),
+        channel(colour[2]),
+        channel(colour[3]),
+    ]
+}
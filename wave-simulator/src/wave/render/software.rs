@@ -0,0 +1,359 @@
+//! A rasterizer on the CPU that draws what `water_vert` and `water_frag` do: the grid's
+//! triangles displaced by the heights, depth tested, with land in `LAND_COLOUR` and the water
+//! sampled from `thatiswater.png` the way the app's default sampler does, nearest and clamped,
+//! then lit by `Light::shade` with the normals interpolated across every triangle, or coloured
+//! by a `Mapping` of the surface's values.
+//! The marker and the quads on top are drawn like `static_frag` and `ui_frag` draw them.
+
+use crate::wave::camera::Camera;
+use crate::wave::config::Config;
+use crate::wave::grid::Grid;
+use crate::wave::mesh::Mesh;
+use crate::wave::render::colormap::Mapping;
+use crate::wave::render::{
+    to_rgba8, Image, Light, Renderer, Shading, Surface, LAND_COLOUR, MARKER_COLOUR, MARKER_CORNERS,
+    MARKER_INDICES,
+};
+use crate::wave::sim::{Solver, WaveRegistry};
+use crate::wave::util::generate_transformation;
+use cgmath::{Matrix4, SquareMatrix, Vector3, Vector4};
+
+// a vertex after the projection, and what's interpolated across triangles
+#[derive(Clone, Copy, Debug)]
+struct ClipVertex {
+    position: Vector4<f32>,
+    uv: [f32; 2],
+    land: f32,
+    value: f32,
+    normal: Vector3<f32>,
+    // where it is in the world, for the way to the eye
+    world: Vector3<f32>,
+}
+
+impl ClipVertex {
+    fn lerp(&self, other: &ClipVertex, t: f32) -> ClipVertex {
+        let mix = |a: f32, b: f32| a + (b - a) * t;
+        ClipVertex {
+            position: self.position + (other.position - self.position) * t,
+            uv: [mix(self.uv[0], other.uv[0]), mix(self.uv[1], other.uv[1])],
+            land: mix(self.land, other.land),
+            value: mix(self.value, other.value),
+            normal: self.normal + (other.normal - self.normal) * t,
+            world: self.world + (other.world - self.world) * t,
+        }
+    }
+
+    // how far in front of the near plane, where z = -w
+    fn near_distance(&self) -> f32 {
+        self.position.z + self.position.w
+    }
+}
+
+/// Cuts off the part of a triangle behind the near plane, leaving up to two triangles' worth
+/// of corners in order.
+fn clip_near(triangle: [ClipVertex; 3]) -> Vec<ClipVertex> {
+    let mut corners = Vec::with_capacity(4);
+    (0..3).for_each(|i| {
+        let (a, b) = (&triangle[i], &triangle[(i + 1) % 3]);
+        let (da, db) = (a.near_distance(), b.near_distance());
+        if da >= 0.0 {
+            corners.push(*a);
+        }
+        if (da >= 0.0) != (db >= 0.0) {
+            corners.push(a.lerp(b, da / (da - db)));
+        }
+    });
+    corners
+}
+
+// what a triangle is filled with: one colour, the water lit from where the eye is, or its values
+// through a colormap
+#[derive(Clone, Copy)]
+enum Fill {
+    Solid([u8; 4]),
+    Water(Light, Vector3<f32>),
+    Mapped(Mapping),
+}
+
+// twice the signed area of the triangle (a, b, p)
+fn edge(a: [f32; 2], b: [f32; 2], p: [f32; 2]) -> f32 {
+    (b[0] - a[0]) * (p[1] - a[1]) - (b[1] - a[1]) * (p[0] - a[0])
+}
+
+pub struct SoftwareRenderer {
+    pub frame: Image,
+    // normalised device depth of what's drawn on every pixel, nearer is lower
+    depth: Vec<f32>,
+    texture: Image,
+    mesh: Option<Mesh>,
+    land: Vec<f32>,
+    values: Vec<f32>,
+}
+
+impl SoftwareRenderer {
+    /// A renderer drawing into a `width` by `height` frame.
+    pub fn new(width: u32, height: u32) -> SoftwareRenderer {
+        let texture = Image::read_png(include_bytes!("../bundles/thatiswater.png") as &[u8])
+            .expect("thatiswater.png is an 8-bit PNG");
+        SoftwareRenderer {
+            frame: Image::new(width, height, [0, 0, 0, 255]),
+            depth: vec![f32::INFINITY; width as usize * height as usize],
+            texture,
+            mesh: None,
+            land: vec![],
+            values: vec![],
+        }
+    }
+
+    /// The water as the app's window would show it, `width` by `height`, from `camera` or
+    /// `Camera::overview` without one, with the far plane pushed back to see all of `grid`.
+    pub fn render(
+        sim: &dyn Solver,
+        waves: &WaveRegistry,
+        grid: &Grid,
+        camera: Option<Camera>,
+        (width, height): (u32, u32),
+    ) -> Image {
+        let extent = grid.width.max(grid.height) as f32 * grid.cell_size;
+        let default = Config::default();
+        let config = Config {
+            far_plane: default.far_plane.max(extent * 2.0),
+            ..default
+        };
+        let camera = camera.unwrap_or_else(|| Camera::overview(grid));
+        let mut renderer = SoftwareRenderer::new(width, height);
+        renderer.upload_surface(&Surface::new(sim, waves, grid.cell_size));
+        renderer.clear([0.0, 0.0, 0.0, 1.0]);
+        renderer.draw_water(
+            config.projection_matrix(width as f32 / height as f32),
+            camera.get_matrix(),
+            &Shading::Water(config.light()),
+        );
+        renderer.frame
+    }
+
+    fn sample(&self, uv: [f32; 2]) -> [u8; 4] {
+        let texel = |t: f32, size: u32| ((t * size as f32).floor().max(0.0) as u32).min(size - 1);
+        self.texture.get(
+            texel(uv[0], self.texture.width),
+            texel(uv[1], self.texture.height),
+        )
+    }
+
+    fn draw_triangle(&mut self, triangle: [ClipVertex; 3], fill: Fill) {
+        let (width, height) = (self.frame.width as f32, self.frame.height as f32);
+        // to pixels, y down
+        let screen = triangle.map(|v| {
+            let w = v.position.w;
+            [
+                (v.position.x / w + 1.0) / 2.0 * width,
+                (1.0 - v.position.y / w) / 2.0 * height,
+                v.position.z / w,
+                1.0 / w,
+            ]
+        });
+        let [a, b, c] = screen.map(|s| [s[0], s[1]]);
+        let area = edge(a, b, c);
+        if area == 0.0 || !area.is_finite() {
+            return;
+        }
+        let min = |i: usize| screen.iter().map(|s| s[i]).fold(f32::INFINITY, f32::min);
+        let max = |i: usize| {
+            screen
+                .iter()
+                .map(|s| s[i])
+                .fold(f32::NEG_INFINITY, f32::max)
+        };
+        let (left, right) = (
+            min(0).max(0.0) as u32,
+            max(0).min(width - 1.0).max(0.0) as u32,
+        );
+        let (top, bottom) = (
+            min(1).max(0.0) as u32,
+            max(1).min(height - 1.0).max(0.0) as u32,
+        );
+
+        for y in top..=bottom {
+            for x in left..=right {
+                let p = [x as f32 + 0.5, y as f32 + 0.5];
+                let weights = [
+                    edge(b, c, p) / area,
+                    edge(c, a, p) / area,
+                    edge(a, b, p) / area,
+                ];
+                if weights.iter().any(|w| *w < 0.0) {
+                    continue;
+                }
+                let blend = |value: &dyn Fn(usize) -> f32| {
+                    (0..3).map(|i| weights[i] * value(i)).sum::<f32>()
+                };
+                let depth = blend(&|i| screen[i][2]);
+                let index = (y * self.frame.width + x) as usize;
+                if depth > 1.0 || depth >= self.depth[index] {
+                    continue;
+                }
+                // attributes are interpolated in the world, not on the screen
+                let inverse_w = blend(&|i| screen[i][3]);
+                let correct =
+                    |value: &dyn Fn(usize) -> f32| blend(&|i| value(i) * screen[i][3]) / inverse_w;
+                let land = correct(&|i| triangle[i].land);
+                let colour = match fill {
+                    Fill::Solid(colour) => colour,
+                    Fill::Water(..) | Fill::Mapped(..) if land >= 0.5 => to_rgba8(LAND_COLOUR),
+                    Fill::Mapped(mapping) => {
+                        to_rgba8(mapping.colour(correct(&|i| triangle[i].value)))
+                    }
+                    Fill::Water(light, eye) => {
+                        let texel = self.sample([
+                            correct(&|i| triangle[i].uv[0]),
+                            correct(&|i| triangle[i].uv[1]),
+                        ]);
+                        let vector = |value: &dyn Fn(usize) -> Vector3<f32>| {
+                            Vector3::new(
+                                correct(&|i| value(i).x),
+                                correct(&|i| value(i).y),
+                                correct(&|i| value(i).z),
+                            )
+                        };
+                        let normal = vector(&|i| triangle[i].normal);
+                        let world = vector(&|i| triangle[i].world);
+                        to_rgba8(light.shade(texel.map(|c| c as f32 / 255.0), normal, eye - world))
+                    }
+                };
+                self.depth[index] = depth;
+                self.frame.set(x, y, colour);
+            }
+        }
+    }
+
+    // clips and draws the triangles `indices` picks out of `vertices`
+    fn draw_triangles(&mut self, vertices: &[ClipVertex], indices: &[u32], fill: Fill) {
+        for triangle in indices.chunks(3) {
+            let corners = clip_near([
+                vertices[triangle[0] as usize],
+                vertices[triangle[1] as usize],
+                vertices[triangle[2] as usize],
+            ]);
+            // a fan over what's left
+            (1..corners.len().saturating_sub(1)).for_each(|i| {
+                self.draw_triangle([corners[0], corners[i], corners[i + 1]], fill);
+            });
+        }
+    }
+}
+
+impl Renderer for SoftwareRenderer {
+    fn upload_surface(&mut self, surface: &Surface) {
+        self.mesh = Some(Mesh::from_heights(&surface.grid, &surface.heights));
+        self.land = surface
+            .land
+            .iter()
+            .map(|land| if *land { 1.0 } else { 0.0 })
+            .collect();
+        self.values = surface.values.clone();
+    }
+
+    fn clear(&mut self, colour: [f32; 4]) {
+        self.frame = Image::new(self.frame.width, self.frame.height, to_rgba8(colour));
+        self.depth
+            .iter_mut()
+            .for_each(|depth| *depth = f32::INFINITY);
+    }
+
+    fn draw_water(&mut self, projection: Matrix4<f32>, view: Matrix4<f32>, shading: &Shading) {
+        let mesh = match self.mesh.take() {
+            Some(mesh) => mesh,
+            None => return,
+        };
+        let transform = projection * view;
+        // the camera is where the view takes the origin from
+        let eye = view
+            .invert()
+            .map_or(Vector3::unit_y(), |inverse| inverse.w.truncate());
+        let vertices = mesh
+            .positions
+            .iter()
+            .zip(mesh.normals.iter())
+            .zip(mesh.uvs.iter())
+            .zip(self.land.iter().zip(self.values.iter()))
+            .map(|(((p, normal), uv), (land, value))| ClipVertex {
+                position: transform * Vector4::new(p[0], p[1], p[2], 1.0),
+                uv: *uv,
+                land: *land,
+                value: *value,
+                normal: (*normal).into(),
+                world: (*p).into(),
+            })
+            .collect::<Vec<ClipVertex>>();
+        let fill = match shading {
+            Shading::Water(light) => Fill::Water(*light, eye),
+            Shading::Mapped(mapping) => Fill::Mapped(*mapping),
+        };
+        self.draw_triangles(&vertices, &mesh.indices, fill);
+        self.mesh = Some(mesh);
+    }
+
+    fn draw_marker(
+        &mut self,
+        projection: Matrix4<f32>,
+        view: Matrix4<f32>,
+        position: Vector3<f32>,
+    ) {
+        let transform =
+            projection * view * generate_transformation(position, (0.0, 0.0, 0.0), (1.0, 1.0, 1.0));
+        let vertices = MARKER_CORNERS
+            .iter()
+            .map(|p| ClipVertex {
+                position: transform * Vector4::new(p[0], p[1], p[2], 1.0),
+                uv: [0.0, 0.0],
+                land: 0.0,
+                value: 0.0,
+                normal: Vector3::unit_y(),
+                world: Vector3::new(0.0, 0.0, 0.0),
+            })
+            .collect::<Vec<ClipVertex>>();
+        let colour = to_rgba8(MARKER_COLOUR);
+        self.draw_triangles(&vertices, &MARKER_INDICES, Fill::Solid(colour));
+    }
+
+    fn draw_quad(&mut self, quad: [f32; 4], image: &Image) {
+        let (width, height) = (self.frame.width as f32, self.frame.height as f32);
+        // the quad's edges in pixels, y down
+        let left = (quad[0] - quad[2] + 1.0) / 2.0 * width;
+        let right = (quad[0] + quad[2] + 1.0) / 2.0 * width;
+        let top = (1.0 - quad[1] - quad[3]) / 2.0 * height;
+        let bottom = (1.0 - quad[1] + quad[3]) / 2.0 * height;
+        if right <= left || bottom <= top {
+            return;
+        }
+        let texel = |t: f32, size: u32| ((t * size as f32).floor().max(0.0) as u32).min(size - 1);
+        let first = |edge: f32| (edge - 0.5).ceil().max(0.0) as u32;
+        let (columns, rows) = (
+            first(left)..first(right).min(self.frame.width),
+            first(top)..first(bottom).min(self.frame.height),
+        );
+        for y in rows {
+            let v = (y as f32 + 0.5 - top) / (bottom - top);
+            for x in columns.clone() {
+                let u = (x as f32 + 0.5 - left) / (right - left);
+                let source = image.get(texel(u, image.width), texel(v, image.height));
+                let behind = self.frame.get(x, y);
+                // over what's there by the alpha, the way the UI pipeline blends
+                let alpha = source[3] as f32 / 255.0;
+                let mix = |s: u8, d: u8, weight: f32| {
+                    (s as f32 * weight + d as f32 * (1.0 - alpha)).round() as u8
+                };
+                self.frame.set(
+                    x,
+                    y,
+                    [
+                        mix(source[0], behind[0], alpha),
+                        mix(source[1], behind[1], alpha),
+                        mix(source[2], behind[2], alpha),
+                        mix(source[3], behind[3], 1.0),
+                    ],
+                );
+            }
+        }
+    }
+}
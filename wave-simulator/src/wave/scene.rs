@@ -0,0 +1,312 @@
+//! Scene descriptions: which waves fill which slots, where waves have been placed, and how the
+//! water and the camera are set up.
+
+use crate::wave::bathymetry::Bathymetry;
+use crate::wave::camera::Camera;
+use crate::wave::constants::MAX_SLOTS;
+use crate::wave::grid::Grid;
+use crate::wave::obstacles::ObstacleMask;
+use crate::wave::sim::{
+    Solver, SolverSettings, Wave, WaveKind, WaveRegistry, DOWN, LEFT, MAX_LIFETIME, RIGHT, UP,
+};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ];
+
+    pub fn get_bit(&self) -> u8 {
+        match self {
+            Direction::Up => UP,
+            Direction::Down => DOWN,
+            Direction::Left => LEFT,
+            Direction::Right => RIGHT,
+        }
+    }
+}
+
+/// The contents of one wave slot.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct WaveDefinition {
+    pub slot: usize,
+    pub amplitude: f32,
+    pub wavelength: u8,
+    #[serde(default)]
+    pub directions: Vec<Direction>,
+    #[serde(default)]
+    pub kind: WaveKind,
+    // in degrees, for plane waves
+    #[serde(default)]
+    pub heading: f32,
+    // see `Wave`
+    #[serde(default)]
+    pub decay: f32,
+    #[serde(default)]
+    pub falloff: f32,
+    #[serde(default)]
+    pub lifetime: u32,
+}
+
+impl WaveDefinition {
+    pub fn from_wave(slot: usize, wave: &Wave) -> WaveDefinition {
+        WaveDefinition {
+            slot,
+            amplitude: wave.amplitude_factor,
+            wavelength: wave.wavelength,
+            directions: Direction::ALL
+                .iter()
+                .filter(|d| wave.directions & d.get_bit() != 0)
+                .copied()
+                .collect(),
+            kind: wave.kind,
+            heading: wave.heading,
+            decay: wave.decay,
+            falloff: wave.falloff,
+            lifetime: wave.lifetime,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.slot >= MAX_SLOTS {
+            return Err(format!(
+                "Invalid wave slot {}; 0 <= slot < {}.",
+                self.slot, MAX_SLOTS
+            ));
+        }
+        // every tile's half sine is wavelength updates long
+        if self.wavelength == 0 {
+            return Err(format!(
+                "Wave slot {} has no wavelength; it must be at least 1.",
+                self.slot
+            ));
+        }
+        // NaN is in no range
+        if !(-50.0..=50.0).contains(&self.amplitude) {
+            return Err(format!(
+                "Amplitude {} is invalid; -50 <= amplitude <= 50.",
+                self.amplitude
+            ));
+        }
+        if !self.heading.is_finite() {
+            return Err(format!(
+                "Heading {} is invalid; it must be a finite number of degrees.",
+                self.heading
+            ));
+        }
+        if !(self.decay >= 0.0 && self.falloff >= 0.0) {
+            return Err(format!(
+                "Wave slot {} can't have a negative decay or falloff.",
+                self.slot
+            ));
+        }
+        if self.lifetime > MAX_LIFETIME {
+            return Err(format!(
+                "Lifetime {} is invalid; lifetime <= {}.",
+                self.lifetime, MAX_LIFETIME
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn to_wave(&self) -> Wave {
+        Wave {
+            directions: self.directions.iter().fold(0, |acc, d| acc | d.get_bit()),
+            wavelength: self.wavelength,
+            kind: self.kind,
+            amplitude_factor: self.amplitude,
+            heading: self.heading,
+            decay: self.decay,
+            falloff: self.falloff,
+            lifetime: self.lifetime,
+        }
+    }
+}
+
+/// A wave placed on the tile at `(x, y)` of the height texture.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Source {
+    pub x: u32,
+    pub y: u32,
+    pub slot: usize,
+}
+
+// toml can't write plain values after tables, so they come first
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct Scene {
+    /// A PNG the size of the grid where dark pixels are land, relative to the scene file.
+    #[serde(default)]
+    pub obstacles: Option<PathBuf>,
+    #[serde(skip)]
+    pub obstacle_mask: Option<ObstacleMask>,
+    /// A grayscale PNG the size of the grid, from black for the shallowest water to white for
+    /// the deepest, relative to the scene file.
+    #[serde(default)]
+    pub bathymetry: Option<PathBuf>,
+    #[serde(skip)]
+    pub bathymetry_map: Option<Bathymetry>,
+    #[serde(default)]
+    pub waves: Vec<WaveDefinition>,
+    #[serde(default)]
+    pub sources: Vec<Source>,
+    // a table, so after the arrays that may be written empty
+    #[serde(default)]
+    pub grid: Grid,
+    #[serde(default)]
+    pub solver: SolverSettings,
+    /// Where the windowed app puts the camera; the headless runner has none.
+    #[serde(default)]
+    pub camera: Option<Camera>,
+}
+
+impl Scene {
+    pub fn from_toml(src: &str) -> Result<Scene, String> {
+        let scene: Scene = toml::from_str(src).map_err(|e| e.to_string())?;
+        scene.validate()?;
+        Ok(scene)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Scene, String> {
+        let src = std::fs::read_to_string(path.as_ref())
+            .map_err(|e| format!("Could not read {}: {}", path.as_ref().display(), e))?;
+        let mut scene = Scene::from_toml(&src)?;
+        let dir = path.as_ref().parent().unwrap_or_else(|| Path::new(""));
+        scene.load_obstacles(dir)?;
+        scene.load_bathymetry(dir)?;
+        Ok(scene)
+    }
+
+    /// Writes the scene to `path` as TOML. Its obstacle mask and depth map, unless they are
+    /// empty, are written next to it as `<name>-obstacles.png` and `<name>-bathymetry.png`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        let name = path
+            .file_stem()
+            .ok_or_else(|| format!("{} is not a file name.", path.display()))?
+            .to_string_lossy();
+        let mut scene = self.clone();
+        scene.obstacles = match &self.obstacle_mask {
+            Some(mask) if !mask.is_open() => {
+                let file = format!("{}-obstacles.png", name);
+                mask.save(dir.join(&file))?;
+                Some(PathBuf::from(file))
+            }
+            _ => None,
+        };
+        scene.bathymetry = match &self.bathymetry_map {
+            Some(bathymetry) if !bathymetry.is_flat() => {
+                let file = format!("{}-bathymetry.png", name);
+                bathymetry.save(dir.join(&file))?;
+                Some(PathBuf::from(file))
+            }
+            _ => None,
+        };
+        std::fs::write(path, scene.to_toml()?)
+            .map_err(|e| format!("Could not write {}: {}", path.display(), e))
+    }
+
+    pub fn to_toml(&self) -> Result<String, String> {
+        toml::to_string_pretty(self).map_err(|e| e.to_string())
+    }
+
+    /// Fills the wave definitions from a registry; empty slots are left out.
+    pub fn set_waves(&mut self, waves: &WaveRegistry) {
+        self.waves = waves
+            .iter()
+            .enumerate()
+            .filter(|(_, wave)| wave.wavelength != 0)
+            .map(|(slot, wave)| WaveDefinition::from_wave(slot, wave))
+            .collect();
+    }
+
+    /// Reads the depth map, if the scene has one, with `dir` as the scene's directory.
+    pub fn load_bathymetry(&mut self, dir: &Path) -> Result<(), String> {
+        if let Some(bathymetry) = &self.bathymetry {
+            self.bathymetry_map = Some(Bathymetry::load(
+                dir.join(bathymetry),
+                self.grid.width,
+                self.grid.height,
+            )?);
+        }
+        Ok(())
+    }
+
+    /// Reads the obstacle mask, if the scene has one, with `dir` as the scene's directory.
+    pub fn load_obstacles(&mut self, dir: &Path) -> Result<(), String> {
+        if let Some(obstacles) = &self.obstacles {
+            let mask = ObstacleMask::load(dir.join(obstacles), self.grid.width, self.grid.height)?;
+            if let Some(source) = self.sources.iter().find(|s| mask.is_blocked(s.x, s.y)) {
+                return Err(format!("Source ({}, {}) is on land.", source.x, source.y));
+            }
+            self.obstacle_mask = Some(mask);
+        }
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.solver.validate()?;
+        self.grid.validate()?;
+        for (index, wave) in self.waves.iter().enumerate() {
+            if self.waves[..index].iter().any(|w| w.slot == wave.slot) {
+                return Err(format!("Wave slot {} is defined twice.", wave.slot));
+            }
+            wave.validate()?;
+        }
+        for source in self.sources.iter() {
+            if !self.waves.iter().any(|w| w.slot == source.slot) {
+                return Err(format!(
+                    "Wave slot {} is placed but never defined.",
+                    source.slot
+                ));
+            }
+            if source.x >= self.grid.width || source.y >= self.grid.height {
+                return Err(format!(
+                    "Source ({}, {}) is outside of the {}x{} grid.",
+                    source.x, source.y, self.grid.width, self.grid.height
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get_waves(&self) -> WaveRegistry {
+        let mut waves = WaveRegistry::new();
+        for wave in self.waves.iter() {
+            waves.set(wave.slot, wave.to_wave());
+        }
+        waves
+    }
+
+    /// A fresh grid for the scene's solver with every source placed on it, in order.
+    pub fn instantiate(&self) -> Box<dyn Solver> {
+        let slots = self.waves.iter().map(|w| w.slot + 1).max().unwrap_or(0);
+        let waves = self.get_waves();
+        let mut sim = self
+            .solver
+            .new_solver(self.grid.width, self.grid.height, slots);
+        if let Some(mask) = &self.obstacle_mask {
+            sim.set_obstacles(mask.clone());
+        }
+        if let Some(bathymetry) = &self.bathymetry_map {
+            sim.set_bathymetry(bathymetry.clone());
+        }
+        self.sources
+            .iter()
+            .for_each(|source| sim.place(source.x, source.y, source.slot, &waves));
+        sim
+    }
+}
@@ -0,0 +1,863 @@
+//! CPU port of the `process_water` kernel in `bundles/shaders.metal`.
+//!
+//! This is the reference implementation of the propagation rule: the Metal kernel is expected to
+//! produce exactly the same grid as `Simulation::step` for the same input.
+
+use crate::wave::bathymetry::Bathymetry;
+use crate::wave::boundary::Boundary;
+use crate::wave::constants::{
+    MAX_SLOTS, SPONGE_DAMPING, SPONGE_WIDTH, WAVE_DAMPING, WAVE_SPEED, WAVE_TIMESTEP,
+};
+use crate::wave::obstacles::ObstacleMask;
+use crate::wave::physics::WaveEquation;
+use crate::wave::snapshot::Snapshot;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+// propagation bitwise storage, see `Wave::directions`
+pub const UP: u8 = 1;
+pub const DOWN: u8 = 2;
+pub const LEFT: u8 = 4;
+pub const RIGHT: u8 = 8;
+
+// the high byte of a channel is the activation bit, the low byte is the tick; until a wave moves
+// onto a tile, the low byte counts how many updates it has waited to (see `Bathymetry::lag_at`)
+pub const ACTIVATED: u16 = 1 << 8;
+// also in the high byte, whether the wave on a tile has turned around off a reflective edge,
+// going up and down or left and right; see `turn`
+pub const TURNED_VERTICAL: u16 = 1 << 9;
+pub const TURNED_HORIZONTAL: u16 = 1 << 10;
+pub const TURNED: u16 = TURNED_VERTICAL | TURNED_HORIZONTAL;
+
+// the high half of a tile's travel is how many tiles the wave came across, the low half how many
+// updates ago it was placed; both stop counting at u16::MAX
+pub const TRAVEL_SHIFT: u32 = 16;
+// tiles can't count ages past this
+pub const MAX_LIFETIME: u32 = u16::MAX as u32;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+#[repr(u8)]
+pub enum WaveKind {
+    /// Moves from tile to tile in its directions.
+    #[default]
+    Directional = 0,
+    /// Spreads out in circles from where it's placed; its directions are ignored.
+    Radial = 1,
+    /// Moves in a straight line at its heading, as a plane wave; its directions are ignored.
+    Plane = 2,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Wave {
+    // x | 1 == up
+    // x | 2 == down
+    // x | 4 == left
+    // x | 8 == right
+    pub directions: u8,
+    // in vertices
+    pub wavelength: u8,
+    pub kind: WaveKind,
+    // amplitude of the wave; may be negative
+    // amplitude is calculated in the vertex shader
+    pub amplitude_factor: f32,
+    // in degrees, for plane waves; see `heading_vector`
+    pub heading: f32,
+    // how much of the amplitude is lost every update, exponentially; see `attenuation`
+    pub decay: f32,
+    // how fast the amplitude falls off with distance from where the wave was placed, as 1/r
+    pub falloff: f32,
+    // in updates; the wave is removed this long after it was placed. 0 is forever
+    pub lifetime: u32,
+}
+
+impl Default for Wave {
+    fn default() -> Wave {
+        Wave {
+            directions: 0,
+            wavelength: 0,
+            kind: WaveKind::Directional,
+            amplitude_factor: 0.0,
+            heading: 0.0,
+            decay: 0.0,
+            falloff: 0.0,
+            lifetime: 0,
+        }
+    }
+}
+
+/// Whether the wave stored in this channel is on the tile.
+pub fn is_activated(channel: u16) -> bool {
+    channel & ACTIVATED != 0
+}
+
+/// The directions the wave stored in this channel moves in: its wave's, swapped along the axes
+/// it has turned around on.
+pub fn get_directions(directions: u8, channel: u16) -> u8 {
+    let mut directions = directions;
+    if channel & TURNED_VERTICAL != 0 {
+        directions = (directions & !(UP | DOWN)) | (directions & UP) << 1 | (directions & DOWN) >> 1;
+    }
+    if channel & TURNED_HORIZONTAL != 0 {
+        directions =
+            (directions & !(LEFT | RIGHT)) | (directions & LEFT) << 1 | (directions & RIGHT) >> 1;
+    }
+    directions
+}
+
+/// How a wave moving in `directions` is turned on `(x, y)`, having come onto it turned the way
+/// `turned` says. With reflective edges, a wave going one way along an axis is turned back into
+/// the grid on the edges across it; up moves to lower y, down to higher, left to higher x and
+/// right to lower, the ways they're read in `Simulation::step`.
+pub fn turn(
+    directions: u8,
+    turned: u16,
+    boundary: Boundary,
+    (x, y): (u32, u32),
+    (width, height): (u32, u32),
+) -> u16 {
+    if boundary != Boundary::Reflective {
+        return turned;
+    }
+    let axes = [
+        (UP, DOWN, y, height, TURNED_VERTICAL),
+        (RIGHT, LEFT, x, width, TURNED_HORIZONTAL),
+    ];
+    axes.iter()
+        .fold(turned, |turned, &(lower, higher, at, size, bit)| {
+            let (lower, higher) = (directions & lower != 0, directions & higher != 0);
+            match lower != higher {
+                true if at == 0 => (turned & !bit) | if lower { bit } else { 0 },
+                true if at == size - 1 => (turned & !bit) | if higher { bit } else { 0 },
+                _ => turned,
+            }
+        })
+}
+
+/// How long the wave stored in this channel has been on the tile.
+pub fn get_tick(channel: u16) -> u16 {
+    channel & 255
+}
+
+/// How many updates ago the wave on a tile was placed.
+pub fn get_age(travel: u32) -> u32 {
+    travel & u16::MAX as u32
+}
+
+/// How many tiles the wave on a tile came across since it was placed.
+pub fn get_distance(travel: u32) -> u32 {
+    travel >> TRAVEL_SHIFT
+}
+
+pub fn pack_travel(age: u32, distance: u32) -> u32 {
+    (distance.min(u16::MAX as u32) << TRAVEL_SHIFT) | age.min(u16::MAX as u32)
+}
+
+/// What is left of a wave's amplitude `age` updates after it was placed, `distance` tiles away:
+/// `exp(-decay * age) / (1 + falloff * distance)`.
+pub fn attenuation(wave: &Wave, age: u32, distance: f32) -> f32 {
+    (-wave.decay * age as f32).exp() / (1.0 + wave.falloff * distance)
+}
+
+/// Whether a wave placed `age` updates ago has outlived its lifetime.
+pub fn is_expired(wave: &Wave, age: u32) -> bool {
+    wave.lifetime != 0 && age >= wave.lifetime
+}
+
+/// The height one channel contributes to its tile, as computed in `water_vert`.
+pub fn channel_height(channel: u16, travel: u32, wave: &Wave) -> f32 {
+    if is_activated(channel) {
+        wave.amplitude_factor
+            * (get_tick(channel) as f32 * (PI / wave.wavelength as f32)).sin()
+            * attenuation(wave, get_age(travel), get_distance(travel) as f32)
+    } else {
+        0.0
+    }
+}
+
+/// The height of a tile, given the channel and travel of every wave slot on it.
+pub fn tile_height(channels: &[u16], travel: &[u32], waves: &WaveRegistry) -> f32 {
+    channels
+        .iter()
+        .zip(travel.iter())
+        .enumerate()
+        .map(|(slot, (channel, travel))| {
+            waves
+                .get(slot)
+                .map_or(0.0, |wave| channel_height(*channel, *travel, wave))
+        })
+        .sum()
+}
+
+/// The way a wave at `heading` degrees moves on the grid, one tile long. 0 goes the same way as
+/// a `RIGHT` wave, 90 as `UP`, 180 as `LEFT` and 270 as `DOWN`.
+pub fn heading_vector(heading: f32) -> (f32, f32) {
+    let radians = heading.to_radians();
+    (-radians.cos(), -radians.sin())
+}
+
+/// How far a tile is from where a wave was placed, along the way the wave moves: the distance
+/// for radial waves, and the projection onto the heading for plane waves.
+pub fn travelled(wave: &Wave, from: (f32, f32), x: u32, y: u32) -> f32 {
+    let offset = (x as f32 - from.0, y as f32 - from.1);
+    match wave.kind {
+        WaveKind::Plane => {
+            let (dx, dy) = heading_vector(wave.heading);
+            offset.0 * dx + offset.1 * dy
+        }
+        _ => offset.0.hypot(offset.1),
+    }
+}
+
+/// A radial or plane wave, `age` updates after it was placed on `(x, y)`. Laid out for the GPU.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Ripple {
+    pub x: u32,
+    pub y: u32,
+    pub slot: u32,
+    pub age: u32,
+}
+
+/// Where a ripple's wavefront seems to come from, and the wave as it seems to move from there.
+#[derive(Clone, Copy)]
+pub struct RippleSource {
+    pub from: (f32, f32),
+    pub wave: Wave,
+    // mirrored across an edge; its way onto the grid comes in over the edge
+    pub mirrored: bool,
+}
+
+/// Where a ripple's wavefronts come from: the ripple itself, and with reflective edges its mirror
+/// image across every edge and corner too, so that it bounces off each of them once. The mirrors
+/// are through the edge tiles, where the automaton's waves turn around; plane waves' headings
+/// are mirrored with them.
+pub fn ripple_sources(
+    ripple: &Ripple,
+    wave: &Wave,
+    boundary: Boundary,
+    (width, height): (u32, u32),
+) -> Vec<RippleSource> {
+    let source = RippleSource {
+        from: (ripple.x as f32, ripple.y as f32),
+        wave: *wave,
+        mirrored: false,
+    };
+    if boundary != Boundary::Reflective {
+        return vec![source];
+    }
+    let (x, y) = source.from;
+    let (right, bottom) = ((width - 1) as f32, (height - 1) as f32);
+    let columns = [(x, false), (-x, true), (2.0 * right - x, true)];
+    let rows = [(y, false), (-y, true), (2.0 * bottom - y, true)];
+    rows.iter()
+        .flat_map(|row| columns.iter().map(move |column| (*column, *row)))
+        .map(|((x, across), (y, down))| {
+            let mut wave = *wave;
+            if across {
+                wave.heading = 180.0 - wave.heading;
+            }
+            if down {
+                wave.heading = -wave.heading;
+            }
+            RippleSource {
+                from: (x, y),
+                wave,
+                mirrored: across || down,
+            }
+        })
+        .collect()
+}
+
+/// How a ripple's wavefront reaches a tile from one of its sources, in a straight line, or for
+/// plane waves straight along the heading from the line they were placed on: how far it is (see
+/// `travelled`), and how many more updates the wavefront takes to get there, the lags of the
+/// tiles it crosses added up as the automaton's waves wait them out (see `Bathymetry::lag_at`).
+/// None if land is in the way, so that the tiles behind it are sheltered.
+pub fn ripple_path(
+    source: &RippleSource,
+    x: u32,
+    y: u32,
+    obstacles: &ObstacleMask,
+    lags: &[u8],
+) -> Option<(f32, u32)> {
+    let wave = &source.wave;
+    let travelled = travelled(wave, source.from, x, y);
+    if obstacles.is_blocked(x, y) {
+        return None;
+    }
+    let start = match wave.kind {
+        WaveKind::Plane => {
+            let (dx, dy) = heading_vector(wave.heading);
+            (x as f32 - dx * travelled, y as f32 - dy * travelled)
+        }
+        _ => source.from,
+    };
+    let offset = (x as f32 - start.0, y as f32 - start.1);
+    let steps = offset.0.abs().max(offset.1.abs()).ceil() as u32;
+    let size = (obstacles.width as f32, obstacles.height as f32);
+    // a mirrored wavefront crosses the grid mirrored before it bounces back onto it
+    let mirror = |at: f32, size: f32| match source.mirrored {
+        true if at < 0.0 => -at,
+        true if at >= size => 2.0 * (size - 1.0) - at,
+        _ => at,
+    };
+    let mut delay = 0;
+    // every tile on the way, not counting the one it starts on
+    for step in 1..=steps {
+        let along = step as f32 / steps as f32;
+        let tile = (
+            mirror((start.0 + offset.0 * along + 0.5).floor(), size.0),
+            mirror((start.1 + offset.1 * along + 0.5).floor(), size.1),
+        );
+        // plane waves can come from off the grid, where nothing is in the way
+        if tile.0 < 0.0 || tile.1 < 0.0 || tile.0 >= size.0 || tile.1 >= size.1 {
+            continue;
+        }
+        let (tx, ty) = (tile.0 as u32, tile.1 as u32);
+        if obstacles.is_blocked(tx, ty) {
+            return None;
+        }
+        delay += lags
+            .get((ty * obstacles.width + tx) as usize)
+            .copied()
+            .unwrap_or(0) as u32;
+    }
+    Some((travelled, delay))
+}
+
+/// How far through its half sine a ripple's wavefront from one of its sources is on a tile, in
+/// updates; None where it isn't on it.
+pub fn ripple_tick(
+    ripple: &Ripple,
+    source: &RippleSource,
+    x: u32,
+    y: u32,
+    obstacles: &ObstacleMask,
+    lags: &[u8],
+) -> Option<(f32, f32)> {
+    let (travelled, delay) = ripple_path(source, x, y, obstacles, lags)?;
+    let tick = ripple.age as f32 - travelled - delay as f32;
+    match tick >= 0.0 && tick <= source.wave.wavelength as f32 {
+        true => Some((tick, travelled)),
+        false => None,
+    }
+}
+
+/// The height a ripple gives a tile: its wavefronts move out one tile per update, slower over
+/// shallows, and each tile goes through the same half sine as an automaton tile once a
+/// wavefront has reached it. Plane waves only move forwards from the line they were placed on.
+pub fn ripple_height(
+    ripple: &Ripple,
+    wave: &Wave,
+    boundary: Boundary,
+    x: u32,
+    y: u32,
+    obstacles: &ObstacleMask,
+    lags: &[u8],
+) -> f32 {
+    let size = (obstacles.width, obstacles.height);
+    ripple_sources(ripple, wave, boundary, size)
+        .iter()
+        .filter_map(|source| ripple_tick(ripple, source, x, y, obstacles, lags))
+        .map(|(tick, travelled)| {
+            wave.amplitude_factor
+                * (tick * (PI / wave.wavelength as f32)).sin()
+                * attenuation(wave, ripple.age, travelled.abs())
+        })
+        .sum()
+}
+
+/// Ages every ripple by one update, and removes the ones that have left a `width` by `height`
+/// grid, however slow its `lags` make them and after bouncing back off reflective edges, have
+/// outlived their wave's lifetime, or whose slot is no longer radial or plane.
+pub fn age_ripples(
+    ripples: &mut Vec<Ripple>,
+    waves: &WaveRegistry,
+    boundary: Boundary,
+    width: u32,
+    height: u32,
+    lags: &[u8],
+) {
+    let slowest = lags.iter().max().copied().unwrap_or(0) as f32;
+    let bounces = if boundary == Boundary::Reflective { 2.0 } else { 1.0 };
+    let farthest = (width as f32).hypot(height as f32) * (1.0 + slowest) * bounces;
+    ripples.retain(|ripple| match waves.get(ripple.slot as usize) {
+        Some(wave) if wave.kind != WaveKind::Directional => {
+            (ripple.age as f32) < farthest + wave.wavelength as f32
+                && !is_expired(wave, ripple.age + 1)
+        }
+        _ => false,
+    });
+    ripples.iter_mut().for_each(|ripple| ripple.age += 1);
+}
+
+/// Every wave definition, indexed by slot. There can be up to `MAX_SLOTS` slots.
+#[derive(Clone, Default)]
+pub struct WaveRegistry {
+    waves: Vec<Wave>,
+}
+
+impl WaveRegistry {
+    pub fn new() -> WaveRegistry {
+        WaveRegistry { waves: vec![] }
+    }
+
+    pub fn len(&self) -> usize {
+        self.waves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.waves.is_empty()
+    }
+
+    pub fn get(&self, slot: usize) -> Option<&Wave> {
+        self.waves.get(slot)
+    }
+
+    /// Fills a slot, adding empty slots before it if the registry is too short. Slots past
+    /// `MAX_SLOTS` are left alone, and false is returned.
+    pub fn set(&mut self, slot: usize, wave: Wave) -> bool {
+        if slot >= MAX_SLOTS {
+            return false;
+        }
+        if slot >= self.waves.len() {
+            self.waves.resize(slot + 1, Wave::default());
+        }
+        self.waves[slot] = wave;
+        true
+    }
+
+    pub fn as_slice(&self) -> &[Wave] {
+        &self.waves
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Wave> {
+        self.waves.iter()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum SolverMode {
+    /// Tiles turn on when a neighbour is on; see `Simulation`.
+    Automaton,
+    /// The damped 2D wave equation; see `WaveEquation`.
+    WaveEquation,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default)]
+pub struct SolverSettings {
+    pub mode: SolverMode,
+    // in tiles per unit of time
+    pub speed: f32,
+    pub damping: f32,
+    // units of time per update
+    pub timestep: f32,
+    pub boundary: Boundary,
+    // in tiles; only used by absorbing boundaries
+    pub sponge_width: u32,
+    pub sponge_damping: f32,
+}
+
+impl Default for SolverSettings {
+    fn default() -> SolverSettings {
+        SolverSettings {
+            mode: SolverMode::Automaton,
+            speed: WAVE_SPEED,
+            damping: WAVE_DAMPING,
+            timestep: WAVE_TIMESTEP,
+            boundary: Boundary::Fixed,
+            sponge_width: SPONGE_WIDTH,
+            sponge_damping: SPONGE_DAMPING,
+        }
+    }
+}
+
+impl SolverSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.speed < 0.0 || self.damping < 0.0 || self.timestep <= 0.0 {
+            return Err(
+                "Wave speed and damping can't be negative, and the timestep must be \
+                        positive."
+                    .to_string(),
+            );
+        }
+        if self.sponge_damping < 0.0 {
+            return Err("Sponge damping can't be negative.".to_string());
+        }
+        // the explicit scheme blows up past the CFL limit
+        if self.speed * self.timestep > std::f32::consts::FRAC_1_SQRT_2 {
+            return Err(format!(
+                "speed * timestep = {} is unstable; it must be at most {}.",
+                self.speed * self.timestep,
+                std::f32::consts::FRAC_1_SQRT_2
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn new_solver(&self, width: u32, height: u32, slots: usize) -> Box<dyn Solver> {
+        match self.mode {
+            SolverMode::Automaton => {
+                let mut sim = Simulation::new(width, height, slots);
+                sim.boundary = self.boundary;
+                sim.sponge_width = self.sponge_width;
+                Box::new(sim)
+            }
+            SolverMode::WaveEquation => Box::new(WaveEquation::new(width, height, *self)),
+        }
+    }
+}
+
+/// Anything that can advance a grid of water and tell how high it is.
+pub trait Solver {
+    fn get_size(&self) -> (u32, u32);
+    /// Places the wave in slot `wave_id` on a tile, like the `place` command does.
+    fn place(&mut self, x: u32, y: u32, wave_id: usize, waves: &WaveRegistry);
+    /// Removes every wave on a tile, placed there or passing through, like a right click does.
+    fn clear(&mut self, x: u32, y: u32);
+    /// Advances every tile by one update.
+    fn step(&mut self, waves: &WaveRegistry);
+    fn height_at(&self, x: u32, y: u32, waves: &WaveRegistry) -> f32;
+    /// How far through its half sine the wave in `slot` is on a tile, in updates; None where
+    /// that wave isn't on it.
+    fn slot_tick(&self, slot: usize, x: u32, y: u32, waves: &WaveRegistry) -> Option<f32>;
+    /// Replaces the obstacles; waves already on newly blocked tiles are removed.
+    fn set_obstacles(&mut self, obstacles: ObstacleMask);
+    fn get_obstacles(&self) -> &ObstacleMask;
+    fn set_bathymetry(&mut self, bathymetry: Bathymetry);
+    fn set_boundary(&mut self, boundary: Boundary);
+    /// Everything needed to carry on from here with `Snapshot::restore`.
+    fn snapshot(&self, waves: &WaveRegistry, time: u64) -> Snapshot;
+}
+
+/// The automaton: a `width` by `height` grid of tiles with one layer per wave slot. Layers are stored one after
+/// the other, each one row by row, which is also how the GPU height buffers are laid out.
+pub struct Simulation {
+    pub width: u32,
+    pub height: u32,
+    pub slots: usize,
+    pub tiles: Vec<u16>,
+    // laid out like `tiles`; see `pack_travel`
+    pub travel: Vec<u32>,
+    pub boundary: Boundary,
+    // in tiles; waves fade out across it with absorbing edges
+    pub sponge_width: u32,
+    pub obstacles: ObstacleMask,
+    pub bathymetry: Bathymetry,
+    // see `Bathymetry::lag_at`
+    pub lags: Vec<u8>,
+    pub ripples: Vec<Ripple>,
+}
+
+impl Simulation {
+    pub fn new(width: u32, height: u32, slots: usize) -> Simulation {
+        Simulation {
+            width,
+            height,
+            slots,
+            tiles: vec![0; slots * width as usize * height as usize],
+            travel: vec![0; slots * width as usize * height as usize],
+            boundary: Boundary::Fixed,
+            sponge_width: SPONGE_WIDTH,
+            obstacles: ObstacleMask::open(width, height),
+            bathymetry: Bathymetry::flat(width, height),
+            lags: vec![0; width as usize * height as usize],
+            ripples: vec![],
+        }
+    }
+
+    pub fn get_layer_len(&self) -> usize {
+        self.width as usize * self.height as usize
+    }
+
+    pub fn get_layer(&self, slot: usize) -> &[u16] {
+        let len = self.get_layer_len();
+        &self.tiles[slot * len..(slot + 1) * len]
+    }
+
+    /// Adds empty layers or drops the last ones so that there are `slots` layers.
+    pub fn set_slot_count(&mut self, slots: usize) {
+        self.slots = slots;
+        self.tiles.resize(slots * self.get_layer_len(), 0);
+        self.travel.resize(slots * self.get_layer_len(), 0);
+    }
+
+    pub fn get(&self, slot: usize, x: u32, y: u32) -> Option<u16> {
+        if slot < self.slots && x < self.width && y < self.height {
+            Some(self.tiles[slot * self.get_layer_len() + (y * self.width + x) as usize])
+        } else {
+            None
+        }
+    }
+
+    /// The channel of every slot on a tile, in slot order.
+    pub fn get_tile(&self, x: u32, y: u32) -> Vec<u16> {
+        (0..self.slots)
+            .filter_map(|slot| self.get(slot, x, y))
+            .collect()
+    }
+
+    /// The travel of every slot on a tile, in slot order.
+    pub fn get_travel(&self, x: u32, y: u32) -> Vec<u32> {
+        if x >= self.width || y >= self.height {
+            return vec![];
+        }
+        let index = (y * self.width + x) as usize;
+        (0..self.slots)
+            .map(|slot| self.travel[slot * self.get_layer_len() + index])
+            .collect()
+    }
+}
+
+impl Solver for Simulation {
+    fn get_size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    // the grid gains layers if it doesn't have that slot yet, up to `MAX_SLOTS`; radial and
+    // plane waves are kept apart, as ripples
+    fn place(&mut self, x: u32, y: u32, wave_id: usize, waves: &WaveRegistry) {
+        if x >= self.width || y >= self.height || self.obstacles.is_blocked(x, y) {
+            return;
+        }
+        if wave_id >= MAX_SLOTS {
+            return;
+        }
+        if waves
+            .get(wave_id)
+            .is_some_and(|wave| wave.kind != WaveKind::Directional)
+        {
+            self.ripples.push(Ripple {
+                x,
+                y,
+                slot: wave_id as u32,
+                age: 0,
+            });
+        } else {
+            if wave_id >= self.slots {
+                self.set_slot_count(wave_id + 1);
+            }
+            let directions = waves.get(wave_id).map_or(0, |wave| wave.directions);
+            let size = (self.width, self.height);
+            let index = wave_id * self.get_layer_len() + (y * self.width + x) as usize;
+            self.tiles[index] = ACTIVATED | turn(directions, 0, self.boundary, (x, y), size);
+            self.travel[index] = 0;
+        }
+    }
+
+    // ripples only go if they started on the tile
+    fn clear(&mut self, x: u32, y: u32) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        let len = self.get_layer_len();
+        let index = (y * self.width + x) as usize;
+        (0..self.slots).for_each(|slot| {
+            self.tiles[slot * len + index] = 0;
+            self.travel[slot * len + index] = 0;
+        });
+        self.ripples.retain(|ripple| (ripple.x, ripple.y) != (x, y));
+    }
+
+    fn get_obstacles(&self) -> &ObstacleMask {
+        &self.obstacles
+    }
+
+    fn set_obstacles(&mut self, obstacles: ObstacleMask) {
+        let len = self.get_layer_len();
+        (0..self.slots).for_each(|slot| {
+            obstacles
+                .tiles
+                .iter()
+                .enumerate()
+                .filter(|(_, blocked)| **blocked != 0)
+                .for_each(|(index, _)| {
+                    self.tiles[slot * len + index] = 0;
+                    self.travel[slot * len + index] = 0;
+                });
+        });
+        self.obstacles = obstacles;
+    }
+
+    fn set_bathymetry(&mut self, bathymetry: Bathymetry) {
+        self.lags = bathymetry.get_lags();
+        self.bathymetry = bathymetry;
+    }
+
+    fn set_boundary(&mut self, boundary: Boundary) {
+        self.boundary = boundary;
+    }
+
+    // only the automaton's settings are kept; the rest are the defaults
+    fn snapshot(&self, waves: &WaveRegistry, time: u64) -> Snapshot {
+        Snapshot {
+            time,
+            waves: waves.clone(),
+            solver: SolverSettings {
+                mode: SolverMode::Automaton,
+                boundary: self.boundary,
+                sponge_width: self.sponge_width,
+                ..SolverSettings::default()
+            },
+            width: self.width,
+            height: self.height,
+            obstacles: self.obstacles.clone(),
+            bathymetry: self.bathymetry.clone(),
+            slots: self.slots as u32,
+            tiles: self.tiles.clone(),
+            travel: self.travel.clone(),
+            ripples: self.ripples.clone(),
+            ..Snapshot::default()
+        }
+    }
+
+    // waves fade out across an absorbing sponge, as in `water_vert`
+    fn height_at(&self, x: u32, y: u32, waves: &WaveRegistry) -> f32 {
+        let sponge = self
+            .boundary
+            .sponge(x, y, self.width, self.height, self.sponge_width);
+        let ripples = self
+            .ripples
+            .iter()
+            .filter_map(|r| {
+                let wave = waves.get(r.slot as usize)?;
+                let (obstacles, lags) = (&self.obstacles, &self.lags);
+                Some(ripple_height(r, wave, self.boundary, x, y, obstacles, lags))
+            })
+            .sum::<f32>();
+        let travel = self.get_travel(x, y);
+        (tile_height(&self.get_tile(x, y), &travel, waves) + ripples) * (1.0 - sponge)
+    }
+
+    // the slot's own layer, or any of its ripples going through the tile, the furthest on
+    fn slot_tick(&self, slot: usize, x: u32, y: u32, waves: &WaveRegistry) -> Option<f32> {
+        let tile = self
+            .get(slot, x, y)
+            .filter(|channel| is_activated(*channel))
+            .map(|channel| get_tick(channel) as f32);
+        let wave = match waves.get(slot) {
+            Some(wave) => wave,
+            None => return tile,
+        };
+        let size = (self.width, self.height);
+        self.ripples
+            .iter()
+            .filter(|ripple| ripple.slot as usize == slot)
+            .flat_map(|ripple| {
+                ripple_sources(ripple, wave, self.boundary, size)
+                    .into_iter()
+                    .map(move |source| (ripple, source))
+            })
+            .filter_map(|(ripple, source)| {
+                ripple_tick(ripple, &source, x, y, &self.obstacles, &self.lags)
+            })
+            .map(|(tick, _)| tick)
+            .chain(tile)
+            .fold(None, |furthest: Option<f32>, tick| {
+                Some(furthest.map_or(tick, |f| f.max(tick)))
+            })
+    }
+
+    // every tile reads its neighbours from the grid as it was before the update; waves only come
+    // in from past the edges if they're periodic, and reflective ones turn them around on the
+    // edge tiles instead (see `turn`); blocked tiles never hold a wave
+    // a wave reaching a tile from a neighbour waits on it for the tile's lag before it moves
+    // on, so that it's slower in shallow water, however short the wave, and keeps the
+    // neighbour's travel, one tile further
+    fn step(&mut self, waves: &WaveRegistry) {
+        let len = self.get_layer_len();
+        let (width, height) = (self.width as i64, self.height as i64);
+        let boundary = self.boundary;
+        let edges = match boundary {
+            Boundary::Periodic => Boundary::Periodic,
+            _ => Boundary::Fixed,
+        };
+        let obstacles = &self.obstacles;
+        let lags = &self.lags;
+        let mut next = self.tiles.clone();
+        let mut next_travel = self.travel.clone();
+
+        (0..self.slots).for_each(|slot| {
+            let wave = waves.get(slot).copied().unwrap_or_default();
+            let previous = &self.tiles[slot * len..(slot + 1) * len];
+            let previous_travel = &self.travel[slot * len..(slot + 1) * len];
+            let layer = &mut next[slot * len..(slot + 1) * len];
+            let travel_layer = &mut next_travel[slot * len..(slot + 1) * len];
+            let read = |x: i64, y: i64| match edges.resolve(x, y, width, height) {
+                Some((x, y)) => (
+                    previous[(y * width + x) as usize],
+                    previous_travel[(y * width + x) as usize],
+                ),
+                None => (0, 0),
+            };
+
+            (0..height).for_each(|y| {
+                (0..width).for_each(|x| {
+                    let neighbours = [
+                        (UP, read(x, y + 1)),
+                        (DOWN, read(x, y - 1)),
+                        (LEFT, read(x - 1, y)),
+                        (RIGHT, read(x + 1, y)),
+                    ];
+                    let lag = lags[(y * width + x) as usize] as u16;
+                    let channel = &mut layer[(y * width + x) as usize];
+                    let travel = &mut travel_layer[(y * width + x) as usize];
+                    if obstacles.is_blocked(x as u32, y as u32) {
+                        *channel = 0;
+                        *travel = 0;
+                        return;
+                    }
+                    if !is_activated(*channel) {
+                        if get_tick(*channel) == 0 {
+                            let from = neighbours.iter().find(|(direction, (neighbour, _))| {
+                                is_activated(*neighbour)
+                                    && get_directions(wave.directions, *neighbour) & direction
+                                        == *direction
+                            });
+                            if let Some((_, (neighbour, neighbour_travel))) = from {
+                                let tile = (x as u32, y as u32);
+                                let size = (width as u32, height as u32);
+                                let turned = neighbour & TURNED;
+                                *channel = 1 | turn(wave.directions, turned, boundary, tile, size);
+                                *travel = pack_travel(
+                                    get_age(*neighbour_travel),
+                                    get_distance(*neighbour_travel) + 1,
+                                );
+                            }
+                        } else {
+                            *channel += 1;
+                            *travel = pack_travel(get_age(*travel) + 1, get_distance(*travel));
+                        }
+                        if get_tick(*channel) > lag {
+                            *channel = ACTIVATED | (*channel & TURNED);
+                        }
+                    }
+                    if is_activated(*channel) {
+                        let age = get_age(*travel) + 1;
+                        if get_tick(*channel) < wave.wavelength as u16 && !is_expired(&wave, age) {
+                            *channel += 1;
+                            *travel = pack_travel(age, get_distance(*travel));
+                        } else {
+                            *channel = 0;
+                            *travel = 0;
+                        }
+                    }
+                });
+            });
+        });
+        self.tiles = next;
+        self.travel = next_travel;
+        age_ripples(
+            &mut self.ripples,
+            waves,
+            self.boundary,
+            self.width,
+            self.height,
+            &self.lags,
+        );
+    }
+}
@@ -0,0 +1,396 @@
+//! Checkpoints: the exact state of a running simulation, in a versioned binary file.
+//!
+//! Everything is little-endian. A file starts with `MAGIC` and a `u32` version, and every list
+//! is a `u32` length followed by its items. Both solvers and the windowed app write the same
+//! format, so a state saved by one can be resumed by the other.
+
+use crate::wave::bathymetry::Bathymetry;
+use crate::wave::boundary::Boundary;
+use crate::wave::constants::MAX_SLOTS;
+use crate::wave::obstacles::ObstacleMask;
+use crate::wave::physics::{Driver, WaveEquation};
+use crate::wave::scene::WaveDefinition;
+use crate::wave::sim::{
+    is_activated, Ripple, Simulation, Solver, SolverMode, SolverSettings, Wave, WaveKind,
+    WaveRegistry,
+};
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+
+pub const MAGIC: &[u8; 8] = b"WAVESNAP";
+pub const VERSION: u32 = 2;
+
+#[derive(Clone, Default)]
+pub struct Snapshot {
+    // updates since the simulation started
+    pub time: u64,
+    pub waves: WaveRegistry,
+    pub solver: SolverSettings,
+    pub width: u32,
+    pub height: u32,
+    pub obstacles: ObstacleMask,
+    pub bathymetry: Bathymetry,
+    // the automaton's layers (see `Simulation`); empty for the wave equation
+    pub slots: u32,
+    pub tiles: Vec<u16>,
+    pub travel: Vec<u32>,
+    pub ripples: Vec<Ripple>,
+    // the wave equation's fields (see `WaveEquation`); empty for the automaton
+    pub heights: Vec<f32>,
+    pub previous: Vec<f32>,
+    pub drivers: Vec<Driver>,
+}
+
+impl Snapshot {
+    /// Whether `bytes` look like the start of a snapshot file.
+    pub fn is_snapshot(bytes: &[u8]) -> bool {
+        bytes.starts_with(MAGIC)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Snapshot, String> {
+        let mut bytes = vec![];
+        File::open(path.as_ref())
+            .and_then(|mut file| file.read_to_end(&mut bytes))
+            .map_err(|e| format!("Could not read {}: {}", path.as_ref().display(), e))?;
+        Snapshot::from_bytes(&bytes).map_err(|e| format!("{}: {}", path.as_ref().display(), e))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        File::create(path.as_ref())
+            .and_then(|file| {
+                let mut file = BufWriter::new(file);
+                file.write_all(&self.to_bytes())?;
+                file.flush()
+            })
+            .map_err(|e| format!("Could not write {}: {}", path.as_ref().display(), e))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        put_u32(&mut out, VERSION);
+        out.extend_from_slice(&self.time.to_le_bytes());
+
+        let solver = &self.solver;
+        out.push(match solver.mode {
+            SolverMode::Automaton => 0,
+            SolverMode::WaveEquation => 1,
+        });
+        put_f32(&mut out, solver.speed);
+        put_f32(&mut out, solver.damping);
+        put_f32(&mut out, solver.timestep);
+        put_u32(&mut out, solver.boundary as u32);
+        put_u32(&mut out, solver.sponge_width);
+        put_f32(&mut out, solver.sponge_damping);
+
+        put_u32(&mut out, self.width);
+        put_u32(&mut out, self.height);
+        put_list(&mut out, self.waves.as_slice(), |out, wave| {
+            out.extend_from_slice(&[wave.directions, wave.wavelength, wave.kind as u8]);
+            put_f32(out, wave.amplitude_factor);
+            put_f32(out, wave.heading);
+            put_f32(out, wave.decay);
+            put_f32(out, wave.falloff);
+            put_u32(out, wave.lifetime);
+        });
+        put_list(&mut out, &self.obstacles.tiles, |out, tile| out.push(*tile));
+        put_list(&mut out, &self.bathymetry.speeds, |out, speed| {
+            put_f32(out, *speed)
+        });
+
+        put_u32(&mut out, self.slots);
+        put_list(&mut out, &self.tiles, |out, tile| {
+            out.extend_from_slice(&tile.to_le_bytes())
+        });
+        put_list(&mut out, &self.travel, |out, travel| put_u32(out, *travel));
+        put_list(&mut out, &self.ripples, |out, ripple| {
+            [ripple.x, ripple.y, ripple.slot, ripple.age]
+                .iter()
+                .for_each(|v| put_u32(out, *v));
+        });
+
+        put_list(&mut out, &self.heights, |out, height| put_f32(out, *height));
+        put_list(&mut out, &self.previous, |out, height| {
+            put_f32(out, *height)
+        });
+        put_list(&mut out, &self.drivers, |out, driver| {
+            put_u32(out, driver.x);
+            put_u32(out, driver.y);
+            put_u32(out, driver.slot as u32);
+            put_u32(out, driver.tick as u32);
+            put_u32(out, driver.start as u32);
+            put_u32(out, driver.from.0);
+            put_u32(out, driver.from.1);
+            put_f32(out, driver.distance);
+        });
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Snapshot, String> {
+        if !Snapshot::is_snapshot(bytes) {
+            return Err("Not a snapshot.".to_string());
+        }
+        let mut input = Input {
+            bytes,
+            at: MAGIC.len(),
+        };
+        let version = input.u32()?;
+        if version != VERSION {
+            return Err(format!(
+                "Snapshot version {} is not supported; this build reads version {}.",
+                version, VERSION
+            ));
+        }
+        let time = input.u64()?;
+
+        let solver = SolverSettings {
+            mode: match input.u8()? {
+                0 => SolverMode::Automaton,
+                1 => SolverMode::WaveEquation,
+                mode => return Err(format!("Unknown solver mode {}.", mode)),
+            },
+            speed: input.f32()?,
+            damping: input.f32()?,
+            timestep: input.f32()?,
+            boundary: match input.u32()? {
+                0 => Boundary::Fixed,
+                1 => Boundary::Reflective,
+                2 => Boundary::Absorbing,
+                3 => Boundary::Periodic,
+                boundary => return Err(format!("Unknown boundary {}.", boundary)),
+            },
+            sponge_width: input.u32()?,
+            sponge_damping: input.f32()?,
+        };
+
+        let (width, height) = (input.u32()?, input.u32()?);
+        let mut waves = WaveRegistry::new();
+        let definitions = input.list(|input| {
+            Ok(Wave {
+                directions: input.u8()?,
+                wavelength: input.u8()?,
+                kind: match input.u8()? {
+                    0 => WaveKind::Directional,
+                    1 => WaveKind::Radial,
+                    2 => WaveKind::Plane,
+                    kind => return Err(format!("Unknown wave kind {}.", kind)),
+                },
+                amplitude_factor: input.f32()?,
+                heading: input.f32()?,
+                decay: input.f32()?,
+                falloff: input.f32()?,
+                lifetime: input.u32()?,
+            })
+        })?;
+        if definitions.len() > MAX_SLOTS {
+            return Err(format!(
+                "The snapshot has {} wave slots; at most {} are supported.",
+                definitions.len(),
+                MAX_SLOTS
+            ));
+        }
+        for (slot, wave) in definitions.into_iter().enumerate() {
+            waves.set(slot, wave);
+        }
+        let obstacles = ObstacleMask {
+            width,
+            height,
+            tiles: input.list(|input| input.u8())?,
+        };
+        let bathymetry = Bathymetry {
+            width,
+            height,
+            speeds: input.list(|input| input.f32())?,
+        };
+
+        let slots = input.u32()?;
+        let tiles = input.list(|input| input.u16())?;
+        let travel = input.list(|input| input.u32())?;
+        let ripples = input.list(|input| {
+            Ok(Ripple {
+                x: input.u32()?,
+                y: input.u32()?,
+                slot: input.u32()?,
+                age: input.u32()?,
+            })
+        })?;
+
+        let heights = input.list(|input| input.f32())?;
+        let previous = input.list(|input| input.f32())?;
+        let drivers = input.list(|input| {
+            Ok(Driver {
+                x: input.u32()?,
+                y: input.u32()?,
+                slot: input.u32()? as usize,
+                tick: input.u32()? as u16,
+                start: input.u32()? as u16,
+                from: (input.u32()?, input.u32()?),
+                distance: input.f32()?,
+            })
+        })?;
+        if input.at != bytes.len() {
+            return Err("The snapshot has trailing bytes.".to_string());
+        }
+
+        let snapshot = Snapshot {
+            time,
+            waves,
+            solver,
+            width,
+            height,
+            obstacles,
+            bathymetry,
+            slots,
+            tiles,
+            travel,
+            ripples,
+            heights,
+            previous,
+            drivers,
+        };
+        snapshot.validate()?;
+        Ok(snapshot)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let slots = self.ripples.iter().map(|ripple| ripple.slot as usize);
+        let slots = slots.chain(self.drivers.iter().map(|driver| driver.slot));
+        if self.slots as usize > MAX_SLOTS || slots.max().is_some_and(|slot| slot >= MAX_SLOTS) {
+            return Err(format!(
+                "The snapshot uses more than {} wave slots.",
+                MAX_SLOTS
+            ));
+        }
+        self.solver.validate()?;
+        // empty slots have no wavelength, and no heights
+        for (slot, wave) in self.waves.iter().enumerate() {
+            if wave.wavelength != 0 {
+                WaveDefinition::from_wave(slot, wave).validate()?;
+            }
+        }
+        let len = self.width as usize * self.height as usize;
+        let layers = self.slots as usize * len;
+        if self.obstacles.tiles.len() != len || self.bathymetry.speeds.len() != len {
+            return Err("The obstacles or depth map don't fit the grid.".to_string());
+        }
+        if self.tiles.len() != layers || self.travel.len() != layers {
+            return Err("The automaton's layers don't fit the grid.".to_string());
+        }
+        let fields = [self.heights.len(), self.previous.len()];
+        if self.solver.mode == SolverMode::WaveEquation && fields != [len, len] {
+            return Err("The wave equation's fields don't fit the grid.".to_string());
+        }
+        let tiles = self.ripples.iter().map(|ripple| (ripple.x, ripple.y));
+        let mut tiles = tiles.chain(self.drivers.iter().map(|driver| (driver.x, driver.y)));
+        if let Some((x, y)) = tiles.find(|(x, y)| *x >= self.width || *y >= self.height) {
+            return Err(format!(
+                "A wave at ({}, {}) is outside of the {}x{} grid.",
+                x, y, self.width, self.height
+            ));
+        }
+        let is_empty = |slot: usize| self.waves.get(slot).is_none_or(|w| w.wavelength == 0);
+        let activated = self
+            .tiles
+            .chunks(len.max(1))
+            .enumerate()
+            .filter(|(_, layer)| layer.iter().any(|tile| is_activated(*tile)))
+            .map(|(slot, _)| slot);
+        let slots = self.ripples.iter().map(|ripple| ripple.slot as usize);
+        let slots = slots.chain(self.drivers.iter().map(|driver| driver.slot));
+        if let Some(slot) = slots.chain(activated).find(|slot| is_empty(*slot)) {
+            return Err(format!(
+                "Wave slot {} has waves but is never defined.",
+                slot
+            ));
+        }
+        Ok(())
+    }
+
+    /// A solver in the exact state the snapshot was taken in.
+    pub fn restore(&self) -> Box<dyn Solver> {
+        match self.solver.mode {
+            SolverMode::Automaton => {
+                let mut sim = Simulation::new(self.width, self.height, self.slots as usize);
+                sim.boundary = self.solver.boundary;
+                sim.sponge_width = self.solver.sponge_width;
+                sim.tiles = self.tiles.clone();
+                sim.travel = self.travel.clone();
+                sim.ripples = self.ripples.clone();
+                sim.obstacles = self.obstacles.clone();
+                sim.set_bathymetry(self.bathymetry.clone());
+                Box::new(sim)
+            }
+            SolverMode::WaveEquation => {
+                let mut sim = WaveEquation::new(self.width, self.height, self.solver);
+                sim.heights = self.heights.clone();
+                sim.previous = self.previous.clone();
+                sim.drivers = self.drivers.clone();
+                sim.obstacles = self.obstacles.clone();
+                sim.bathymetry = self.bathymetry.clone();
+                Box::new(sim)
+            }
+        }
+    }
+}
+
+fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_f32(out: &mut Vec<u8>, value: f32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_list<T, F: Fn(&mut Vec<u8>, &T)>(out: &mut Vec<u8>, items: &[T], put: F) {
+    put_u32(out, items.len() as u32);
+    items.iter().for_each(|item| put(out, item));
+}
+
+struct Input<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Input<'a> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let bytes = self
+            .bytes
+            .get(self.at..self.at + N)
+            .ok_or_else(|| "The snapshot ends early.".to_string())?;
+        self.at += N;
+        Ok(bytes.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_le_bytes(self.take()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+
+    fn f32(&mut self) -> Result<f32, String> {
+        Ok(f32::from_le_bytes(self.take()?))
+    }
+
+    fn list<T, F: FnMut(&mut Input<'a>) -> Result<T, String>>(
+        &mut self,
+        mut item: F,
+    ) -> Result<Vec<T>, String> {
+        let len = self.u32()? as usize;
+        // every item is at least a byte long
+        if len > self.bytes.len() - self.at {
+            return Err("The snapshot ends early.".to_string());
+        }
+        (0..len).map(|_| item(self)).collect()
+    }
+}
@@ -1,4 +1,5 @@
 use cgmath::{Deg, Matrix4, SquareMatrix, Vector3};
+use std::io::{Read, Write};
 
 pub fn generate_transformation(
     location: Vector3<f32>,
@@ -34,6 +35,43 @@ pub fn generate_transformation(
             },
             Deg(rotation.2),
         );
-    let matrix = matrix * Matrix4::from_nonuniform_scale(scale.0, scale.1, scale.2);
-    matrix
+    matrix * Matrix4::from_nonuniform_scale(scale.0, scale.1, scale.2)
+}
+
+/// Decodes a PNG into one 0-255 brightness per pixel, row by row. Colours are averaged and
+/// fully transparent pixels count as white; 16-bit samples keep their high byte.
+pub fn read_grayscale_png<R: Read>(reader: R) -> Result<(u32, u32, Vec<u8>), String> {
+    let mut decoder = png::Decoder::new(reader);
+    // every sample comes out as one byte, whatever the depth, and palettes as colours
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let (info, mut reader) = decoder.read_info().map_err(|e| e.to_string())?;
+    let mut img = vec![0; info.buffer_size()];
+    reader.next_frame(&mut img).map_err(|e| e.to_string())?;
+
+    let samples = info.color_type.samples();
+    let pixels = img
+        .chunks(samples)
+        .map(|pixel| match pixel {
+            [gray] => *gray,
+            [_, 0] | [_, _, _, 0] => 255,
+            [gray, _] => *gray,
+            [r, g, b] | [r, g, b, _] => ((*r as u32 + *g as u32 + *b as u32) / 3) as u8,
+            _ => 255,
+        })
+        .collect();
+    Ok((info.width, info.height, pixels))
+}
+
+/// Encodes one 0-255 brightness per pixel, row by row, as an 8-bit grayscale PNG.
+pub fn write_grayscale_png<W: Write>(
+    writer: W,
+    width: u32,
+    height: u32,
+    pixels: &[u8],
+) -> Result<(), String> {
+    let mut encoder = png::Encoder::new(writer, width, height);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    writer.write_image_data(pixels).map_err(|e| e.to_string())
 }
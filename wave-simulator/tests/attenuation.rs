@@ -0,0 +1,142 @@
+use wave_simulator::wave::boundary::Boundary;
+use wave_simulator::wave::scene::Scene;
+use wave_simulator::wave::sim::{
+    attenuation, get_age, get_distance, pack_travel, Simulation, Solver, Wave, WaveKind,
+    WaveRegistry, RIGHT,
+};
+
+fn wave(kind: WaveKind, decay: f32, falloff: f32, lifetime: u32) -> WaveRegistry {
+    let mut waves = WaveRegistry::new();
+    waves.set(
+        0,
+        Wave {
+            directions: RIGHT,
+            wavelength: 4,
+            kind,
+            amplitude_factor: 1.0,
+            decay,
+            falloff,
+            lifetime,
+            ..Wave::default()
+        },
+    );
+    waves
+}
+
+// a right wave placed on the right of a row, and stepped `updates` times
+fn row(waves: &WaveRegistry, updates: usize) -> Simulation {
+    let mut sim = Simulation::new(20, 1, 1);
+    sim.place(15, 0, 0, waves);
+    (0..updates).for_each(|_| sim.step(waves));
+    sim
+}
+
+#[test]
+fn travel_packs_age_and_distance() {
+    let travel = pack_travel(12, 3);
+    assert_eq!((get_age(travel), get_distance(travel)), (12, 3));
+    assert_eq!(get_age(pack_travel(1 << 20, 0)), u16::MAX as u32);
+}
+
+#[test]
+fn tiles_count_updates_and_tiles() {
+    let waves = wave(WaveKind::Directional, 0.0, 0.0, 0);
+    let sim = row(&waves, 3);
+    // the source and the tiles the wave moved onto since
+    (12..16).for_each(|x| {
+        let travel = sim.get_travel(x, 0)[0];
+        assert_eq!(get_age(travel), 3);
+        assert_eq!(get_distance(travel), 15 - x);
+    });
+}
+
+#[test]
+fn waves_decay_over_time() {
+    let (still, fading) = (
+        wave(WaveKind::Directional, 0.0, 0.0, 0),
+        wave(WaveKind::Directional, 0.1, 0.0, 0),
+    );
+    let (a, b) = (row(&still, 3), row(&fading, 3));
+    (12..16).for_each(|x| {
+        let expected = a.height_at(x, 0, &still) * (-0.3f32).exp();
+        assert!((b.height_at(x, 0, &fading) - expected).abs() < 1e-6);
+    });
+}
+
+#[test]
+fn waves_fall_off_with_distance() {
+    let (still, fading) = (
+        wave(WaveKind::Directional, 0.0, 0.0, 0),
+        wave(WaveKind::Directional, 0.0, 0.5, 0),
+    );
+    let (a, b) = (row(&still, 3), row(&fading, 3));
+    (12..16).for_each(|x| {
+        let expected = a.height_at(x, 0, &still) / (1.0 + 0.5 * (15 - x) as f32);
+        assert!((b.height_at(x, 0, &fading) - expected).abs() < 1e-6);
+    });
+}
+
+#[test]
+fn no_attenuation_by_default() {
+    let waves = wave(WaveKind::Directional, 0.0, 0.0, 0);
+    assert_eq!(attenuation(waves.get(0).unwrap(), 1000, 1000.0), 1.0);
+}
+
+#[test]
+fn tiles_deactivate_after_their_lifetime() {
+    let run = |lifetime| {
+        let waves = wave(WaveKind::Directional, 0.0, 0.0, lifetime);
+        let mut sim = Simulation::new(10, 1, 1);
+        // the wave goes round and round forever otherwise
+        sim.boundary = Boundary::Periodic;
+        sim.place(5, 0, 0, &waves);
+        (0..30).for_each(|_| sim.step(&waves));
+        (0..10).any(|x| sim.height_at(x, 0, &waves) != 0.0)
+    };
+    assert!(run(0));
+    assert!(!run(20));
+}
+
+#[test]
+fn ripples_leave_after_their_lifetime() {
+    let waves = wave(WaveKind::Radial, 0.0, 0.0, 5);
+    let mut sim = Simulation::new(50, 50, 1);
+    sim.place(25, 25, 0, &waves);
+    (0..4).for_each(|_| sim.step(&waves));
+    assert_eq!(sim.ripples.len(), 1);
+    sim.step(&waves);
+    assert!(sim.ripples.is_empty());
+}
+
+#[test]
+fn ripples_fall_off_with_distance() {
+    let (still, fading) = (
+        wave(WaveKind::Radial, 0.0, 0.0, 0),
+        wave(WaveKind::Radial, 0.0, 1.0, 0),
+    );
+    let mut a = Simulation::new(21, 21, 1);
+    let mut b = Simulation::new(21, 21, 1);
+    a.place(10, 10, 0, &still);
+    b.place(10, 10, 0, &fading);
+    (0..6).for_each(|_| {
+        a.step(&still);
+        b.step(&fading);
+    });
+    let expected = a.height_at(14, 10, &still) / 5.0;
+    assert!(expected > 0.0);
+    assert!((b.height_at(14, 10, &fading) - expected).abs() < 1e-6);
+}
+
+#[test]
+fn scenes_set_attenuation() {
+    let src = "[[waves]]\nslot = 0\namplitude = 1.0\nwavelength = 6\ndirections = [\"up\"]\n\
+               decay = 0.05\nfalloff = 0.2\nlifetime = 300\n";
+    let waves = Scene::from_toml(src).unwrap().get_waves();
+    let wave = waves.get(0).unwrap();
+    assert_eq!((wave.decay, wave.falloff, wave.lifetime), (0.05, 0.2, 300));
+
+    let src = "[[waves]]\nslot = 0\namplitude = 1.0\nwavelength = 6\ndecay = -1.0\n";
+    assert!(Scene::from_toml(src).is_err());
+    let src = "[[waves]]\nslot = 0\namplitude = 1.0\nwavelength = 6\nlifetime = 100000\n";
+    assert!(Scene::from_toml(src).is_err());
+}
@@ -0,0 +1,164 @@
+use std::io::Cursor;
+use wave_simulator::wave::bathymetry::Bathymetry;
+use wave_simulator::wave::physics::WaveEquation;
+use wave_simulator::wave::scene::Scene;
+use wave_simulator::wave::sim::{
+    is_activated, pack_travel, Simulation, Solver, SolverMode, SolverSettings, Wave, WaveRegistry,
+    DOWN,
+};
+
+fn one_wave(directions: u8, wavelength: u8) -> WaveRegistry {
+    let mut waves = WaveRegistry::new();
+    waves.set(
+        0,
+        Wave {
+            directions,
+            wavelength,
+            amplitude_factor: 1.0,
+            ..Wave::default()
+        },
+    );
+    waves
+}
+
+// the first row the wave has reached in column x
+fn front(sim: &Simulation, x: u32) -> u32 {
+    (0..sim.height)
+        .rev()
+        .find(|y| is_activated(sim.get(0, x, *y).unwrap()))
+        .unwrap_or(0)
+}
+
+#[test]
+fn speed_goes_with_the_square_root_of_depth() {
+    let bathymetry = Bathymetry::from_fn(4, 1, |x, _| [1.0, 0.25, 0.0, 0.09][x as usize]);
+    assert_eq!(bathymetry.speed_at(0, 0), 1.0);
+    assert_eq!(bathymetry.speed_at(1, 0), 0.5);
+    assert_eq!(bathymetry.speed_at(2, 0), 0.1);
+    assert_eq!(bathymetry.get_lags(), vec![0, 1, 9, 2]);
+    assert_eq!(bathymetry.speed_at(7, 0), 1.0);
+}
+
+#[test]
+fn white_pixels_are_deep() {
+    let encode = |depth: png::BitDepth, pixels: &[u8]| {
+        let mut bytes = vec![];
+        {
+            let mut encoder = png::Encoder::new(&mut bytes, 2, 1);
+            encoder.set_color(png::ColorType::Grayscale);
+            encoder.set_depth(depth);
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(pixels).unwrap();
+        }
+        bytes
+    };
+    let bytes = encode(png::BitDepth::Eight, &[255, 0]);
+    let bathymetry = Bathymetry::from_png(Cursor::new(&bytes), 2, 1).unwrap();
+    assert_eq!(bathymetry.speed_at(0, 0), 1.0);
+    assert!(bathymetry.speed_at(1, 0) < 0.2);
+    assert!(Bathymetry::from_png(Cursor::new(&bytes), 1, 2).is_err());
+
+    // a 16-bit map is as deep as an 8-bit one, not twice as wide
+    let wide = encode(png::BitDepth::Sixteen, &[0xff, 0xff, 0x00, 0x00]);
+    assert_eq!(
+        Bathymetry::from_png(Cursor::new(&wide), 2, 1).unwrap(),
+        bathymetry
+    );
+    assert!(Bathymetry::from_png(Cursor::new(&wide), 4, 1).is_err());
+}
+
+#[test]
+fn automaton_waves_slow_down_in_the_shallows() {
+    let waves = one_wave(DOWN, 30);
+    let mut sim = Simulation::new(2, 30, 1);
+    // column 1 runs at half speed
+    sim.set_bathymetry(Bathymetry::from_fn(2, 30, |x, _| [1.0, 0.25][x as usize]));
+    sim.place(0, 0, 0, &waves);
+    sim.place(1, 0, 0, &waves);
+    (0..21).for_each(|_| sim.step(&waves));
+
+    assert_eq!(front(&sim, 0), 21);
+    // two updates per tile
+    assert_eq!(front(&sim, 1), 10);
+}
+
+#[test]
+fn short_waves_wait_out_long_lags() {
+    // a wave 4 updates long, where tiles take 10
+    let waves = one_wave(DOWN, 4);
+    let mut sim = Simulation::new(1, 12, 1);
+    sim.set_bathymetry(Bathymetry::from_fn(1, 12, |_, _| 0.0));
+    assert_eq!(sim.lags[0], 9);
+    sim.place(0, 0, 0, &waves);
+    (0..51).for_each(|_| sim.step(&waves));
+    assert_eq!(front(&sim, 0), 5);
+    assert_eq!(sim.get_travel(0, 5), vec![pack_travel(51, 5)]);
+
+    // across the shoal, where the scene's own waves are 12 long
+    let mut scene = Scene::load("scenes/shoal.toml").unwrap();
+    scene.waves[0].wavelength = 4;
+    let waves = scene.get_waves();
+    let mut sim = scene.instantiate();
+    let crossed = (0..150).any(|_| {
+        sim.step(&waves);
+        sim.height_at(50, 95, &waves) != 0.0
+    });
+    assert!(crossed);
+}
+
+#[test]
+fn flat_water_changes_nothing() {
+    let waves = one_wave(DOWN, 5);
+    let mut flat = Simulation::new(4, 12, 1);
+    let mut plain = Simulation::new(4, 12, 1);
+    flat.set_bathymetry(Bathymetry::flat(4, 12));
+    flat.place(1, 0, 0, &waves);
+    plain.place(1, 0, 0, &waves);
+    (0..9).for_each(|_| {
+        flat.step(&waves);
+        plain.step(&waves);
+    });
+    assert_eq!(flat.tiles, plain.tiles);
+}
+
+#[test]
+fn ripples_lag_behind_over_shallows() {
+    let settings = SolverSettings {
+        mode: SolverMode::WaveEquation,
+        damping: 0.0,
+        ..SolverSettings::default()
+    };
+    let waves = one_wave(0, 6);
+    let mut sim = WaveEquation::new(41, 41, settings);
+    // shallow to the right of the source
+    sim.set_bathymetry(Bathymetry::from_fn(
+        41,
+        41,
+        |x, _| {
+            if x > 20 {
+                0.2
+            } else {
+                1.0
+            }
+        },
+    ));
+    sim.place(20, 20, 0, &waves);
+    (0..24).for_each(|_| sim.step(&waves));
+
+    let reached = |x: u32| sim.height_at(x, 20, &waves).abs() > 1e-3;
+    assert!(reached(8));
+    assert!(!reached(32));
+}
+
+#[test]
+fn scenes_load_their_bathymetry() {
+    let scene = Scene::load("scenes/shoal.toml").unwrap();
+    let bathymetry = scene.bathymetry_map.as_ref().unwrap();
+    assert!(bathymetry.speed_at(50, 55) < 0.2);
+    assert_eq!(bathymetry.speed_at(5, 5), 1.0);
+
+    let waves = scene.get_waves();
+    let mut sim = scene.instantiate();
+    (0..40).for_each(|_| sim.step(&waves));
+    assert!(sim.height_at(20, 40, &waves) != 0.0 || sim.height_at(20, 41, &waves) != 0.0);
+}
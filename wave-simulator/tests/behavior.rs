@@ -0,0 +1,114 @@
+use wave_simulator::behavior::Behavior;
+use wave_simulator::wave::backend::{Backend, SoftwareBackend};
+use wave_simulator::wave::behavior::main::MainBehavior;
+use wave_simulator::wave::grid::Grid;
+use wave_simulator::wave::sim::{Simulation, Solver, Wave, WaveRegistry, UP};
+use wave_simulator::wave::WaveApp;
+use winit::event::{ElementState, VirtualKeyCode};
+
+fn app() -> WaveApp<SoftwareBackend> {
+    let mut app = WaveApp {
+        backend: Some(SoftwareBackend::new(Grid::default(), (64, 48))),
+        window_size: (64, 48),
+        ..WaveApp::default()
+    };
+    MainBehavior.init(&mut app);
+    app
+}
+
+fn press(app: &mut WaveApp<SoftwareBackend>, key: VirtualKeyCode) {
+    MainBehavior.on_keyboard_update(app, key, ElementState::Pressed);
+}
+
+// the way a person types it into the console
+fn run(app: &mut WaveApp<SoftwareBackend>, line: &str) {
+    press(app, VirtualKeyCode::Return);
+    line.chars().for_each(|c| MainBehavior.on_character(app, c));
+    press(app, VirtualKeyCode::Return);
+    press(app, VirtualKeyCode::Escape);
+}
+
+// like the event loop does it
+fn frames(app: &mut WaveApp<SoftwareBackend>, count: u64) {
+    for _ in 0..count {
+        MainBehavior.update(app);
+        app.time += 1;
+        MainBehavior.draw(app);
+    }
+}
+
+#[test]
+fn placed_waves_spread_and_get_drawn() {
+    let mut app = app();
+    run(&mut app, "wave 0 amp=1 len=8 dir=up");
+    run(&mut app, "place 0 50 50");
+    assert_eq!(app.backend.as_ref().unwrap().get_slot_count(), 1);
+
+    let every = app.config.frames_per_update;
+    frames(&mut app, every * 4 + 1);
+    assert_eq!(app.updates, 4);
+
+    let backend = app.backend.as_ref().unwrap();
+    let (tiles, _) = backend.read_layers();
+    assert!(tiles.iter().filter(|&&tile| tile != 0).count() > 1);
+    let frame = &backend.renderer.frame;
+    assert_eq!((frame.width, frame.height), (64, 48));
+    assert!(frame.pixels.chunks(4).any(|pixel| pixel[..3] != [0, 0, 0]));
+}
+
+#[test]
+fn paused_water_stays_put() {
+    let mut app = app();
+    run(&mut app, "wave 0 amp=1 len=8 dir=up");
+    run(&mut app, "place 0 50 50");
+    let placed = app.backend.as_ref().unwrap().read_layers();
+
+    app.paused = true;
+    let every = app.config.frames_per_update;
+    frames(&mut app, every * 3);
+    assert_eq!(app.updates, 0);
+    assert_eq!(app.backend.as_ref().unwrap().read_layers(), placed);
+}
+
+#[test]
+fn clearing_a_tile_empties_it() {
+    let mut app = app();
+    run(&mut app, "wave 0 amp=1 len=8 dir=up");
+    run(&mut app, "place 0 50 50");
+    assert_ne!(app.backend.as_ref().unwrap().get_tile(0, 50, 50), Some(0));
+    run(&mut app, "clear 50 50");
+    assert_eq!(app.backend.as_ref().unwrap().get_tile(0, 50, 50), Some(0));
+}
+
+#[test]
+fn snapshots_bring_their_layers_along() {
+    // more layers than waves, like after a wave is taken out of the last slot
+    let mut waves = WaveRegistry::new();
+    waves.set(
+        0,
+        Wave {
+            directions: UP,
+            wavelength: 8,
+            amplitude_factor: 1.0,
+            ..Wave::default()
+        },
+    );
+    let mut sim = Simulation::new(20, 20, 3);
+    sim.place(5, 5, 0, &waves);
+    sim.step(&waves);
+    let snapshot = sim.snapshot(&waves, 1);
+    assert_eq!(snapshot.slots, 3);
+    let path =
+        std::env::temp_dir().join(format!("wave-simulator-layers-{}.snap", std::process::id()));
+    snapshot.save(&path).unwrap();
+
+    let mut app = app();
+    run(&mut app, &format!("snapshot load {}", path.display()));
+    let backend = app.backend.as_ref().unwrap();
+    assert_eq!(backend.get_slot_count(), 3);
+    assert_eq!(
+        backend.read_layers(),
+        (snapshot.tiles.clone(), snapshot.travel.clone())
+    );
+    std::fs::remove_file(&path).unwrap();
+}
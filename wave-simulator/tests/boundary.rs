@@ -0,0 +1,187 @@
+use wave_simulator::wave::boundary::Boundary;
+use wave_simulator::wave::physics::WaveEquation;
+use wave_simulator::wave::sim::{
+    is_activated, Simulation, Solver, SolverMode, SolverSettings, Wave, WaveKind, WaveRegistry,
+    RIGHT, UP,
+};
+
+fn one_wave(directions: u8, wavelength: u8) -> WaveRegistry {
+    let mut waves = WaveRegistry::new();
+    waves.set(
+        0,
+        Wave {
+            directions,
+            wavelength,
+            amplitude_factor: 1.0,
+            ..Wave::default()
+        },
+    );
+    waves
+}
+
+fn settings(boundary: Boundary) -> SolverSettings {
+    SolverSettings {
+        mode: SolverMode::WaveEquation,
+        damping: 0.0,
+        boundary,
+        ..SolverSettings::default()
+    }
+}
+
+// a ripple from the middle of the grid, long after it has reached the edges
+fn ripple(boundary: Boundary) -> WaveEquation {
+    let waves = one_wave(0, 6);
+    let mut sim = WaveEquation::new(30, 30, settings(boundary));
+    sim.place(15, 15, 0, &waves);
+    (0..120).for_each(|_| sim.step(&waves));
+    sim
+}
+
+fn energy(sim: &WaveEquation) -> f32 {
+    sim.heights.iter().map(|h| h * h).sum()
+}
+
+#[test]
+fn reads_past_the_edges() {
+    assert_eq!(Boundary::Fixed.resolve(-1, 3, 10, 10), None);
+    assert_eq!(Boundary::Fixed.resolve(2, 3, 10, 10), Some((2, 3)));
+    assert_eq!(Boundary::Reflective.resolve(-1, 10, 10, 10), Some((0, 9)));
+    assert_eq!(Boundary::Absorbing.resolve(10, -1, 10, 10), Some((9, 0)));
+    assert_eq!(Boundary::Periodic.resolve(-1, 10, 10, 10), Some((9, 0)));
+}
+
+#[test]
+fn only_absorbing_edges_have_a_sponge() {
+    assert_eq!(Boundary::Absorbing.sponge(0, 5, 20, 20, 4), 1.0);
+    assert_eq!(Boundary::Absorbing.sponge(2, 19, 20, 20, 4), 1.0);
+    assert!(Boundary::Absorbing.sponge(2, 5, 20, 20, 4) > 0.0);
+    assert_eq!(Boundary::Absorbing.sponge(4, 10, 20, 20, 4), 0.0);
+    assert_eq!(Boundary::Reflective.sponge(0, 0, 20, 20, 4), 0.0);
+}
+
+#[test]
+fn periodic_automaton_wraps_around() {
+    let waves = one_wave(UP | RIGHT, 20);
+    let mut sim = Simulation::new(5, 5, 1);
+    sim.boundary = Boundary::Periodic;
+    sim.place(0, 0, 0, &waves);
+    sim.step(&waves);
+    // up reads the tile below, right the tile to the right
+    assert!(is_activated(sim.get(0, 0, 4).unwrap()));
+    assert!(is_activated(sim.get(0, 4, 0).unwrap()));
+
+    let mut fixed = Simulation::new(5, 5, 1);
+    fixed.place(0, 0, 0, &waves);
+    fixed.step(&waves);
+    assert!(!is_activated(fixed.get(0, 0, 4).unwrap()));
+}
+
+#[test]
+fn both_solvers_have_every_edge() {
+    let edges = [
+        Boundary::Fixed,
+        Boundary::Reflective,
+        Boundary::Absorbing,
+        Boundary::Periodic,
+    ];
+    for boundary in edges.iter() {
+        let automaton = SolverSettings {
+            boundary: *boundary,
+            ..SolverSettings::default()
+        };
+        assert!(automaton.validate().is_ok());
+        assert!(settings(*boundary).validate().is_ok());
+    }
+    let src = "[solver]\nboundary = \"absorbing\"\n";
+    assert!(wave_simulator::wave::scene::Scene::from_toml(src).is_ok());
+}
+
+#[test]
+fn reflective_automaton_turns_waves_around() {
+    // up moves to lower rows, so nothing below where it's placed is ever reached on its own
+    let waves = one_wave(UP, 2);
+    let run = |boundary| {
+        let mut sim = Simulation::new(5, 8, 1);
+        sim.boundary = boundary;
+        sim.place(2, 3, 0, &waves);
+        (0..12).any(|_| {
+            sim.step(&waves);
+            is_activated(sim.get(0, 2, 6).unwrap())
+        })
+    };
+    assert!(run(Boundary::Reflective));
+    assert!(!run(Boundary::Fixed));
+    assert!(!run(Boundary::Absorbing));
+}
+
+#[test]
+fn reflective_ripples_bounce_back() {
+    let mut waves = one_wave(0, 2);
+    let mut wave = *waves.get(0).unwrap();
+    wave.kind = WaveKind::Radial;
+    waves.set(0, wave);
+    // 7 tiles from the ripple, and 13 from its mirror image across the left edge
+    let height = |boundary| {
+        let mut sim = Simulation::new(20, 20, 1);
+        sim.boundary = boundary;
+        sim.place(3, 10, 0, &waves);
+        (0..14).for_each(|_| sim.step(&waves));
+        sim.height_at(10, 10, &waves)
+    };
+    assert!((height(Boundary::Reflective) - 1.0).abs() < 1e-5);
+    assert_eq!(height(Boundary::Fixed), 0.0);
+}
+
+#[test]
+fn absorbing_automaton_fades_out() {
+    let waves = one_wave(0, 4);
+    let mut sim = Simulation::new(20, 20, 1);
+    sim.boundary = Boundary::Absorbing;
+    sim.sponge_width = 4;
+    sim.place(0, 10, 0, &waves);
+    sim.place(2, 10, 0, &waves);
+    sim.place(10, 10, 0, &waves);
+    sim.step(&waves);
+    assert_eq!(sim.height_at(0, 10, &waves), 0.0);
+    let inside = sim.height_at(10, 10, &waves);
+    assert!(inside > 0.0);
+    assert!(sim.height_at(2, 10, &waves) < inside);
+}
+
+#[test]
+fn sponges_soak_up_ripples() {
+    let reflective = energy(&ripple(Boundary::Reflective));
+    let fixed = energy(&ripple(Boundary::Fixed));
+    let absorbing = energy(&ripple(Boundary::Absorbing));
+    assert!(absorbing < reflective * 0.1);
+    assert!(absorbing < fixed * 0.1);
+}
+
+#[test]
+fn fixed_edges_stay_flat_and_reflective_ones_move() {
+    let (fixed, reflective) = (ripple(Boundary::Fixed), ripple(Boundary::Reflective));
+    let edge = |sim: &WaveEquation| (0..30).map(|x| sim.heights[x].abs()).sum::<f32>();
+    assert!(edge(&reflective) > 0.0);
+    // the edge tiles themselves are free; only what's past them is held at 0
+    assert!(edge(&fixed) < edge(&reflective));
+}
+
+#[test]
+fn periodic_ripples_stay_symmetric() {
+    let waves = one_wave(0, 6);
+    let mut sim = WaveEquation::new(20, 20, settings(Boundary::Periodic));
+    sim.place(0, 0, 0, &waves);
+    (0..30).for_each(|_| sim.step(&waves));
+    let at = |x: u32, y: u32| sim.height_at(x, y, &waves);
+    assert!(at(3, 0).abs() > 1e-4);
+    assert!((at(3, 0) - at(17, 0)).abs() < 1e-5);
+    assert!((at(0, 3) - at(0, 17)).abs() < 1e-5);
+}
+
+#[test]
+fn scenes_pick_their_boundary() {
+    let src = "[solver]\nboundary = \"periodic\"\n";
+    let scene = wave_simulator::wave::scene::Scene::from_toml(src).unwrap();
+    assert_eq!(scene.solver.boundary, Boundary::Periodic);
+    assert_eq!(SolverSettings::default().boundary, Boundary::Fixed);
+}
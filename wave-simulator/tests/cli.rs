@@ -0,0 +1,185 @@
+use std::path::PathBuf;
+use wave_simulator::wave::cli::Options;
+use wave_simulator::wave::export::Format;
+use wave_simulator::wave::scene::Scene;
+use wave_simulator::wave::sim::SolverMode;
+
+fn parse(args: &str) -> Result<Options, String> {
+    let args = args
+        .split_whitespace()
+        .map(String::from)
+        .collect::<Vec<String>>();
+    Options::parse(&args)
+}
+
+// a fresh directory for one test to write into
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("wave-simulator-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn options_parse_in_any_order() {
+    let options = parse(
+        "--paused scenes/example.toml --solver wave-equation --grid 160x90 \
+         --cell-size 0.5 run.toml --headless 200 --out runs --set fps=30",
+    )
+    .unwrap();
+    assert_eq!(options.scene, Some(PathBuf::from("scenes/example.toml")));
+    assert_eq!(options.replay, Some(PathBuf::from("run.toml")));
+    assert_eq!(options.solver, Some(SolverMode::WaveEquation));
+    assert_eq!(options.grid, Some((160, 90)));
+    assert_eq!(options.cell_size, Some(0.5));
+    assert!(options.paused);
+    assert_eq!(options.headless, Some(200));
+    assert_eq!(options.get_out(), PathBuf::from("runs"));
+    assert_eq!(options.get_config().unwrap().fps, 30.0);
+
+    let options = parse("").unwrap();
+    assert_eq!(options.scene, None);
+    assert!(!options.paused);
+    assert_eq!(options.get_out(), PathBuf::from("."));
+}
+
+#[test]
+fn bad_options_are_rejected() {
+    assert!(parse("--solver lattice").is_err());
+    assert!(parse("--grid 160").is_err());
+    assert!(parse("--grid 160x").is_err());
+    assert!(parse("--headless many").is_err());
+    assert!(parse("--headless").is_err());
+    assert!(parse("--fast").is_err());
+    assert!(parse("--every 0").is_err());
+    assert!(parse("--range -1").is_err());
+    assert!(parse("--format gif").is_err());
+    assert!(parse("a.toml b.toml c.toml").is_err());
+    assert!(parse("--set fps=0").unwrap().get_config().is_err());
+    assert!(parse("--set nothing=1").unwrap().get_config().is_err());
+}
+
+#[test]
+fn both_binaries_take_the_same_options() {
+    let split = |args: &str| {
+        args.split_whitespace()
+            .map(String::from)
+            .collect::<Vec<String>>()
+    };
+    let options = "--every 50 --at 3,7 --format png,npy --range 2 --replay run.toml \
+                   --solver wave-equation --mesh water.glb --snapshot run.snap";
+    let headless =
+        Options::parse_headless(&split(&format!("scenes/example.toml 200 {}", options))).unwrap();
+    let windowed = parse(&format!("scenes/example.toml --headless 200 {}", options)).unwrap();
+    assert_eq!(headless, windowed);
+    assert_eq!(headless.every, Some(50));
+    assert_eq!(headless.at, vec![3, 7]);
+    assert_eq!(headless.get_formats(), vec![Format::Png, Format::Npy]);
+    assert_eq!(headless.replay, Some(PathBuf::from("run.toml")));
+
+    assert!(Options::parse_headless(&split("scenes/example.toml")).is_err());
+    assert!(Options::parse_headless(&split("scenes/example.toml many")).is_err());
+    assert!(parse("a.toml b.toml --replay c.toml").is_err());
+}
+
+#[test]
+fn options_go_over_the_scene() {
+    let scene = Options {
+        scene: Some(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("scenes/example.toml")),
+        ..Options::default()
+    };
+    let original = scene.get_scene().unwrap();
+    assert_eq!(original.solver.mode, SolverMode::Automaton);
+
+    let changed = Options {
+        solver: Some(SolverMode::WaveEquation),
+        grid: Some((120, 110)),
+        cell_size: Some(0.25),
+        ..scene.clone()
+    }
+    .get_scene()
+    .unwrap();
+    assert_eq!(changed.solver.mode, SolverMode::WaveEquation);
+    assert_eq!((changed.grid.width, changed.grid.height), (120, 110));
+    assert_eq!(changed.grid.cell_size, 0.25);
+    assert_eq!(changed.waves, original.waves);
+
+    // the example's sources don't fit on a smaller grid
+    let smaller = Options {
+        grid: Some((20, 20)),
+        ..scene
+    };
+    assert!(smaller.get_scene().is_err());
+
+    let empty = parse("--grid 30x40").unwrap().get_scene().unwrap();
+    assert_eq!((empty.grid.width, empty.grid.height), (30, 40));
+    assert!(empty.waves.is_empty());
+}
+
+#[test]
+fn headless_runs_write_the_last_tick() {
+    let dir = scratch("cli");
+    let scene = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("scenes/example.toml");
+    let options = Options {
+        scene: Some(scene.clone()),
+        out: Some(dir.clone()),
+        ..Options::default()
+    };
+    let paths = options.run_headless(12).unwrap();
+    assert_eq!(paths, vec![dir.join("tick_000012.txt")]);
+    assert_eq!(options.get_formats(), vec![Format::Txt]);
+
+    let scene = Scene::load(&scene).unwrap();
+    let mut sim = scene.instantiate();
+    let waves = scene.get_waves();
+    (0..12).for_each(|_| sim.step(&waves));
+    let rows = std::fs::read_to_string(&paths[0]).unwrap();
+    let row = rows.lines().nth(90).unwrap();
+    let first = row.split(' ').next().unwrap().parse::<f32>().unwrap();
+    assert_eq!(first, sim.height_at(0, 90, &waves));
+    assert_eq!(rows.lines().count(), 100);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn headless_runs_export_the_selected_ticks_and_carry_on_from_snapshots() {
+    let dir = scratch("every");
+    let snapshot = dir.join("run.snap");
+    let options = parse(&format!(
+        "{}/scenes/example.toml --headless 20 --every 10 --format txt,npy --out {} --snapshot {}",
+        env!("CARGO_MANIFEST_DIR"),
+        dir.display(),
+        snapshot.display()
+    ))
+    .unwrap();
+    let paths = options.run_headless(20).unwrap();
+    let names = paths
+        .iter()
+        .map(|path| path.file_name().unwrap().to_str().unwrap())
+        .collect::<Vec<&str>>();
+    assert_eq!(
+        names,
+        vec![
+            "tick_000000.txt",
+            "tick_000000.npy",
+            "tick_000010.txt",
+            "tick_000010.npy",
+            "tick_000020.txt",
+            "tick_000020.npy",
+            "run.snap"
+        ]
+    );
+
+    // ticks carry on from the snapshot's
+    let args = vec![
+        snapshot.display().to_string(),
+        "5".to_string(),
+        "--out".to_string(),
+        dir.display().to_string(),
+    ];
+    let resumed = Options::parse_headless(&args).unwrap();
+    assert_eq!(
+        resumed.run_headless(5).unwrap(),
+        vec![dir.join("tick_000025.txt")]
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
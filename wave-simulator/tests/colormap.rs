@@ -0,0 +1,233 @@
+use wave_simulator::wave::camera::Camera;
+use wave_simulator::wave::command::{Command, View};
+use wave_simulator::wave::config::Config;
+use wave_simulator::wave::grid::Grid;
+use wave_simulator::wave::physics::WaveEquation;
+use wave_simulator::wave::render::colormap::{Colormap, Mapping, Quantity, STRIP_WIDTH};
+use wave_simulator::wave::render::software::SoftwareRenderer;
+use wave_simulator::wave::render::{to_rgba8, Renderer, Shading, Surface, LAND_COLOUR};
+use wave_simulator::wave::sim::{Simulation, Solver, SolverSettings, Wave, WaveKind, WaveRegistry};
+
+fn wave(kind: WaveKind, directions: u8, amplitude: f32) -> Wave {
+    Wave {
+        directions,
+        wavelength: 8,
+        kind,
+        amplitude_factor: amplitude,
+        ..Wave::default()
+    }
+}
+
+#[test]
+fn colormaps_run_from_end_to_end() {
+    let viridis = Colormap::Viridis;
+    assert_eq!(to_rgba8(viridis.colour(0.0)), [0x44, 0x01, 0x54, 255]);
+    assert_eq!(to_rgba8(viridis.colour(1.0)), [0xfd, 0xe7, 0x25, 255]);
+    // past the ends are the ends
+    assert_eq!(viridis.colour(-3.0), viridis.colour(0.0));
+    assert_eq!(viridis.colour(7.0), viridis.colour(1.0));
+
+    let blue_red = Colormap::BlueRed;
+    let [r, _, b, _] = to_rgba8(blue_red.colour(0.0));
+    assert!(b > r);
+    let [r, _, b, _] = to_rgba8(blue_red.colour(1.0));
+    assert!(r > b);
+    assert_eq!(to_rgba8(blue_red.colour(0.5)), [0xf7, 0xf7, 0xf7, 255]);
+
+    let strip = viridis.strip();
+    assert_eq!((strip.width, strip.height), (STRIP_WIDTH, 1));
+    // a colour for the middle of every texel, the way the GPU samples it
+    let texel = 1.0 / STRIP_WIDTH as f32;
+    assert_eq!(strip.get(0, 0), to_rgba8(viridis.colour(texel / 2.0)));
+    assert_eq!(
+        strip.get(STRIP_WIDTH - 1, 0),
+        to_rgba8(viridis.colour(1.0 - texel / 2.0))
+    );
+
+    assert_eq!(Colormap::parse("blue-red"), Ok(Colormap::BlueRed));
+    assert!(Colormap::parse("jet").is_err());
+    assert_eq!(Quantity::parse("tick"), Ok(Quantity::Tick));
+    assert!(Quantity::parse("speed").is_err());
+}
+
+#[test]
+fn mappings_suit_what_they_show() {
+    let mut waves = WaveRegistry::new();
+    waves.set(0, wave(WaveKind::Directional, 8, 1.5));
+    waves.set(1, wave(WaveKind::Radial, 0, -2.0));
+
+    let height = Mapping::new(Quantity::Height, 0, &waves);
+    assert_eq!(height.colormap, Colormap::BlueRed);
+    assert_eq!(height.range, (-2.0, 2.0));
+    assert_eq!(height.position(0.0), 0.5);
+    assert_eq!(height.colour(2.0), Colormap::BlueRed.colour(1.0));
+
+    let tick = Mapping::new(Quantity::Tick, 1, &waves);
+    assert_eq!((tick.colormap, tick.range), (Colormap::Viridis, (0.0, 8.0)));
+    let activation = Mapping::new(Quantity::Activation, 0, &WaveRegistry::new());
+    assert_eq!(activation.range, (0.0, 1.0));
+    // no waves to go by
+    let flat = Mapping::new(Quantity::Height, 0, &WaveRegistry::new());
+    assert_eq!(flat.range, (-1.0, 1.0));
+}
+
+#[test]
+fn ticks_are_read_per_slot() {
+    let mut waves = WaveRegistry::new();
+    // left takes from the tile to the left, so it moves along x
+    waves.set(0, wave(WaveKind::Directional, 4, 1.0));
+    waves.set(1, wave(WaveKind::Radial, 0, 1.0));
+    let mut sim = Simulation::new(9, 9, 2);
+    sim.place(0, 4, 0, &waves);
+    sim.place(4, 4, 1, &waves);
+    (0..3).for_each(|_| sim.step(&waves));
+
+    // the directional wave's layer
+    assert_eq!(sim.slot_tick(0, 0, 4, &waves), Some(3.0));
+    assert_eq!(sim.slot_tick(0, 3, 4, &waves), Some(1.0));
+    assert_eq!(sim.slot_tick(0, 5, 4, &waves), None);
+    // and the radial one's ripple, two tiles out
+    assert_eq!(sim.slot_tick(1, 4, 4, &waves), Some(3.0));
+    assert_eq!(sim.slot_tick(1, 6, 4, &waves), Some(1.0));
+    assert_eq!(sim.slot_tick(1, 8, 8, &waves), None);
+    assert_eq!(sim.slot_tick(5, 0, 4, &waves), None);
+
+    // vertices go the other way from tiles along y, but not along x
+    let grid = Grid::new(9, 9);
+    let vertex = |x: u32, y: u32| (grid.height - 1 - y) * grid.width + x;
+    let ticks = Mapping::new(Quantity::Tick, 0, &waves).values(&sim, &waves);
+    let active = Mapping::new(Quantity::Activation, 0, &waves).values(&sim, &waves);
+    assert_eq!(ticks.len(), grid.get_len());
+    assert_eq!(ticks[vertex(3, 4) as usize], 1.0);
+    assert_eq!(active[vertex(3, 4) as usize], 1.0);
+    assert_eq!(ticks[vertex(5, 4) as usize], 0.0);
+    assert_eq!(active[vertex(5, 4) as usize], 0.0);
+
+    // the wave equation has no slots
+    let mut field = WaveEquation::new(9, 9, SolverSettings::default());
+    field.place(4, 4, 0, &waves);
+    assert_eq!(field.slot_tick(0, 4, 4, &waves), None);
+}
+
+#[test]
+fn the_legend_shows_the_colormap() {
+    let mut waves = WaveRegistry::new();
+    waves.set(2, wave(WaveKind::Directional, 8, 1.0));
+    let legend = Mapping::new(Quantity::Tick, 2, &waves).legend();
+    // the bar goes low to high, left to right
+    let (left, right) = (legend.get(3, 16), legend.get(legend.width - 4, 16));
+    assert_eq!(left, to_rgba8(Colormap::Viridis.colour(0.0)));
+    assert_eq!(right, to_rgba8(Colormap::Viridis.colour(1.0)));
+    // with text above and below it, on a see-through background
+    let lit = |rows: std::ops::Range<u32>| {
+        rows.flat_map(|y| (0..legend.width).map(move |x| (x, y)))
+            .filter(|(x, y)| legend.get(*x, *y) == [255, 255, 255, 255])
+            .count()
+    };
+    assert!(lit(0..12) > 0);
+    assert!(lit(21..legend.height) > 0);
+    assert_eq!(legend.get(0, 0)[3], 160);
+}
+
+#[test]
+fn huge_ranges_fit_in_the_legend() {
+    let legend = match Command::parse("view height range=-1e19,1e19").unwrap() {
+        Command::View(Some(view)) => view.to_mapping(0, &WaveRegistry::new()).legend(),
+        command => panic!("{:?} isn't a view", command),
+    };
+    let lit = |columns: std::ops::Range<u32>| {
+        columns
+            .flat_map(|x| (21..legend.height).map(move |y| (x, y)))
+            .filter(|(x, y)| legend.get(*x, *y) == [255, 255, 255, 255])
+            .count()
+    };
+    // both ends are written, apart, and inside the legend
+    assert!(lit(0..legend.width / 2) > 0);
+    assert!(lit(legend.width / 2..legend.width) > 0);
+    let middle = legend.width / 2;
+    assert_eq!(lit(middle - 4..middle + 4), 0);
+    assert_eq!(lit(legend.width - 3..legend.width), 0);
+}
+
+#[test]
+fn mapped_water_is_drawn_in_the_colormap() {
+    let grid = Grid::new(20, 20);
+    let mut surface = Surface::flat(grid);
+    // the values rise left to right, half way in the middle
+    surface.values = (0..grid.get_len())
+        .map(|i| (i as u32 % grid.width) as f32)
+        .collect();
+    let mapping = Mapping {
+        quantity: Quantity::Height,
+        slot: 0,
+        colormap: Colormap::Viridis,
+        range: (0.0, 20.0),
+    };
+    let camera = Camera {
+        y: 30.0,
+        pitch: std::f32::consts::FRAC_PI_2,
+        ..Camera::default()
+    };
+    let mut renderer = SoftwareRenderer::new(64, 64);
+    renderer.upload_surface(&surface);
+    renderer.clear([0.0, 0.0, 0.0, 1.0]);
+    renderer.draw_water(
+        Config::default().projection_matrix(1.0),
+        camera.get_matrix(),
+        &Shading::Mapped(mapping),
+    );
+    let frame = &renderer.frame;
+    // the middle of the grid is half way along, and unlit
+    let middle = frame.get(32, 32);
+    let expected = to_rgba8(Colormap::Viridis.colour(0.5));
+    assert!((0..3).all(|c| (middle[c] as i32 - expected[c] as i32).abs() <= 8));
+    let (left, right) = (frame.get(20, 32), frame.get(44, 32));
+    assert!(right[1] > left[1]);
+    // land is still land
+    surface.land = vec![true; grid.get_len()];
+    renderer.upload_surface(&surface);
+    renderer.clear([0.0, 0.0, 0.0, 1.0]);
+    renderer.draw_water(
+        Config::default().projection_matrix(1.0),
+        camera.get_matrix(),
+        &Shading::Mapped(mapping),
+    );
+    assert_eq!(renderer.frame.get(32, 32), to_rgba8(LAND_COLOUR));
+}
+
+#[test]
+fn views_pick_what_they_leave_out() {
+    let view = |line: &str| match Command::parse(line).unwrap() {
+        Command::View(view) => view,
+        command => panic!("{:?} isn't a view", command),
+    };
+    assert_eq!(view("view water"), None);
+    assert_eq!(
+        view("view tick slot=2 map=blue-red range=-1,4"),
+        Some(View {
+            quantity: Quantity::Tick,
+            slot: Some(2),
+            colormap: Some(Colormap::BlueRed),
+            range: Some((-1.0, 4.0)),
+        })
+    );
+    assert!(Command::parse("view speed").is_err());
+    assert!(Command::parse("view height range=2,1").is_err());
+    assert!(Command::parse("view height map=jet").is_err());
+    assert!(Command::parse("view height zoom=2").is_err());
+    assert!(Command::parse("view")
+        .unwrap_err()
+        .starts_with("Usage: view"));
+
+    let mut waves = WaveRegistry::new();
+    waves.set(3, wave(WaveKind::Directional, 8, 1.0));
+    let mapping = view("view tick").unwrap().to_mapping(3, &waves);
+    assert_eq!(mapping, Mapping::new(Quantity::Tick, 3, &waves));
+    let mapping = view("view height map=viridis")
+        .unwrap()
+        .to_mapping(3, &waves);
+    assert_eq!(
+        (mapping.colormap, mapping.range),
+        (Colormap::Viridis, (-1.0, 1.0))
+    );
+}
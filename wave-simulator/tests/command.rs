@@ -0,0 +1,109 @@
+use std::path::PathBuf;
+use wave_simulator::wave::command::{Command, DepthMap};
+use wave_simulator::wave::export::Format;
+use wave_simulator::wave::scene::Direction;
+use wave_simulator::wave::sim::WaveKind;
+
+fn wave(line: &str) -> wave_simulator::wave::scene::WaveDefinition {
+    match Command::parse(line).unwrap() {
+        Command::Wave(wave) => wave,
+        command => panic!("{:?} isn't a wave", command),
+    }
+}
+
+#[test]
+fn waves_parse() {
+    let definition = wave("wave 2 amp=3 len=20 dir=up,right");
+    assert_eq!(definition.slot, 2);
+    assert_eq!(definition.amplitude, 3.0);
+    assert_eq!(definition.wavelength, 20);
+    assert_eq!(definition.directions, vec![Direction::Up, Direction::Right]);
+    assert_eq!(definition.kind, WaveKind::Directional);
+    assert_eq!(definition.to_wave().directions, 1 | 8);
+
+    assert_eq!(wave("wave 0 amp=1 len=8 dir=all").kind, WaveKind::Radial);
+    let plane = wave("wave 0   len=8 heading=45 amp=-1.5");
+    assert_eq!((plane.kind, plane.heading), (WaveKind::Plane, 45.0));
+    let fading = wave("wave 1 amp=1 len=8 decay=0.01 falloff=0.1 life=600");
+    assert_eq!(
+        (fading.decay, fading.falloff, fading.lifetime),
+        (0.01, 0.1, 600)
+    );
+}
+
+#[test]
+fn bad_waves_are_rejected() {
+    for line in [
+        "wave",
+        "wave two amp=1 len=8",
+        "wave 0 len=8",
+        "wave 0 amp=1",
+        "wave 0 amp=1 len=8 dir=sideways",
+        "wave 0 amp=1 len=8 speed=2",
+        "wave 0 amp=1 len=8 up",
+        "wave 0 amp=51 len=8",
+        "wave 0 amp=1 len=300",
+        "wave 0 amp=1 len=8 decay=-1",
+        "wave 0 amp=1 len=8 life=70000",
+        "wave 0 amp=1 len=8 dir=up heading=90",
+    ]
+    .iter()
+    {
+        assert!(Command::parse(line).is_err(), "{}", line);
+    }
+}
+
+#[test]
+fn commands_parse() {
+    let parse = |line: &str| Command::parse(line).unwrap();
+    assert_eq!(
+        parse("place 2"),
+        Command::Place {
+            slot: 2,
+            tile: None
+        }
+    );
+    assert_eq!(
+        parse("place 0 10 20"),
+        Command::Place {
+            slot: 0,
+            tile: Some((10, 20))
+        }
+    );
+    assert_eq!(parse("obstacles clear"), Command::Obstacles(None));
+    assert_eq!(
+        parse("obstacles land.png"),
+        Command::Obstacles(Some(PathBuf::from("land.png")))
+    );
+    assert_eq!(parse("depth shoal"), Command::Depth(DepthMap::Shoal));
+    assert_eq!(
+        parse("depth floor.png"),
+        Command::Depth(DepthMap::File(PathBuf::from("floor.png")))
+    );
+    assert_eq!(parse("scene save "), Command::SaveScene(None));
+    assert_eq!(
+        parse("snapshot load run.snap"),
+        Command::LoadSnapshot(PathBuf::from("run.snap"))
+    );
+    assert_eq!(
+        parse("export 10 png,npy heights"),
+        Command::Export {
+            every: 10,
+            formats: vec![Format::Png, Format::Npy],
+            dir: Some(PathBuf::from("heights")),
+        }
+    );
+    assert_eq!(parse("export stop"), Command::StopExport);
+    assert_eq!(parse("help"), Command::Help);
+}
+
+#[test]
+fn mistakes_say_what_went_wrong() {
+    let error = |line: &str| Command::parse(line).unwrap_err();
+    assert!(error("place").starts_with("Usage: place"));
+    assert!(error("snapshot run.snap").starts_with("Usage: snapshot"));
+    assert!(error("export 0 txt").contains("interval"));
+    assert!(error("export 10 gif").contains("gif"));
+    assert_eq!(error("jump 2"), "Unknown command jump; try \"help\".");
+    assert!(Command::parse("   ").is_err());
+}
@@ -0,0 +1,67 @@
+use wave_simulator::wave::config::Config;
+
+#[test]
+fn missing_settings_keep_their_defaults() {
+    let config = Config::from_toml("fps = 30.0\nvsync = false\n").unwrap();
+    assert_eq!(config.fps, 30.0);
+    assert!(!config.vsync);
+    assert_eq!(config.window_width, Config::default().window_width);
+    assert_eq!(Config::from_toml("").unwrap(), Config::default());
+}
+
+#[test]
+fn configs_survive_a_round_trip() {
+    let config = Config {
+        fov: 90.0,
+        frames_per_update: 2,
+        wireframe: true,
+        ..Config::default()
+    };
+    assert_eq!(
+        Config::from_toml(&config.to_toml().unwrap()).unwrap(),
+        config
+    );
+}
+
+#[test]
+fn invalid_configs_are_rejected() {
+    assert!(Config::from_toml("fps = 0.0\n").is_err());
+    assert!(Config::from_toml("fov = 180.0\n").is_err());
+    assert!(Config::from_toml("near_plane = 10.0\nfar_plane = 5.0\n").is_err());
+    assert!(Config::from_toml("frames_per_update = 0\n").is_err());
+    assert!(Config::from_toml("raycast_steps = 0\n").is_err());
+    assert!(Config::from_toml("camera_speed = -1.0\n").is_err());
+    // typos aren't silently ignored
+    let e = Config::from_toml("fsp = 30.0\n").unwrap_err();
+    assert!(e.contains("fsp"), "{}", e);
+}
+
+#[test]
+fn settings_are_overridden_one_by_one() {
+    let mut config = Config::default();
+    config.set("fps=144").unwrap();
+    config.set("vsync = false").unwrap();
+    config.set("camera_speed=0.5").unwrap();
+    config.set("window_width=800").unwrap();
+    assert_eq!(config.fps, 144.0);
+    assert!(!config.vsync);
+    assert_eq!(config.camera_speed, 0.5);
+    assert_eq!(config.window_width, 800);
+
+    let e = config.set("speed=1").unwrap_err();
+    assert!(e.contains("Unknown setting speed"), "{}", e);
+    assert!(config.set("fps").is_err());
+    assert!(config.set("vsync=maybe").is_err());
+    assert!(config.set("window_width=-3").is_err());
+    // a failed override leaves the config as it was
+    assert_eq!(config.window_width, 800);
+}
+
+#[test]
+fn overrides_are_validated_afterwards() {
+    let mut config = Config::default();
+    config.set("far_plane=0.05").unwrap();
+    assert!(config.validate().is_err());
+    config.set("near_plane=0.01").unwrap();
+    assert!(config.validate().is_ok());
+}
@@ -0,0 +1,78 @@
+use wave_simulator::wave::console::{Console, CELL_HEIGHT, CELL_WIDTH};
+
+fn type_line(console: &mut Console, line: &str) -> Option<String> {
+    line.chars().for_each(|c| console.type_char(c));
+    console.submit()
+}
+
+#[test]
+fn typing_edits_the_input() {
+    let mut console = Console::default();
+    console.type_char('x');
+    assert_eq!(console.input, "", "nothing is typed while it's closed");
+
+    // the `g` that opened it comes after, and is dropped
+    console.open("wave ", true);
+    "g2\u{8}1\r".chars().for_each(|c| console.type_char(c));
+    assert_eq!(console.input, "wave 21");
+    console.backspace();
+    assert_eq!(console.input, "wave 2");
+    assert_eq!(console.submit(), Some("wave 2".to_string()));
+    assert_eq!(console.input, "");
+    assert_eq!(console.log.last().unwrap().text, "> wave 2");
+    assert_eq!(console.submit(), None);
+}
+
+#[test]
+fn history_goes_back_and_forth() {
+    let mut console = Console::default();
+    console.open("", false);
+    type_line(&mut console, "place 0");
+    type_line(&mut console, "place 1");
+    type_line(&mut console, "place 1");
+    type_line(&mut console, "help");
+    assert_eq!(console.history, vec!["place 0", "place 1", "help"]);
+
+    console.previous();
+    assert_eq!(console.input, "help");
+    console.previous();
+    console.previous();
+    console.previous();
+    assert_eq!(console.input, "place 0");
+    console.next();
+    assert_eq!(console.input, "place 1");
+    console.next();
+    console.next();
+    assert_eq!(console.input, "");
+}
+
+#[test]
+fn the_log_keeps_the_last_lines() {
+    let mut console = Console::default();
+    console.print("one\ntwo");
+    console.error("three");
+    assert_eq!(console.log.len(), 3);
+    assert!(console.log[2].error && !console.log[1].error);
+    (0..500).for_each(|i| console.print(&i.to_string()));
+    assert_eq!(console.log.len(), 200);
+    assert_eq!(console.log.last().unwrap().text, "499");
+}
+
+#[test]
+fn rendering_draws_the_last_lines() {
+    let mut console = Console::default();
+    console.open("", false);
+    console.error("|");
+    let (columns, rows) = (20, 3);
+    let pixels = console.render(columns, rows);
+    let width = columns * CELL_WIDTH;
+    assert_eq!(pixels.len(), width * rows * CELL_HEIGHT * 4);
+    let at = |x: usize, y: usize| &pixels[(y * width + x) * 4..(y * width + x) * 4 + 4];
+
+    // an empty line above, then the error, then the input
+    assert_eq!(at(2, 4), [0, 0, 0, 160]);
+    assert_eq!(at(2, CELL_HEIGHT + 4), [255, 96, 96, 255]);
+    // the `>` of the input's prompt, in white
+    assert_eq!(at(2, 2 * CELL_HEIGHT + 2), [255, 255, 255, 255]);
+    assert_eq!(at(width - 1, 2 * CELL_HEIGHT + 4), [0, 0, 0, 160]);
+}
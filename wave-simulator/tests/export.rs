@@ -0,0 +1,136 @@
+use std::convert::TryInto;
+use wave_simulator::wave::export::{
+    height_range, heights, write_npy, write_png16, Exporter, Format,
+};
+use wave_simulator::wave::sim::{Simulation, Solver, Wave, WaveKind, WaveRegistry, UP};
+
+fn running() -> (Simulation, WaveRegistry) {
+    let mut waves = WaveRegistry::new();
+    waves.set(
+        0,
+        Wave {
+            directions: UP,
+            wavelength: 6,
+            kind: WaveKind::Radial,
+            amplitude_factor: 1.5,
+            ..Wave::default()
+        },
+    );
+    let mut sim = Simulation::new(12, 8, 1);
+    sim.place(6, 4, 0, &waves);
+    (0..4).for_each(|_| sim.step(&waves));
+    (sim, waves)
+}
+
+fn read_npy(bytes: &[u8]) -> (String, Vec<f32>) {
+    assert_eq!(&bytes[..8], b"\x93NUMPY\x01\x00");
+    let len = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+    assert_eq!((10 + len) % 64, 0);
+    let header = String::from_utf8(bytes[10..10 + len].to_vec()).unwrap();
+    let data = bytes[10 + len..]
+        .chunks(4)
+        .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+        .collect();
+    (header, data)
+}
+
+fn read_png16(bytes: &[u8]) -> (u32, u32, Vec<u16>) {
+    let mut decoder = png::Decoder::new(bytes);
+    // the default transformations strip 16-bit samples down to 8
+    decoder.set_transformations(png::Transformations::IDENTITY);
+    let (info, mut reader) = decoder.read_info().unwrap();
+    assert_eq!(info.bit_depth, png::BitDepth::Sixteen);
+    assert_eq!(info.color_type, png::ColorType::Grayscale);
+    let mut img = vec![0; info.buffer_size()];
+    reader.next_frame(&mut img).unwrap();
+    let pixels = img
+        .chunks(2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+        .collect();
+    (info.width, info.height, pixels)
+}
+
+#[test]
+fn heights_go_row_by_row() {
+    let (sim, waves) = running();
+    let all = heights(&sim, &waves);
+    assert_eq!(all.len(), 12 * 8);
+    assert!(all.iter().any(|h| *h != 0.0));
+    assert_eq!(all[3 * 12 + 7], sim.height_at(7, 3, &waves));
+}
+
+#[test]
+fn npy_files_hold_the_exact_heights() {
+    let (sim, waves) = running();
+    let all = heights(&sim, &waves);
+    let mut bytes = vec![];
+    write_npy(&mut bytes, 12, 8, &all).unwrap();
+    let (header, data) = read_npy(&bytes);
+    assert!(header.contains("'descr': '<f4'"));
+    assert!(header.contains("'fortran_order': False"));
+    assert!(header.contains("'shape': (8, 12)"));
+    assert!(header.ends_with('\n'));
+    let bits = |v: &[f32]| v.iter().map(|h| h.to_bits()).collect::<Vec<u32>>();
+    assert_eq!(bits(&data), bits(&all));
+}
+
+#[test]
+fn pngs_spread_the_range_over_16_bits() {
+    let heights = [-2.0, -1.0, 0.0, 1.0, 2.0, 5.0];
+    let mut bytes = vec![];
+    write_png16(&mut bytes, 3, 2, &heights, 2.0).unwrap();
+    let (width, height, pixels) = read_png16(&bytes);
+    assert_eq!((width, height), (3, 2));
+    assert_eq!(pixels, vec![0, 16384, 32768, 49151, 65535, 65535]);
+}
+
+#[test]
+fn the_default_range_covers_every_wave() {
+    let (_, mut waves) = running();
+    assert_eq!(height_range(&waves), 1.5);
+    waves.set(
+        1,
+        Wave {
+            amplitude_factor: -0.5,
+            ..*waves.get(0).unwrap()
+        },
+    );
+    assert_eq!(height_range(&waves), 2.0);
+    assert_eq!(height_range(&WaveRegistry::new()), 1.0);
+}
+
+#[test]
+fn formats_parse_from_a_list() {
+    assert_eq!(
+        Format::parse_list("png,npy").unwrap(),
+        vec![Format::Png, Format::Npy]
+    );
+    assert!(Format::parse_list("png,jpg").is_err());
+}
+
+#[test]
+fn exporters_write_a_file_per_format() {
+    let (sim, waves) = running();
+    let dir = std::env::temp_dir().join(format!("wave-simulator-export-{}", std::process::id()));
+    let exporter = Exporter {
+        dir: dir.clone(),
+        formats: vec![Format::Txt, Format::Png, Format::Npy],
+        range: None,
+    };
+    let paths = exporter.export(40, &sim, &waves).unwrap();
+    assert_eq!(
+        paths,
+        vec![
+            dir.join("tick_000040.txt"),
+            dir.join("tick_000040.png"),
+            dir.join("tick_000040.npy"),
+        ]
+    );
+    let txt = std::fs::read_to_string(&paths[0]).unwrap();
+    assert_eq!(txt.lines().count(), 8);
+    let (width, height, _) = read_png16(&std::fs::read(&paths[1]).unwrap());
+    assert_eq!((width, height), (12, 8));
+    let (_, data) = read_npy(&std::fs::read(&paths[2]).unwrap());
+    assert_eq!(data, heights(&sim, &waves));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
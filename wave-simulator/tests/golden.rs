@@ -0,0 +1,133 @@
+// Scenes rendered at fixed ticks, against the images in tests/golden. A failing one writes what
+// was rendered and where it differs next to the test binaries, in golden/; after a change that
+// is meant to look different, run with UPDATE_GOLDEN=1 to write the images over.
+
+use std::path::{Path, PathBuf};
+use wave_simulator::wave::camera::Camera;
+use wave_simulator::wave::render::diff::{compare, perceived_difference, Tolerance};
+use wave_simulator::wave::render::software::SoftwareRenderer;
+use wave_simulator::wave::render::Image;
+use wave_simulator::wave::scene::Scene;
+
+const SIZE: (u32, u32) = (320, 180);
+
+fn golden(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{}.png", name))
+}
+
+// the scene after `ticks` updates, from its own camera unless one is given
+fn render(scene: &str, ticks: u64, camera: Option<Camera>) -> Image {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("scenes")
+        .join(scene);
+    let scene = Scene::load(&path).unwrap();
+    let waves = scene.get_waves();
+    let mut sim = scene.instantiate();
+    (0..ticks).for_each(|_| sim.step(&waves));
+    let camera = camera.or(scene.camera);
+    SoftwareRenderer::render(sim.as_ref(), &waves, &scene.grid, camera, SIZE)
+}
+
+fn check(name: &str, actual: &Image) {
+    let path = golden(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        actual.save(&path).unwrap();
+        return;
+    }
+    let expected = Image::load(&path)
+        .unwrap_or_else(|e| panic!("{}; run with UPDATE_GOLDEN=1 to make it.", e));
+    let tolerance = Tolerance::default();
+    let difference = compare(&expected, actual, &tolerance).unwrap();
+    if difference.is_within(&tolerance) {
+        return;
+    }
+    let out = Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden");
+    std::fs::create_dir_all(&out).unwrap();
+    let (rendered, diff) = (
+        out.join(format!("{}.png", name)),
+        out.join(format!("{}.diff.png", name)),
+    );
+    actual.save(&rendered).unwrap();
+    difference.image.save(&diff).unwrap();
+    panic!(
+        "{} of {} pixels of {} changed; see {} and {}.",
+        difference.changed,
+        difference.total,
+        name,
+        rendered.display(),
+        diff.display()
+    );
+}
+
+#[test]
+fn example() {
+    check("example_40", &render("example.toml", 40, None));
+}
+
+#[test]
+fn harbour() {
+    check("harbour_80", &render("harbour.toml", 80, None));
+}
+
+#[test]
+fn shoal_from_the_water() {
+    // low over the water, with most of it behind the near plane or past the edges
+    let camera = Camera {
+        y: 6.0,
+        z: 20.0,
+        pitch: 0.3,
+        ..Camera::default()
+    };
+    check("shoal_60_low", &render("shoal.toml", 60, Some(camera)));
+}
+
+#[test]
+fn the_same_image_has_no_difference() {
+    let image = render("example.toml", 10, None);
+    let difference = compare(&image, &image, &Tolerance::default()).unwrap();
+    assert_eq!(difference.changed, 0);
+    assert_eq!(difference.total, 320 * 180);
+    assert!(difference.is_within(&Tolerance {
+        colour: 0.0,
+        pixels: 0.0
+    }));
+}
+
+#[test]
+fn differences_are_measured_by_how_they_look() {
+    let (black, white) = ([0, 0, 0, 255], [255, 255, 255, 255]);
+    let most = perceived_difference(black, white);
+    assert!(most > 0.9 && most <= 1.0);
+    assert_eq!(perceived_difference(white, white), 0.0);
+    // a step in brightness shows more than the same step in blue
+    let (dim, blue) = ([250, 250, 250, 255], [255, 255, 250, 255]);
+    assert!(perceived_difference(white, dim) > perceived_difference(white, blue));
+    // a transparent pixel is as good as white
+    assert_eq!(perceived_difference([0, 0, 0, 0], white), 0.0);
+}
+
+#[test]
+fn changed_pixels_are_counted_and_marked() {
+    let expected = Image::new(4, 2, [0, 0, 0, 255]);
+    let mut actual = expected.clone();
+    actual.set(1, 1, [255, 255, 255, 255]);
+    actual.set(2, 0, [2, 2, 2, 255]);
+    let difference = compare(&expected, &actual, &Tolerance::default()).unwrap();
+    assert_eq!(difference.changed, 1);
+    assert_eq!(difference.image.get(1, 1), [255, 0, 0, 255]);
+    assert_ne!(difference.image.get(2, 0), [255, 0, 0, 255]);
+    assert!(!difference.is_within(&Tolerance::default()));
+    assert!(difference.is_within(&Tolerance {
+        colour: 0.1,
+        pixels: 0.125
+    }));
+
+    assert!(compare(
+        &expected,
+        &Image::new(2, 4, [0, 0, 0, 255]),
+        &Tolerance::default()
+    )
+    .is_err());
+}
@@ -0,0 +1,99 @@
+use wave_simulator::wave::grid::Grid;
+use wave_simulator::wave::mesh::{surface_heights, Mesh};
+use wave_simulator::wave::scene::Scene;
+use wave_simulator::wave::sim::WaveRegistry;
+
+#[test]
+fn grids_must_have_a_size() {
+    assert!(Grid::default().validate().is_ok());
+    assert!(Grid::new(37, 2).validate().is_ok());
+    assert!(Grid::new(1, 10).validate().is_err());
+    assert!(Grid::new(10, 5000).validate().is_err());
+    let flat = Grid {
+        cell_size: 0.0,
+        ..Grid::default()
+    };
+    assert!(flat.validate().is_err());
+}
+
+#[test]
+fn vertices_are_centred_on_the_origin() {
+    let grid = Grid {
+        width: 8,
+        height: 5,
+        cell_size: 0.5,
+    };
+    assert_eq!(grid.vertex_position(0, 0), (-2.0, -1.25));
+    assert_eq!(grid.vertex_position(7, 4), (1.5, 0.75));
+    assert_eq!(grid.get_len(), 40);
+}
+
+#[test]
+fn points_find_the_tile_under_them() {
+    let grid = Grid {
+        width: 13,
+        height: 7,
+        cell_size: 2.0,
+    };
+    // vertex (0, 0) is at (-13, -7) and shows the last tile row
+    assert_eq!(grid.tile_at(-13.0, -7.0), Some((0, 6)));
+    assert_eq!(grid.tile_at(-10.5, -6.5), Some((1, 6)));
+    assert_eq!(grid.tile_at(12.9, 6.9), Some((12, 0)));
+    assert_eq!(grid.tile_at(0.5, 0.5), Some((6, 3)));
+    assert_eq!(grid.tile_at(13.0, 0.0), None);
+    assert_eq!(grid.tile_at(0.0, -7.1), None);
+    for (x, z) in [(0, 0), (5, 2), (12, 6)].iter() {
+        let (wx, wz) = grid.vertex_position(*x, *z);
+        assert_eq!(grid.tile_at(wx, wz), Some(grid.tile_of_vertex(*x, *z)));
+    }
+}
+
+#[test]
+fn scenes_set_the_grid() {
+    let scene = Scene::from_toml("[grid]\nwidth = 64\nheight = 30\ncell_size = 0.25\n").unwrap();
+    assert_eq!(
+        scene.grid,
+        Grid {
+            width: 64,
+            height: 30,
+            cell_size: 0.25
+        }
+    );
+    assert_eq!(Scene::from_toml(&scene.to_toml().unwrap()).unwrap(), scene);
+    assert_eq!(scene.instantiate().get_size(), (64, 30));
+
+    // cell_size can be left out
+    let scene = Scene::from_toml("[grid]\nwidth = 20\nheight = 12\n").unwrap();
+    assert_eq!(scene.grid.cell_size, 1.0);
+    assert_eq!(Scene::from_toml("").unwrap().grid, Grid::default());
+
+    assert!(Scene::from_toml("[grid]\nwidth = 0\nheight = 12\n").is_err());
+}
+
+#[test]
+fn sources_must_be_on_the_scene_grid() {
+    let waves = "[[waves]]\nslot = 0\namplitude = 1.0\nwavelength = 5\n\n";
+    let inside = format!(
+        "[grid]\nwidth = 30\nheight = 20\n\n{}[[sources]]\nx = 29\ny = 19\nslot = 0\n",
+        waves
+    );
+    assert!(Scene::from_toml(&inside).is_ok());
+    let outside = format!(
+        "[grid]\nwidth = 30\nheight = 20\n\n{}[[sources]]\nx = 10\ny = 20\nslot = 0\n",
+        waves
+    );
+    let e = Scene::from_toml(&outside).unwrap_err();
+    assert!(e.contains("30x20"), "{}", e);
+}
+
+#[test]
+fn meshes_use_the_cell_size() {
+    let scene = Scene::from_toml("[grid]\nwidth = 6\nheight = 3\ncell_size = 2.0\n").unwrap();
+    let sim = scene.instantiate();
+    let waves = WaveRegistry::new();
+    let mesh = Mesh::water(sim.as_ref(), &waves, scene.grid.cell_size);
+    assert_eq!(surface_heights(sim.as_ref(), &waves).len(), 18);
+    assert_eq!(mesh.positions[0], [-6.0, 0.0, -3.0]);
+    assert_eq!(mesh.positions[17], [4.0, 0.0, 1.0]);
+    assert_eq!(mesh.indices.len(), 6 * 5 * 2);
+}
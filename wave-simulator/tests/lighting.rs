@@ -0,0 +1,123 @@
+use cgmath::Vector3;
+use wave_simulator::wave::camera::Camera;
+use wave_simulator::wave::config::Config;
+use wave_simulator::wave::grid::Grid;
+use wave_simulator::wave::render::software::SoftwareRenderer;
+use wave_simulator::wave::render::{Light, Renderer, Shading, Surface};
+
+const BLUE: [f32; 4] = [0.1, 0.3, 0.6, 1.0];
+
+fn light(direction: Vector3<f32>) -> Light {
+    Light {
+        direction,
+        ambient: 0.2,
+        diffuse: 0.8,
+        specular: 0.0,
+        fresnel: 0.0,
+        ..Light::unlit()
+    }
+}
+
+fn brightness(colour: [f32; 4]) -> f32 {
+    colour[0] + colour[1] + colour[2]
+}
+
+#[test]
+fn unlit_colours_are_kept() {
+    let shaded = Light::unlit().shade(BLUE, Vector3::unit_x(), Vector3::unit_y());
+    assert_eq!(shaded, BLUE);
+}
+
+#[test]
+fn facing_the_light_is_brighter() {
+    let light = light(Vector3::new(1.0, 1.0, 0.0));
+    let eye = Vector3::unit_y();
+    let facing = light.shade(BLUE, Vector3::new(1.0, 1.0, 0.0), eye);
+    let flat = light.shade(BLUE, Vector3::unit_y(), eye);
+    let away = light.shade(BLUE, Vector3::new(-1.0, 0.0, 0.0), eye);
+    assert!(brightness(facing) > brightness(flat));
+    assert!(brightness(flat) > brightness(away));
+    // turned away from it, only the ambient light is left
+    assert!((away[2] - BLUE[2] * 0.2).abs() < 1e-6);
+    assert!((facing[2] - BLUE[2]).abs() < 1e-6);
+    assert_eq!(facing[3], 1.0);
+}
+
+#[test]
+fn the_light_glints_off_towards_the_eye() {
+    let light = Light {
+        specular: 1.0,
+        shininess: 32.0,
+        ..light(Vector3::new(1.0, 1.0, 0.0))
+    };
+    // the light comes in on one side of the normal, and the eye is on the other
+    let mirrored = light.shade(BLUE, Vector3::unit_y(), Vector3::new(-1.0, 1.0, 0.0));
+    let aside = light.shade(BLUE, Vector3::unit_y(), Vector3::new(0.0, 1.0, 1.0));
+    assert!(brightness(mirrored) > brightness(aside) + 2.0);
+    // nothing glints from behind
+    let behind = light.shade(BLUE, Vector3::unit_y(), Vector3::new(-1.0, -1.0, 0.0));
+    assert!(brightness(behind) < brightness(mirrored));
+}
+
+#[test]
+fn water_seen_edge_on_takes_the_light_colour() {
+    let light = Light {
+        colour: [1.0, 0.5, 0.0],
+        fresnel: 1.0,
+        ..Light::unlit()
+    };
+    let straight = light.shade(BLUE, Vector3::unit_y(), Vector3::unit_y());
+    let grazing = light.shade(BLUE, Vector3::unit_y(), Vector3::unit_x());
+    assert_eq!(straight, [0.1, 0.15, 0.0, 1.0]);
+    assert_eq!(grazing, [1.0, 0.5, 0.0, 1.0]);
+}
+
+#[test]
+fn the_config_light_is_validated() {
+    let light = Config::default().light();
+    assert_eq!(light.ambient, Config::default().ambient);
+    assert!(Config::from_toml("light_direction = [0.0, 0.0, 0.0]\n").is_err());
+    assert!(Config::from_toml("ambient = -0.5\n").is_err());
+    assert!(Config::from_toml("shininess = 0.0\n").is_err());
+    assert!(Config::from_toml("fresnel = 2.0\n").is_err());
+
+    let mut config = Config::default();
+    config.set("light_direction = [0.0, 1.0, 0.0]").unwrap();
+    assert_eq!(config.light().direction, Vector3::unit_y());
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn slopes_facing_the_light_are_drawn_brighter() {
+    // water rising along x, with the light on one side of it and then the other
+    let grid = Grid::new(20, 20);
+    let mut surface = Surface::flat(grid);
+    surface.heights = (0..grid.get_len())
+        .map(|i| (i as u32 % grid.width) as f32 * 0.5)
+        .collect();
+    let normal = surface.normals()[grid.get_len() / 2];
+    assert!(normal[0] < 0.0 && normal[1] > 0.0 && normal[2] == 0.0);
+
+    let camera = Camera {
+        y: 40.0,
+        pitch: std::f32::consts::FRAC_PI_2,
+        ..Camera::default()
+    };
+    let pixel = |light: Light| {
+        let mut renderer = SoftwareRenderer::new(32, 32);
+        renderer.upload_surface(&surface);
+        renderer.clear([0.0, 0.0, 0.0, 1.0]);
+        renderer.draw_water(
+            Config::default().projection_matrix(1.0),
+            camera.get_matrix(),
+            &Shading::Water(light),
+        );
+        let [r, g, b, _] = renderer.frame.get(16, 16);
+        r as u32 + g as u32 + b as u32
+    };
+    let towards = pixel(light(Vector3::new(-1.0, 1.0, 0.0)));
+    let away = pixel(light(Vector3::new(1.0, 1.0, 0.0)));
+    let unlit = pixel(Light::unlit());
+    assert!(towards > away, "{} {}", towards, away);
+    assert!(unlit > towards);
+}
@@ -0,0 +1,134 @@
+use std::convert::TryInto;
+use wave_simulator::wave::constants::LAND_HEIGHT;
+use wave_simulator::wave::grid::Grid;
+use wave_simulator::wave::mesh::{surface_heights, vertex_normals, Mesh};
+use wave_simulator::wave::obstacles::ObstacleMask;
+use wave_simulator::wave::sim::{Simulation, Solver, Wave, WaveKind, WaveRegistry};
+
+fn running() -> (Simulation, WaveRegistry) {
+    let mut waves = WaveRegistry::new();
+    waves.set(
+        0,
+        Wave {
+            wavelength: 6,
+            kind: WaveKind::Radial,
+            amplitude_factor: 1.0,
+            ..Wave::default()
+        },
+    );
+    let mut sim = Simulation::new(10, 10, 1);
+    let mut obstacles = ObstacleMask::open(10, 10);
+    obstacles.set_blocked(1, 1, true);
+    sim.set_obstacles(obstacles);
+    sim.place(5, 5, 0, &waves);
+    (0..3).for_each(|_| sim.step(&waves));
+    (sim, waves)
+}
+
+#[test]
+fn vertices_follow_the_vertex_shader() {
+    let (sim, waves) = running();
+    let heights = surface_heights(&sim, &waves);
+    let at = |x: u32, z: u32| heights[(z * 10 + x) as usize];
+    // row z shows tile row 9 - z
+    assert_eq!(at(3, 5), sim.height_at(3, 4, &waves));
+    assert_eq!(at(7, 0), sim.height_at(7, 9, &waves));
+    assert_eq!(at(1, 8), LAND_HEIGHT);
+    assert!(heights.iter().any(|h| *h != 0.0 && *h != LAND_HEIGHT));
+}
+
+#[test]
+fn meshes_match_the_water_grid() {
+    let (sim, waves) = running();
+    let mesh = Mesh::water(&sim, &waves, 1.0);
+    assert_eq!(mesh.positions.len(), 100);
+    assert_eq!(mesh.indices.len(), 6 * 9 * 9);
+    assert_eq!(mesh.positions[0], [-5.0, 0.0, -5.0]);
+    assert_eq!(mesh.positions[99][0], 4.0);
+    assert_eq!(mesh.positions[99][2], 4.0);
+    // the same texture coordinates as water_vert
+    assert_eq!(mesh.uvs[0], [0.25, 0.25]);
+    assert_eq!(mesh.uvs[99], [0.7, 0.7]);
+    assert_eq!(&mesh.indices[..6], &[0, 10, 1, 1, 10, 11]);
+}
+
+#[test]
+fn normals_point_up_and_lean_away_from_slopes() {
+    let flat = vertex_normals(3, 3, 1.0, &[0.0; 9]);
+    assert!(flat.iter().all(|n| *n == [0.0, 1.0, 0.0]));
+
+    // rising along x
+    let heights = [0.0, 1.0, 2.0, 0.0, 1.0, 2.0, 0.0, 1.0, 2.0];
+    let normals = vertex_normals(3, 3, 1.0, &heights);
+    let n = normals[4];
+    assert!(n[0] < 0.0 && n[1] > 0.0 && n[2] == 0.0);
+    assert!((n[0] * n[0] + n[1] * n[1] + n[2] * n[2] - 1.0).abs() < 1e-6);
+    assert_eq!(normals[3], normals[4]);
+}
+
+#[test]
+fn obj_files_count_from_one() {
+    let mesh = Mesh::from_heights(&Grid::new(2, 2), &[0.0; 4]);
+    let mut out = vec![];
+    mesh.write_obj(&mut out).unwrap();
+    let obj = String::from_utf8(out).unwrap();
+    assert_eq!(obj.lines().filter(|l| l.starts_with("v ")).count(), 4);
+    assert_eq!(obj.lines().filter(|l| l.starts_with("vt ")).count(), 4);
+    assert_eq!(obj.lines().filter(|l| l.starts_with("vn ")).count(), 4);
+    assert!(obj.contains("f 1/1/1 3/3/3 2/2/2\n"));
+    assert!(obj.contains("f 2/2/2 3/3/3 4/4/4\n"));
+}
+
+#[test]
+fn ply_files_have_every_vertex_and_face() {
+    let mesh = Mesh::from_heights(&Grid::new(3, 2), &[0.0, 0.5, 0.0, 0.0, 0.5, 0.0]);
+    let mut out = vec![];
+    mesh.write_ply(&mut out).unwrap();
+    let end = b"end_header\n";
+    let at = out.windows(end.len()).position(|w| w == end).unwrap() + end.len();
+    let header = String::from_utf8(out[..at].to_vec()).unwrap();
+    assert!(header.contains("element vertex 6\n"));
+    assert!(header.contains("element face 4\n"));
+    // 8 floats per vertex, then a count and 3 indices per face
+    assert_eq!(out.len() - at, 6 * 32 + 4 * 13);
+    let y = f32::from_le_bytes(out[at + 32 + 4..at + 32 + 8].try_into().unwrap());
+    assert_eq!(y, 0.5);
+}
+
+#[test]
+fn glb_files_are_well_formed() {
+    let (sim, waves) = running();
+    let mesh = Mesh::water(&sim, &waves, 1.0);
+    let mut out = vec![];
+    mesh.write_glb(&mut out).unwrap();
+    let word = |at: usize| u32::from_le_bytes(out[at..at + 4].try_into().unwrap()) as usize;
+    assert_eq!(&out[..4], b"glTF");
+    assert_eq!(word(4), 2);
+    assert_eq!(word(8), out.len());
+    let json_len = word(12);
+    assert_eq!(&out[16..20], b"JSON");
+    assert_eq!(json_len % 4, 0);
+    let json = String::from_utf8(out[20..20 + json_len].to_vec()).unwrap();
+    assert!(json.contains("\"POSITION\":0,\"NORMAL\":1,\"TEXCOORD_0\":2"));
+    assert!(json.contains("\"count\":486,\"type\":\"SCALAR\""));
+    let bin = 20 + json_len;
+    assert_eq!(&out[bin + 4..bin + 8], b"BIN\0");
+    assert_eq!(word(bin), 100 * (12 + 12 + 8) + 486 * 4);
+    assert_eq!(bin + 8 + word(bin), out.len());
+}
+
+#[test]
+fn meshes_are_saved_by_extension() {
+    let (sim, waves) = running();
+    let mesh = Mesh::water(&sim, &waves, 1.0);
+    let dir = std::env::temp_dir();
+    let id = std::process::id();
+    for extension in ["obj", "ply", "glb"].iter() {
+        let path = dir.join(format!("wave-simulator-mesh-{}.{}", id, extension));
+        mesh.save(&path).unwrap();
+        assert!(std::fs::metadata(&path).unwrap().len() > 0);
+        std::fs::remove_file(&path).unwrap();
+    }
+    let e = mesh.save(dir.join("water.stl")).unwrap_err();
+    assert!(e.contains(".obj, .ply or .glb"), "{}", e);
+}
@@ -0,0 +1,171 @@
+use std::io::Cursor;
+use wave_simulator::wave::obstacles::ObstacleMask;
+use wave_simulator::wave::physics::WaveEquation;
+use wave_simulator::wave::scene::Scene;
+use wave_simulator::wave::sim::{
+    is_activated, Simulation, Solver, SolverMode, SolverSettings, Wave, WaveRegistry, DOWN,
+};
+
+fn one_wave(directions: u8) -> WaveRegistry {
+    let mut waves = WaveRegistry::new();
+    waves.set(
+        0,
+        Wave {
+            directions,
+            wavelength: 20,
+            amplitude_factor: 1.0,
+            ..Wave::default()
+        },
+    );
+    waves
+}
+
+fn encode(width: u32, height: u32, colour: png::ColorType, pixels: &[u8]) -> Vec<u8> {
+    encode_with_depth(width, height, colour, png::BitDepth::Eight, pixels)
+}
+
+fn encode_with_depth(
+    width: u32,
+    height: u32,
+    colour: png::ColorType,
+    depth: png::BitDepth,
+    pixels: &[u8],
+) -> Vec<u8> {
+    let mut bytes = vec![];
+    {
+        let mut encoder = png::Encoder::new(&mut bytes, width, height);
+        encoder.set_color(colour);
+        encoder.set_depth(depth);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(pixels).unwrap();
+    }
+    bytes
+}
+
+// a wall along y = 2, with a gap at x = 3
+fn wall() -> ObstacleMask {
+    let mut mask = ObstacleMask::open(6, 6);
+    (0..6)
+        .filter(|x| *x != 3)
+        .for_each(|x| mask.set_blocked(x, 2, true));
+    mask
+}
+
+#[test]
+fn dark_pixels_are_land() {
+    let gray = encode(2, 2, png::ColorType::Grayscale, &[0, 255, 127, 128]);
+    let mask = ObstacleMask::from_png(Cursor::new(gray), 2, 2).unwrap();
+    assert_eq!(mask.tiles, vec![1, 0, 1, 0]);
+
+    let rgba = [
+        0, 0, 0, 255, 0, 0, 0, 0, 255, 255, 255, 255, 10, 20, 30, 255,
+    ];
+    let rgba = encode(2, 2, png::ColorType::RGBA, &rgba);
+    let mask = ObstacleMask::from_png(Cursor::new(rgba), 2, 2).unwrap();
+    assert_eq!(mask.tiles, vec![1, 0, 0, 1]);
+
+    // 16-bit samples are big-endian, and only their high bytes count
+    let wide = [0x00, 0x00, 0xff, 0xff, 0x7f, 0xff, 0x80, 0x00];
+    let wide = encode_with_depth(
+        2,
+        2,
+        png::ColorType::Grayscale,
+        png::BitDepth::Sixteen,
+        &wide,
+    );
+    let mask = ObstacleMask::from_png(Cursor::new(wide), 2, 2).unwrap();
+    assert_eq!(mask.tiles, vec![1, 0, 1, 0]);
+    // and so do 1-bit ones, which are 0 or 255
+    let narrow = encode_with_depth(
+        2,
+        2,
+        png::ColorType::Grayscale,
+        png::BitDepth::One,
+        &[0b0100_0000, 0b1000_0000],
+    );
+    let mask = ObstacleMask::from_png(Cursor::new(narrow), 2, 2).unwrap();
+    assert_eq!(mask.tiles, vec![1, 0, 0, 1]);
+}
+
+#[test]
+fn masks_must_fit_the_grid() {
+    let gray = encode(2, 2, png::ColorType::Grayscale, &[0; 4]);
+    assert!(ObstacleMask::from_png(Cursor::new(gray), 3, 2).is_err());
+    assert!(ObstacleMask::from_png(Cursor::new(vec![1, 2, 3]), 2, 2).is_err());
+}
+
+#[test]
+fn automaton_waves_go_through_gaps_only() {
+    let waves = one_wave(DOWN);
+    let mut sim = Simulation::new(6, 6, 1);
+    sim.set_obstacles(wall());
+    (0..6).for_each(|x| sim.place(x, 0, 0, &waves));
+    (0..4).for_each(|_| sim.step(&waves));
+
+    assert!(is_activated(sim.get(0, 3, 2).unwrap()));
+    assert!(is_activated(sim.get(0, 3, 4).unwrap()));
+    (0..6).filter(|x| *x != 3).for_each(|x| {
+        assert!(!is_activated(sim.get(0, x, 2).unwrap()));
+        assert!(!is_activated(sim.get(0, x, 4).unwrap()));
+    });
+}
+
+#[test]
+fn nothing_is_placed_on_land() {
+    let waves = one_wave(DOWN);
+    let mut sim = Simulation::new(6, 6, 1);
+    sim.place(0, 2, 0, &waves);
+    sim.set_obstacles(wall());
+    assert_eq!(sim.get(0, 0, 2), Some(0));
+    sim.place(1, 2, 0, &waves);
+    assert_eq!(sim.height_at(1, 2, &waves), 0.0);
+
+    let mut sim = WaveEquation::new(6, 6, SolverSettings::default());
+    sim.set_obstacles(wall());
+    sim.place(1, 2, 0, &waves);
+    assert!(sim.drivers.is_empty());
+}
+
+#[test]
+fn land_stays_flat_and_shelters_the_water_behind_it() {
+    let settings = SolverSettings {
+        mode: SolverMode::WaveEquation,
+        ..SolverSettings::default()
+    };
+    let mut mask = ObstacleMask::open(20, 20);
+    (0..20).for_each(|x| mask.set_blocked(x, 10, true));
+    let waves = one_wave(0);
+    let mut sim = WaveEquation::new(20, 20, settings);
+    sim.set_obstacles(mask.clone());
+    sim.place(10, 5, 0, &waves);
+    (0..40).for_each(|_| sim.step(&waves));
+
+    (0..20).for_each(|x| assert_eq!(sim.height_at(x, 10, &waves), 0.0));
+    assert!(sim.height_at(10, 5, &waves).abs() + sim.height_at(10, 3, &waves).abs() > 0.0);
+    (11..20).for_each(|y| assert_eq!(sim.height_at(10, y, &waves), 0.0));
+}
+
+#[test]
+fn scenes_load_their_obstacles() {
+    let scene = Scene::load("scenes/harbour.toml").unwrap();
+    let mask = scene.obstacle_mask.as_ref().unwrap();
+    assert!(mask.tiles.iter().any(|t| *t != 0));
+    let waves = scene.get_waves();
+    let sim = scene.instantiate();
+    let (width, height) = sim.get_size();
+    (0..height).for_each(|y| {
+        (0..width)
+            .filter(|x| mask.is_blocked(*x, y))
+            .for_each(|x| assert_eq!(sim.height_at(x, y, &waves), 0.0));
+    });
+
+    let mut on_land = scene.clone();
+    on_land.sources[0] = wave_simulator::wave::scene::Source {
+        x: 0,
+        y: 99,
+        slot: 0,
+    };
+    assert!(on_land
+        .load_obstacles(std::path::Path::new("scenes"))
+        .is_err());
+}
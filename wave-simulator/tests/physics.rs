@@ -0,0 +1,225 @@
+use wave_simulator::wave::obstacles::ObstacleMask;
+use wave_simulator::wave::physics::{drive, place_drivers, Driver, WaveEquation};
+use wave_simulator::wave::scene::Scene;
+use wave_simulator::wave::sim::{Solver, SolverMode, SolverSettings, Wave, WaveRegistry, UP};
+
+fn settings(damping: f32) -> SolverSettings {
+    SolverSettings {
+        mode: SolverMode::WaveEquation,
+        speed: 1.0,
+        damping,
+        timestep: 0.5,
+        ..SolverSettings::default()
+    }
+}
+
+fn one_wave(wavelength: u8, amplitude_factor: f32) -> WaveRegistry {
+    let mut waves = WaveRegistry::new();
+    waves.set(
+        0,
+        Wave {
+            wavelength,
+            amplitude_factor,
+            ..Wave::default()
+        },
+    );
+    waves
+}
+
+fn energy(sim: &WaveEquation) -> f32 {
+    sim.heights.iter().map(|h| h * h).sum()
+}
+
+#[test]
+fn drivers_follow_the_automaton_sine() {
+    let waves = one_wave(4, 2.0);
+    let mut drivers = vec![Driver {
+        x: 1,
+        y: 2,
+        slot: 0,
+        tick: 0,
+        start: 0,
+        from: (1, 2),
+        distance: 0.0,
+    }];
+    let heights = (0..5)
+        .map(|_| drive(&mut drivers, &waves).iter().map(|t| t.height).sum())
+        .collect::<Vec<f32>>();
+    let expected = [2.0f32.sqrt(), 2.0, 2.0f32.sqrt(), 0.0, 0.0];
+    heights
+        .iter()
+        .zip(expected.iter())
+        .for_each(|(h, e)| assert!((h - e).abs() < 1e-5, "{} != {}", h, e));
+    assert!(drivers.is_empty());
+}
+
+#[test]
+fn drivers_decay_and_expire() {
+    let mut waves = WaveRegistry::new();
+    waves.set(
+        0,
+        Wave {
+            wavelength: 4,
+            amplitude_factor: 2.0,
+            decay: 0.5,
+            lifetime: 3,
+            ..Wave::default()
+        },
+    );
+    let mut drivers = vec![Driver {
+        x: 1,
+        y: 2,
+        slot: 0,
+        tick: 0,
+        start: 0,
+        from: (1, 2),
+        distance: 0.0,
+    }];
+    let heights = (0..4)
+        .map(|_| drive(&mut drivers, &waves).iter().map(|t| t.height).sum())
+        .collect::<Vec<f32>>();
+    let expected = [
+        2.0f32.sqrt() * (-0.5f32).exp(),
+        2.0 * (-1.0f32).exp(),
+        0.0,
+        0.0,
+    ];
+    heights
+        .iter()
+        .zip(expected.iter())
+        .for_each(|(h, e)| assert!((h - e).abs() < 1e-5, "{} != {}", h, e));
+    assert!(drivers.is_empty());
+}
+
+fn beam(lifetime: u32, falloff: f32) -> WaveRegistry {
+    let mut waves = WaveRegistry::new();
+    waves.set(
+        0,
+        Wave {
+            directions: UP,
+            wavelength: 4,
+            amplitude_factor: 1.0,
+            falloff,
+            lifetime,
+            ..Wave::default()
+        },
+    );
+    waves
+}
+
+#[test]
+fn directional_waves_drive_their_directions() {
+    let waves = beam(0, 0.0);
+    let drivers = place_drivers((3, 6), 0, waves.get(0), &ObstacleMask::open(8, 10), 0.5);
+    // up moves to lower rows, and the wave gets a tile further every other update
+    assert_eq!(drivers.len(), 7);
+    assert!(drivers.iter().all(|driver| driver.x == 3));
+    assert!(drivers
+        .iter()
+        .all(|driver| driver.distance == (6 - driver.y) as f32
+            && driver.start == 2 * (6 - driver.y) as u16));
+
+    // and the water moves off that way
+    let mut sim = WaveEquation::new(21, 31, settings(0.0));
+    sim.place(10, 15, 0, &waves);
+    (0..20).for_each(|_| sim.step(&waves));
+    let rows = |rows: std::ops::Range<u32>| -> f32 {
+        rows.map(|y| sim.height_at(10, y, &waves).powi(2)).sum()
+    };
+    assert!(rows(0..15) > rows(16..31) * 4.0);
+}
+
+#[test]
+fn drivers_fall_off_with_distance() {
+    let waves = beam(0, 0.5);
+    let mut drivers = place_drivers((0, 4), 0, waves.get(0), &ObstacleMask::open(1, 5), 1.0);
+    let peaks = (0..12).fold(vec![0.0f32; 5], |mut peaks, _| {
+        drive(&mut drivers, &waves).iter().for_each(|tile| {
+            let peak = &mut peaks[tile.y as usize];
+            *peak = peak.max(tile.height);
+        });
+        peaks
+    });
+    // 4 tiles out is 1 + 0.5 * 4 times lower than the tile it was placed on
+    assert!((peaks[4] - 1.0).abs() < 1e-5);
+    assert!((peaks[0] - 1.0 / 3.0).abs() < 1e-5);
+}
+
+#[test]
+fn lifetimes_let_go_of_the_drivers_but_not_the_field() {
+    let waves = beam(3, 0.0);
+    let mut drivers = place_drivers((0, 9), 0, waves.get(0), &ObstacleMask::open(1, 10), 1.0);
+    let driven = (0..10)
+        .flat_map(|_| drive(&mut drivers, &waves))
+        .map(|tile| tile.y)
+        .collect::<Vec<u32>>();
+    // only the tiles the wave got to before it expired were driven
+    assert!(driven.iter().all(|y| *y >= 7));
+    assert!(drivers.is_empty());
+
+    let mut sim = WaveEquation::new(21, 21, settings(0.0));
+    sim.place(10, 10, 0, &waves);
+    (0..3).for_each(|_| sim.step(&waves));
+    assert!(sim.drivers.is_empty());
+    let expired = sim.heights.clone();
+    sim.step(&waves);
+    assert_ne!(sim.heights, expired);
+}
+
+#[test]
+fn ripples_spread_evenly() {
+    let waves = one_wave(6, 1.0);
+    let mut sim = WaveEquation::new(41, 41, settings(0.0));
+    sim.place(20, 20, 0, &waves);
+    (0..20).for_each(|_| sim.step(&waves));
+
+    let at = |x: u32, y: u32| sim.height_at(x, y, &waves);
+    assert!(at(25, 20).abs() > 1e-4);
+    assert!((at(25, 20) - at(15, 20)).abs() < 1e-5);
+    assert!((at(25, 20) - at(20, 25)).abs() < 1e-5);
+    assert!((at(20, 15) - at(20, 25)).abs() < 1e-5);
+}
+
+#[test]
+fn damping_removes_energy() {
+    let waves = one_wave(6, 1.0);
+    let mut free = WaveEquation::new(31, 31, settings(0.0));
+    let mut damped = WaveEquation::new(31, 31, settings(0.5));
+    free.place(15, 15, 0, &waves);
+    damped.place(15, 15, 0, &waves);
+    (0..30).for_each(|_| {
+        free.step(&waves);
+        damped.step(&waves);
+    });
+    assert!(energy(&damped) < energy(&free) * 0.5);
+    assert!(energy(&free).is_finite());
+}
+
+#[test]
+fn unstable_settings_are_rejected() {
+    let mut unstable = settings(0.0);
+    unstable.timestep = 1.0;
+    assert!(unstable.validate().is_err());
+    assert!(settings(0.0).validate().is_ok());
+
+    let src = "[solver]\nmode = \"wave-equation\"\nspeed = 2.0\ntimestep = 0.5\n";
+    assert!(Scene::from_toml(src).is_err());
+}
+
+#[test]
+fn scenes_pick_their_solver() {
+    let src = "[[waves]]\nslot = 0\namplitude = 1.0\nwavelength = 4\n\n\
+               [[sources]]\nx = 50\ny = 50\nslot = 0\n\n\
+               [solver]\nmode = \"wave-equation\"\n";
+    let scene = Scene::from_toml(src).unwrap();
+    assert_eq!(scene.solver.mode, SolverMode::WaveEquation);
+    assert_eq!(scene.solver.timestep, SolverSettings::default().timestep);
+
+    let waves = scene.get_waves();
+    let mut sim = scene.instantiate();
+    sim.step(&waves);
+    sim.step(&waves);
+    // the automaton's neighbour would still be flat after two updates
+    assert!(sim.height_at(50, 50, &waves) > 0.9);
+    assert!(sim.height_at(51, 50, &waves) > 0.0);
+}
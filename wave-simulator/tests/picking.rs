@@ -0,0 +1,159 @@
+use cgmath::Vector3;
+use wave_simulator::wave::camera::{to_clip, Camera};
+use wave_simulator::wave::command::Command;
+use wave_simulator::wave::config::Config;
+use wave_simulator::wave::grid::Grid;
+use wave_simulator::wave::raycaster::search;
+use wave_simulator::wave::recording::{Event, Recording};
+use wave_simulator::wave::scene::Scene;
+use wave_simulator::wave::sim::{
+    Solver, SolverMode, SolverSettings, Wave, WaveKind, WaveRegistry, UP,
+};
+
+fn waves(kind: WaveKind) -> WaveRegistry {
+    let mut waves = WaveRegistry::new();
+    waves.set(
+        0,
+        Wave {
+            directions: UP,
+            wavelength: 6,
+            kind,
+            amplitude_factor: 1.0,
+            ..Wave::default()
+        },
+    );
+    waves
+}
+
+fn is_flat(sim: &dyn Solver, waves: &WaveRegistry) -> bool {
+    let (width, height) = sim.get_size();
+    (0..height).all(|y| (0..width).all(|x| sim.height_at(x, y, waves) == 0.0))
+}
+
+#[test]
+fn the_cursor_maps_to_clip_space() {
+    assert_eq!(to_clip((640.0, 360.0), (1280, 720)), (0.0, 0.0));
+    assert_eq!(to_clip((0.0, 0.0), (1280, 720)), (-1.0, 1.0));
+    assert_eq!(to_clip((1280.0, 720.0), (1280, 720)), (1.0, -1.0));
+}
+
+#[test]
+fn rays_go_through_the_cursor() {
+    let projection = Config::default().projection_matrix(16.0 / 9.0);
+    let camera = Camera::default();
+    let centre = camera.ray_through(projection, (640.0, 360.0), (1280, 720));
+    assert!(centre.x.abs() < 1e-6 && centre.y.abs() < 1e-6);
+    assert_eq!(centre.z, -1.0);
+
+    let corner = camera.ray_through(projection, (0.0, 720.0), (1280, 720));
+    assert!(corner.x < 0.0 && corner.y < 0.0 && corner.z < 0.0);
+    // at 70 degrees up and down, the bottom edge is tan(35) below the middle
+    assert!((corner.y + 35f32.to_radians().tan()).abs() < 1e-4);
+    assert!((corner.x / corner.y - 16.0 / 9.0).abs() < 1e-4);
+
+    // turned around, the same pixel looks the other way
+    let turned = Camera {
+        yaw: std::f32::consts::PI,
+        ..camera
+    };
+    let behind = turned.ray_through(projection, (640.0, 360.0), (1280, 720));
+    assert!((behind.z - 1.0).abs() < 1e-5);
+}
+
+#[test]
+fn grazing_rays_pick_the_nearest_water() {
+    let grid = Grid::new(100, 100);
+    let config = Config::default();
+    let camera = Camera {
+        x: 0.0,
+        y: 2.0,
+        z: 0.0,
+        ..Camera::default()
+    };
+    // within the tolerance of flat water from 30 to 50 along the ray
+    let ray = Vector3::new(0.0, -0.05, 1.0);
+    let hit = search(ray, &camera, &grid, &config, |_, _| 0.0).unwrap();
+    assert!(hit.z >= 30.0 && hit.z <= 31.0, "{:?}", hit);
+
+    // a wave standing up in the way is hit first
+    let wave = |_: u32, y: u32| if y == 29 { 1.0 } else { 0.0 };
+    let hit = search(ray, &camera, &grid, &config, wave).unwrap();
+    assert_eq!(grid.tile_at(hit.x, hit.z), Some((50, 29)));
+
+    let up = Vector3::new(0.0, 0.05, 1.0);
+    assert!(search(up, &camera, &grid, &config, |_, _| 0.0).is_none());
+}
+
+#[test]
+fn clearing_removes_the_waves_on_a_tile() {
+    for (mode, kind) in [
+        (SolverMode::Automaton, WaveKind::Directional),
+        (SolverMode::Automaton, WaveKind::Radial),
+        (SolverMode::WaveEquation, WaveKind::Directional),
+    ]
+    .iter()
+    {
+        let waves = waves(*kind);
+        let settings = SolverSettings {
+            mode: *mode,
+            ..SolverSettings::default()
+        };
+        let mut sim = settings.new_solver(20, 20, 1);
+        sim.place(10, 10, 0, &waves);
+        sim.clear(10, 10);
+        // off the grid does nothing
+        sim.clear(20, 3);
+        (0..8).for_each(|_| sim.step(&waves));
+        assert!(is_flat(sim.as_ref(), &waves), "{:?} {:?}", mode, kind);
+    }
+}
+
+#[test]
+fn clears_are_recorded_and_replayed() {
+    let mut recording = Recording::default();
+    let wave = waves(WaveKind::Directional).get(0).copied().unwrap();
+    recording.record_definition(0, 0, &wave);
+    recording.record_placement(0, 5, 5, 0);
+    recording.record_placement(0, 8, 2, 0);
+    recording.record_clear(3, 5, 3);
+    let src = recording.to_toml().unwrap();
+    let loaded = Recording::from_toml(&src).unwrap();
+    assert_eq!(loaded, recording);
+    assert_eq!(
+        loaded.events[3],
+        Event::Clear {
+            tick: 3,
+            x: 5,
+            y: 3
+        }
+    );
+
+    // the wave placed on (5, 5) has gone up over (5, 3) after three updates
+    let scene = Scene {
+        grid: Grid {
+            width: 20,
+            height: 20,
+            cell_size: 1.0,
+        },
+        ..Scene::default()
+    };
+    let (sim, waves) = recording.replay(&scene, 3).unwrap();
+    assert_eq!(sim.height_at(5, 3, &waves), 0.0);
+    assert_ne!(sim.height_at(5, 4, &waves), 0.0);
+    assert_ne!(sim.height_at(8, 1, &waves), 0.0);
+    recording.events.pop();
+    let (sim, waves) = recording.replay(&scene, 3).unwrap();
+    assert_ne!(sim.height_at(5, 3, &waves), 0.0);
+}
+
+#[test]
+fn clear_commands_parse() {
+    assert_eq!(Command::parse("clear").unwrap(), Command::Clear(None));
+    assert_eq!(
+        Command::parse("clear 4 7").unwrap(),
+        Command::Clear(Some((4, 7)))
+    );
+    assert!(Command::parse("clear 4")
+        .unwrap_err()
+        .starts_with("Usage: clear"));
+}
@@ -0,0 +1,199 @@
+use wave_simulator::wave::bathymetry::Bathymetry;
+use wave_simulator::wave::obstacles::ObstacleMask;
+use wave_simulator::wave::physics::place_drivers;
+use wave_simulator::wave::scene::Scene;
+use wave_simulator::wave::sim::{
+    heading_vector, Simulation, Solver, Wave, WaveKind, WaveRegistry, RIGHT, UP,
+};
+
+fn plane(heading: f32, wavelength: u8) -> WaveRegistry {
+    let mut waves = WaveRegistry::new();
+    waves.set(
+        0,
+        Wave {
+            wavelength,
+            kind: WaveKind::Plane,
+            amplitude_factor: 1.0,
+            heading,
+            ..Wave::default()
+        },
+    );
+    waves
+}
+
+#[test]
+fn headings_match_the_directions() {
+    let close = |(x, y): (f32, f32), (ex, ey): (f32, f32)| {
+        assert!((x - ex).abs() < 1e-6 && (y - ey).abs() < 1e-6)
+    };
+    // RIGHT tiles read from the right, so the wave moves towards x = 0
+    close(heading_vector(0.0), (-1.0, 0.0));
+    close(heading_vector(90.0), (0.0, -1.0));
+    close(heading_vector(180.0), (1.0, 0.0));
+    close(heading_vector(270.0), (0.0, 1.0));
+}
+
+fn directional(directions: u8, wavelength: u8) -> WaveRegistry {
+    let mut waves = WaveRegistry::new();
+    waves.set(
+        0,
+        Wave {
+            directions,
+            wavelength,
+            amplitude_factor: 1.0,
+            ..Wave::default()
+        },
+    );
+    waves
+}
+
+// places a plane wave and a directional one on the same tile of a line, and checks that their
+// fronts move the same way along it. Automaton tiles read a tick ahead of ripples once they have
+// propagated, so the fronts can be a tile apart.
+fn moves_like(heading: f32, directions: u8, line: impl Fn(u32) -> (u32, u32)) {
+    let (plane, directional) = (plane(heading, 4), directional(directions, 4));
+    let mut sim = Simulation::new(20, 20, 1);
+    let mut reference = Simulation::new(20, 20, 1);
+    sim.place(line(15).0, line(15).1, 0, &plane);
+    reference.place(line(15).0, line(15).1, 0, &directional);
+    (0..7).for_each(|_| {
+        sim.step(&plane);
+        reference.step(&directional);
+    });
+    let front = |solver: &Simulation, waves: &WaveRegistry| {
+        (0..20)
+            .find(|i| solver.height_at(line(*i).0, line(*i).1, waves) > 0.0)
+            .unwrap()
+    };
+    let (a, b) = (front(&sim, &plane), front(&reference, &directional));
+    assert!(a < 15 && b < 15);
+    assert!(a == b || a == b + 1, "fronts at {} and {}", a, b);
+    assert!((16..20).all(|i| sim.height_at(line(i).0, line(i).1, &plane) == 0.0));
+}
+
+#[test]
+fn heading_0_moves_like_right() {
+    moves_like(0.0, RIGHT, |x| (x, 10));
+}
+
+#[test]
+fn heading_90_moves_like_up() {
+    moves_like(90.0, UP, |y| (10, y));
+}
+
+#[test]
+fn plane_waves_span_the_grid() {
+    let waves = plane(90.0, 4);
+    let mut sim = Simulation::new(20, 20, 1);
+    sim.place(10, 15, 0, &waves);
+    (0..6).for_each(|_| sim.step(&waves));
+    let at = |x: u32, y: u32| sim.height_at(x, y, &waves);
+    assert!(at(10, 11) > 0.0);
+    assert_eq!(at(0, 11), at(10, 11));
+    assert_eq!(at(19, 11), at(10, 11));
+}
+
+#[test]
+fn diagonal_fronts_are_straight() {
+    let waves = plane(45.0, 6);
+    let mut sim = Simulation::new(30, 30, 1);
+    sim.place(20, 20, 0, &waves);
+    (0..10).for_each(|_| sim.step(&waves));
+    let at = |x: u32, y: u32| sim.height_at(x, y, &waves);
+    // tiles on a line across the heading are at the same height
+    assert!(at(15, 15) > 0.0);
+    assert_eq!(at(15, 15), at(12, 18));
+    assert_eq!(at(15, 15), at(18, 12));
+}
+
+#[test]
+fn plane_waves_only_move_forwards() {
+    let waves = plane(0.0, 4);
+    let mut sim = Simulation::new(20, 20, 1);
+    sim.place(10, 10, 0, &waves);
+    (0..6).for_each(|_| sim.step(&waves));
+    let at = |x: u32, y: u32| sim.height_at(x, y, &waves);
+    assert!((0..10).any(|x| at(x, 10) > 0.0));
+    (11..20).for_each(|x| assert_eq!(at(x, 10), 0.0));
+}
+
+#[test]
+fn wave_equation_drives_a_line_across_the_heading() {
+    let waves = plane(90.0, 4);
+    let drivers = place_drivers((5, 7), 0, waves.get(0), &ObstacleMask::open(20, 10), 1.0);
+    assert_eq!(drivers.len(), 20);
+    assert!(drivers.iter().all(|driver| driver.y == 7));
+
+    let open = ObstacleMask::open(10, 10);
+    let drivers = place_drivers((5, 5), 0, plane(45.0, 4).get(0), &open, 1.0);
+    assert!(drivers.iter().all(|driver| driver.x + driver.y == 10));
+
+    let mut radial = plane(0.0, 4);
+    radial.set(
+        0,
+        Wave {
+            kind: WaveKind::Radial,
+            ..*radial.get(0).unwrap()
+        },
+    );
+    assert_eq!(place_drivers((5, 5), 0, radial.get(0), &open, 1.0).len(), 1);
+}
+
+#[test]
+fn scenes_can_place_plane_waves() {
+    let src = "[[waves]]\nslot = 0\namplitude = 1.0\nwavelength = 6\nkind = \"plane\"\n\
+               heading = 45.0\n\n[[sources]]\nx = 50\ny = 50\nslot = 0\n";
+    let scene = Scene::from_toml(src).unwrap();
+    let waves = scene.get_waves();
+    let wave = waves.get(0).unwrap();
+    assert_eq!(wave.kind, WaveKind::Plane);
+    assert_eq!(wave.heading, 45.0);
+    let mut sim = scene.instantiate();
+    (0..8).for_each(|_| sim.step(&waves));
+    let at = |x: u32, y: u32| sim.height_at(x, y, &waves);
+    assert!(at(47, 47) > 0.0);
+    assert_eq!(at(47, 47), at(45, 49));
+    assert_eq!(at(53, 53), 0.0);
+}
+
+#[test]
+fn land_shelters_the_water_behind_it() {
+    // moving towards y = 0, with a breakwater across x = 5 to 14 on y = 10
+    let waves = plane(90.0, 4);
+    let mut sim = Simulation::new(20, 20, 1);
+    let mut breakwater = ObstacleMask::open(20, 20);
+    (5..15).for_each(|x| breakwater.set_blocked(x, 10, true));
+    sim.set_obstacles(breakwater);
+    sim.place(10, 18, 0, &waves);
+    (0..11).for_each(|_| sim.step(&waves));
+
+    let at = |x: u32, y: u32| sim.height_at(x, y, &waves);
+    // past the end of the breakwater the front is 10 tiles on, but not behind it
+    assert!(at(2, 9) > 0.0);
+    assert!(at(17, 9) > 0.0);
+    assert_eq!(at(10, 9), 0.0);
+    assert_eq!(sim.slot_tick(0, 10, 9, &waves), None);
+    // and just in front of it, the end of the wave is going by
+    assert_eq!(sim.slot_tick(0, 10, 11, &waves), Some(4.0));
+}
+
+#[test]
+fn plane_waves_slow_down_over_shallows() {
+    let waves = plane(90.0, 4);
+    let mut sim = Simulation::new(20, 20, 1);
+    // half speed from y = 10 down, on the left half
+    sim.set_bathymetry(Bathymetry::from_fn(20, 20, |x, y| {
+        if x < 10 && y <= 10 {
+            0.25
+        } else {
+            1.0
+        }
+    }));
+    sim.place(10, 18, 0, &waves);
+    (0..12).for_each(|_| sim.step(&waves));
+    let tick = |x: u32| sim.slot_tick(0, x, 8, &waves);
+    // 10 tiles on, 3 of them shallow on the left, where the front is further back
+    assert_eq!(tick(15), Some(2.0));
+    assert_eq!(tick(5), None);
+    assert_eq!(sim.slot_tick(0, 5, 9, &waves), Some(1.0));
+}
@@ -0,0 +1,154 @@
+use wave_simulator::wave::bathymetry::Bathymetry;
+use wave_simulator::wave::boundary::Boundary;
+use wave_simulator::wave::obstacles::ObstacleMask;
+use wave_simulator::wave::scene::Scene;
+use wave_simulator::wave::sim::{
+    age_ripples, ripple_height, Ripple, Simulation, Solver, Wave, WaveKind, WaveRegistry,
+};
+
+fn radial(wavelength: u8) -> WaveRegistry {
+    let mut waves = WaveRegistry::new();
+    waves.set(
+        0,
+        Wave {
+            wavelength,
+            kind: WaveKind::Radial,
+            amplitude_factor: 1.0,
+            ..Wave::default()
+        },
+    );
+    waves
+}
+
+#[test]
+fn wavefronts_are_circles() {
+    let waves = radial(8);
+    let mut sim = Simulation::new(41, 41, 1);
+    sim.place(20, 20, 0, &waves);
+    (0..12).for_each(|_| sim.step(&waves));
+
+    let at = |x: u32, y: u32| sim.height_at(x, y, &waves);
+    // 8 tiles out, in four directions
+    let ring = [(28, 20), (12, 20), (20, 28), (20, 12)];
+    ring.iter()
+        .for_each(|(x, y)| assert!((at(*x, *y) - at(28, 20)).abs() < 1e-6));
+    assert!(at(28, 20) > 0.5);
+    // (26, 26) is 8.49 tiles out
+    let diagonal = (12.0f32 - 72.0f32.sqrt()) * std::f32::consts::PI / 8.0;
+    assert!((at(26, 26) - diagonal.sin()).abs() < 1e-5);
+    assert!((at(26, 26) - at(14, 14)).abs() < 1e-6);
+    // not reached yet
+    assert_eq!(at(0, 0), 0.0);
+}
+
+#[test]
+fn ripples_pass_through_once() {
+    let waves = radial(4);
+    let mut sim = Simulation::new(9, 9, 1);
+    sim.place(4, 4, 0, &waves);
+    (0..30).for_each(|_| sim.step(&waves));
+    assert!(sim.ripples.is_empty());
+    (0..9).for_each(|y| (0..9).for_each(|x| assert_eq!(sim.height_at(x, y, &waves), 0.0)));
+}
+
+#[test]
+fn ripples_leave_with_their_slot() {
+    let mut waves = radial(4);
+    let mut ripples = vec![Ripple {
+        x: 1,
+        y: 1,
+        slot: 0,
+        age: 0,
+    }];
+    age_ripples(&mut ripples, &waves, Boundary::Fixed, 10, 10, &[]);
+    assert_eq!(ripples[0].age, 1);
+    waves.set(0, Wave::default());
+    age_ripples(&mut ripples, &waves, Boundary::Fixed, 10, 10, &[]);
+    assert!(ripples.is_empty());
+}
+
+#[test]
+fn ripples_start_like_automaton_tiles() {
+    let waves = radial(6);
+    let wave = waves.get(0).unwrap();
+    let ripple = Ripple {
+        x: 3,
+        y: 3,
+        slot: 0,
+        age: 3,
+    };
+    let open = ObstacleMask::open(10, 10);
+    assert_eq!(
+        ripple_height(&ripple, wave, Boundary::Fixed, 3, 3, &open, &[]),
+        1.0
+    );
+    assert_eq!(
+        ripple_height(&ripple, wave, Boundary::Fixed, 3, 0, &open, &[]),
+        0.0
+    );
+    assert_eq!(
+        ripple_height(&ripple, wave, Boundary::Fixed, 3, 7, &open, &[]),
+        0.0
+    );
+}
+
+#[test]
+fn scenes_can_place_radial_waves() {
+    let src = "[[waves]]\nslot = 0\namplitude = 1.0\nwavelength = 6\nkind = \"radial\"\n\n\
+               [[sources]]\nx = 50\ny = 50\nslot = 0\n";
+    let scene = Scene::from_toml(src).unwrap();
+    let waves = scene.get_waves();
+    assert_eq!(waves.get(0).unwrap().kind, WaveKind::Radial);
+    let mut sim = scene.instantiate();
+    (0..5).for_each(|_| sim.step(&waves));
+    let at = |x: u32, y: u32| sim.height_at(x, y, &waves);
+    assert!(at(52, 50) > 0.0);
+    assert_eq!(at(52, 50), at(48, 50));
+    assert_eq!(at(52, 50), at(50, 52));
+}
+
+#[test]
+fn walls_shelter_the_water_behind_them() {
+    let waves = radial(4);
+    let mut sim = Simulation::new(21, 21, 1);
+    // a wall across x = 12, from y = 6 to 14
+    let mut wall = ObstacleMask::open(21, 21);
+    (6..=14).for_each(|y| wall.set_blocked(12, y, true));
+    sim.set_obstacles(wall);
+    sim.place(8, 10, 0, &waves);
+    (0..8).for_each(|_| sim.step(&waves));
+
+    let at = |x: u32, y: u32| sim.height_at(x, y, &waves);
+    // as far out on either side of the source, but only one behind the wall
+    assert!(at(3, 10) > 0.5);
+    assert_eq!(at(13, 10), 0.0);
+    assert_eq!(sim.slot_tick(0, 13, 10, &waves), None);
+    assert!(sim.slot_tick(0, 3, 10, &waves).is_some());
+    // past the end of it is in the open
+    assert!(at(12, 16) > 0.0);
+}
+
+#[test]
+fn ripples_slow_down_over_shallows() {
+    let waves = radial(4);
+    let mut sim = Simulation::new(21, 21, 1);
+    // half speed to the right of x = 12
+    sim.set_bathymetry(Bathymetry::from_fn(
+        21,
+        21,
+        |x, _| {
+            if x > 12 {
+                0.25
+            } else {
+                1.0
+            }
+        },
+    ));
+    sim.place(10, 10, 0, &waves);
+    (0..6).for_each(|_| sim.step(&waves));
+    let tick = |x: u32| sim.slot_tick(0, x, 10, &waves);
+    // 4 tiles out to the left, and to the right only just, 2 of them shallow
+    assert_eq!(tick(6), Some(2.0));
+    assert_eq!(tick(14), Some(0.0));
+    assert_eq!(tick(15), None);
+}
@@ -0,0 +1,186 @@
+use wave_simulator::wave::boundary::Boundary;
+use wave_simulator::wave::command::DepthMap;
+use wave_simulator::wave::recording::{Event, Recording};
+use wave_simulator::wave::scene::Scene;
+use wave_simulator::wave::sim::{Solver, Wave, WaveKind, WaveRegistry, UP};
+
+fn wave(kind: WaveKind, amplitude_factor: f32) -> Wave {
+    Wave {
+        directions: UP,
+        wavelength: 6,
+        kind,
+        amplitude_factor,
+        decay: 0.01,
+        ..Wave::default()
+    }
+}
+
+fn session() -> Recording {
+    let mut recording = Recording::default();
+    recording.record_definition(0, 0, &wave(WaveKind::Directional, 1.0));
+    recording.record_placement(0, 20, 30, 0);
+    recording.record_definition(12, 1, &wave(WaveKind::Radial, 0.5));
+    recording.record_placement(12, 10, 10, 1);
+    recording.record_placement(12, 30, 12, 0);
+    recording.record_definition(25, 0, &wave(WaveKind::Directional, -2.0));
+    recording
+}
+
+fn heights(sim: &dyn Solver, waves: &WaveRegistry) -> Vec<u32> {
+    let (width, height) = sim.get_size();
+    (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| sim.height_at(x, y, waves).to_bits())
+        .collect()
+}
+
+#[test]
+fn recordings_round_trip_through_toml() {
+    let recording = session();
+    let src = recording.to_toml().unwrap();
+    assert!(src.contains("event = 'define'"));
+    assert!(src.contains("event = 'place'"));
+    assert_eq!(Recording::from_toml(&src).unwrap(), recording);
+}
+
+#[test]
+fn events_must_be_in_order() {
+    let mut recording = session();
+    recording.record_placement(3, 1, 1, 0);
+    let e = Recording::from_toml(&recording.to_toml().unwrap()).unwrap_err();
+    assert!(e.contains("out of order"), "{}", e);
+}
+
+#[test]
+fn slots_past_the_limit_are_rejected() {
+    let mut recording = session();
+    recording.record_placement(30, 1, 1, 1_000_000_000);
+    let e = Recording::from_toml(&recording.to_toml().unwrap()).unwrap_err();
+    assert!(e.contains("slot"), "{}", e);
+
+    let mut recording = session();
+    recording.record_definition(30, 1_000_000_000, &wave(WaveKind::Radial, 1.0));
+    assert!(Recording::from_toml(&recording.to_toml().unwrap()).is_err());
+}
+
+#[test]
+fn broken_definitions_are_rejected() {
+    let mut recording = session();
+    recording.record_definition(30, 2, &wave(WaveKind::Radial, 80.0));
+    let e = Recording::from_toml(&recording.to_toml().unwrap()).unwrap_err();
+    assert!(e.contains("Amplitude"), "{}", e);
+
+    // a half sine 0 updates long has no heights
+    let mut recording = session();
+    let flat = Wave {
+        wavelength: 0,
+        ..wave(WaveKind::Directional, 1.0)
+    };
+    recording.record_definition(30, 2, &flat);
+    assert!(recording.validate().unwrap_err().contains("no wavelength"));
+}
+
+#[test]
+fn replays_match_the_session_bit_for_bit() {
+    let scene = Scene::default();
+    let (sim, waves) = session().replay(&scene, 40).unwrap();
+
+    // the same session, by hand
+    let mut by_hand = scene.instantiate();
+    let mut slots = scene.get_waves();
+    for tick in 0..=40 {
+        match tick {
+            0 => {
+                slots.set(0, wave(WaveKind::Directional, 1.0));
+                by_hand.place(20, 30, 0, &slots);
+            }
+            12 => {
+                slots.set(1, wave(WaveKind::Radial, 0.5));
+                by_hand.place(10, 10, 1, &slots);
+                by_hand.place(30, 12, 0, &slots);
+            }
+            25 => {
+                slots.set(0, wave(WaveKind::Directional, -2.0));
+            }
+            _ => {}
+        }
+        if tick < 40 {
+            by_hand.step(&slots);
+        }
+    }
+
+    assert!(heights(sim.as_ref(), &waves).iter().any(|h| *h != 0));
+    assert_eq!(
+        heights(sim.as_ref(), &waves),
+        heights(by_hand.as_ref(), &slots)
+    );
+    let (again, waves_again) = session().replay(&scene, 40).unwrap();
+    assert_eq!(
+        heights(sim.as_ref(), &waves),
+        heights(again.as_ref(), &waves_again)
+    );
+}
+
+#[test]
+fn replays_work_in_wave_equation_mode() {
+    let scene = Scene::from_toml("[solver]\nmode = \"wave-equation\"\n").unwrap();
+    let (a, waves_a) = session().replay(&scene, 30).unwrap();
+    let (b, waves_b) = session().replay(&scene, 30).unwrap();
+    assert!(heights(a.as_ref(), &waves_a).iter().any(|h| *h != 0));
+    assert_eq!(heights(a.as_ref(), &waves_a), heights(b.as_ref(), &waves_b));
+}
+
+#[test]
+fn events_apply_after_their_tick() {
+    let recording = session();
+    assert_eq!(recording.events_at(12).count(), 3);
+    assert!(recording
+        .events_at(25)
+        .all(|event| matches!(event, Event::Define { .. })));
+    let (sim, waves) = recording.replay(&Scene::default(), 12).unwrap();
+    // placed after the 12th update, so it hasn't moved yet
+    assert_eq!(sim.height_at(10, 10, &waves), 0.0);
+    assert_eq!(waves.len(), 2);
+}
+
+#[test]
+fn edges_obstacles_and_depth_are_replayed() {
+    let mut recording = session();
+    recording.record_boundary(25, Boundary::Periodic);
+    recording.record_depth(25, DepthMap::Shoal);
+    recording.record_obstacles(30, None);
+    let src = recording.to_toml().unwrap();
+    assert!(src.contains("event = 'boundary'"));
+    assert_eq!(Recording::from_toml(&src).unwrap(), recording);
+
+    let scene = Scene::default();
+    let (sim, waves) = recording.replay(&scene, 40).unwrap();
+    let (plain, plain_waves) = session().replay(&scene, 40).unwrap();
+    assert_ne!(
+        heights(sim.as_ref(), &waves),
+        heights(plain.as_ref(), &plain_waves)
+    );
+    let (again, waves_again) = recording.replay(&scene, 40).unwrap();
+    assert_eq!(
+        heights(sim.as_ref(), &waves),
+        heights(again.as_ref(), &waves_again)
+    );
+}
+
+#[test]
+fn missing_obstacle_files_stop_the_replay() {
+    let mut recording = session();
+    recording.record_obstacles(30, Some("no/such/land.png".into()));
+    let e = recording.replay(&Scene::default(), 40).err().unwrap();
+    assert!(e.contains("land.png"), "{}", e);
+}
+
+#[test]
+fn recordings_that_switched_solver_are_not_saved() {
+    let mut recording = session();
+    recording.solver_switched = true;
+    let path = std::env::temp_dir().join("wave-simulator-switched.toml");
+    let e = recording.save(&path).unwrap_err();
+    assert!(e.contains("solver"), "{}", e);
+    assert!(!path.exists());
+}
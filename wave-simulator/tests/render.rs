@@ -0,0 +1,191 @@
+use cgmath::Vector3;
+use wave_simulator::wave::camera::Camera;
+use wave_simulator::wave::config::Config;
+use wave_simulator::wave::grid::Grid;
+use wave_simulator::wave::obstacles::ObstacleMask;
+use wave_simulator::wave::render::software::SoftwareRenderer;
+use wave_simulator::wave::render::{
+    to_rgba8, Image, Light, Renderer, Shading, Surface, LAND_COLOUR, MARKER_COLOUR,
+};
+use wave_simulator::wave::sim::{Simulation, Solver, WaveRegistry};
+
+const BLACK: [u8; 4] = [0, 0, 0, 255];
+
+// straight down on the middle of the grid, from high enough to see all of a 20 by 20 one
+fn overhead() -> Camera {
+    Camera {
+        y: 30.0,
+        pitch: std::f32::consts::FRAC_PI_2,
+        ..Camera::default()
+    }
+}
+
+// the water as it comes out of the texture
+fn unlit() -> Shading {
+    Shading::Water(Light::unlit())
+}
+
+fn render(surface: &Surface, camera: &Camera) -> Image {
+    let mut renderer = SoftwareRenderer::new(64, 64);
+    renderer.upload_surface(surface);
+    renderer.clear([0.0, 0.0, 0.0, 1.0]);
+    renderer.draw_water(
+        Config::default().projection_matrix(1.0),
+        camera.get_matrix(),
+        &unlit(),
+    );
+    renderer.frame
+}
+
+#[test]
+fn nothing_is_drawn_without_a_surface() {
+    let mut renderer = SoftwareRenderer::new(8, 8);
+    renderer.clear([1.0, 0.0, 0.0, 1.0]);
+    renderer.draw_water(
+        Config::default().projection_matrix(1.0),
+        overhead().get_matrix(),
+        &unlit(),
+    );
+    assert_eq!(renderer.frame, Image::new(8, 8, [255, 0, 0, 255]));
+}
+
+#[test]
+fn water_is_textured_and_the_rest_is_cleared() {
+    let frame = render(&Surface::flat(Grid::new(20, 20)), &overhead());
+    let centre = frame.get(32, 32);
+    assert_ne!(centre, BLACK);
+    assert_ne!(centre, to_rgba8(LAND_COLOUR));
+    // the grid doesn't reach the corners
+    assert_eq!(frame.get(0, 0), BLACK);
+    assert_eq!(frame.get(63, 63), BLACK);
+
+    // every pixel of the water comes out of the texture
+    let texture = Image::load(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/wave/bundles/thatiswater.png"
+    ))
+    .unwrap();
+    let texels = texture
+        .pixels
+        .chunks(4)
+        .map(|t| [t[0], t[1], t[2], t[3]])
+        .collect::<Vec<[u8; 4]>>();
+    assert!(texels.contains(&centre));
+}
+
+#[test]
+fn land_is_drawn_in_its_colour() {
+    let grid = Grid::new(20, 20);
+    let mut obstacles = ObstacleMask::open(20, 20);
+    // the top half of the tiles, which is the near half of the grid
+    (0..20).for_each(|x| (0..10).for_each(|y| obstacles.set_blocked(x, y, true)));
+    let mut sim = Simulation::new(20, 20, 10);
+    sim.set_obstacles(obstacles);
+    let surface = Surface::new(&sim, &WaveRegistry::new(), grid.cell_size);
+    assert_eq!(surface.land.iter().filter(|land| **land).count(), 200);
+
+    let frame = render(&surface, &overhead());
+    // seen from above, the near side is at the bottom
+    assert_eq!(frame.get(32, 40), to_rgba8(LAND_COLOUR));
+    assert_ne!(frame.get(32, 24), to_rgba8(LAND_COLOUR));
+    assert_ne!(frame.get(32, 24), BLACK);
+}
+
+#[test]
+fn nearer_water_hides_what_is_behind_it() {
+    let grid = Grid::new(20, 20);
+    let mut surface = Surface::flat(grid);
+    surface.land = vec![true; grid.get_len()];
+    let mut renderer = SoftwareRenderer::new(64, 64);
+    renderer.upload_surface(&surface);
+    renderer.clear([0.0, 0.0, 0.0, 1.0]);
+    let projection = Config::default().projection_matrix(1.0);
+    renderer.draw_water(projection, overhead().get_matrix(), &unlit());
+
+    // the same land lower down is behind what's drawn, and doesn't replace it
+    surface.land = vec![false; grid.get_len()];
+    surface.heights = vec![-5.0; grid.get_len()];
+    renderer.upload_surface(&surface);
+    renderer.draw_water(projection, overhead().get_matrix(), &unlit());
+    assert_eq!(renderer.frame.get(32, 32), to_rgba8(LAND_COLOUR));
+
+    // and in front of it, it does
+    surface.heights = vec![5.0; grid.get_len()];
+    renderer.upload_surface(&surface);
+    renderer.draw_water(projection, overhead().get_matrix(), &unlit());
+    assert_ne!(renderer.frame.get(32, 32), to_rgba8(LAND_COLOUR));
+}
+
+#[test]
+fn water_behind_the_camera_is_cut_off() {
+    // standing in the middle of the water, looking along it
+    let camera = Camera {
+        y: 2.0,
+        ..Camera::default()
+    };
+    let frame = render(&Surface::flat(Grid::new(20, 20)), &camera);
+    // below the horizon is water, above it nothing
+    assert_ne!(frame.get(32, 63), BLACK);
+    assert_eq!(frame.get(32, 0), BLACK);
+}
+
+#[test]
+fn the_marker_is_drawn_where_it_is_seen() {
+    let mut renderer = SoftwareRenderer::new(64, 64);
+    renderer.upload_surface(&Surface::flat(Grid::new(20, 20)));
+    renderer.clear([0.0, 0.0, 0.0, 1.0]);
+    let projection = Config::default().projection_matrix(1.0);
+    let view = overhead().get_matrix();
+    renderer.draw_water(projection, view, &unlit());
+    renderer.draw_marker(projection, view, Vector3::new(0.0, 1.0, 0.0));
+    assert_eq!(renderer.frame.get(32, 32), to_rgba8(MARKER_COLOUR));
+    // a unit cube 29 away covers a pixel or two around the middle
+    assert_ne!(renderer.frame.get(32, 40), to_rgba8(MARKER_COLOUR));
+
+    // under the water, it's hidden
+    renderer.clear([0.0, 0.0, 0.0, 1.0]);
+    renderer.draw_water(projection, view, &unlit());
+    renderer.draw_marker(projection, view, Vector3::new(0.0, -5.0, 0.0));
+    assert_ne!(renderer.frame.get(32, 32), to_rgba8(MARKER_COLOUR));
+}
+
+#[test]
+fn quads_are_blended_over_the_frame() {
+    let mut renderer = SoftwareRenderer::new(8, 8);
+    renderer.clear([0.0, 0.0, 0.0, 1.0]);
+    let mut image = Image::new(2, 2, [0, 0, 0, 0]);
+    image.set(0, 0, [255, 255, 255, 255]);
+    image.set(1, 0, [255, 0, 0, 128]);
+    image.set(1, 1, [0, 255, 0, 255]);
+    // the middle half of the frame, so every texel covers 2 by 2 pixels
+    renderer.draw_quad([0.0, 0.0, 0.5, 0.5], &image);
+    let frame = &renderer.frame;
+    assert_eq!(frame.get(2, 2), [255, 255, 255, 255]);
+    assert_eq!(frame.get(3, 3), [255, 255, 255, 255]);
+    assert_eq!(frame.get(5, 2), [128, 0, 0, 255]);
+    assert_eq!(frame.get(2, 5), BLACK);
+    assert_eq!(frame.get(5, 5), [0, 255, 0, 255]);
+    // and nothing outside of it
+    assert_eq!(frame.get(1, 1), BLACK);
+    assert_eq!(frame.get(6, 6), BLACK);
+
+    // half off the frame is cut off
+    renderer.draw_quad([-1.0, -1.0, 0.5, 0.5], &image);
+    assert_eq!(renderer.frame.get(0, 7), [128, 0, 0, 255]);
+    assert_eq!(renderer.frame.get(2, 7), BLACK);
+}
+
+#[test]
+fn images_save_and_load() {
+    let mut image = Image::new(3, 2, [10, 20, 30, 255]);
+    image.set(2, 1, [200, 100, 50, 128]);
+    assert_eq!(image.get(2, 1), [200, 100, 50, 128]);
+    let path = std::env::temp_dir().join(format!("render_{}.png", std::process::id()));
+    image.save(&path).unwrap();
+    let loaded = Image::load(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.unwrap(), image);
+
+    assert!(Image::load("/no/such/image.png").is_err());
+    assert_eq!(Image::crosshair().width, 5);
+}
@@ -0,0 +1,184 @@
+use std::path::PathBuf;
+use wave_simulator::wave::bathymetry::Bathymetry;
+use wave_simulator::wave::camera::Camera;
+use wave_simulator::wave::obstacles::ObstacleMask;
+use wave_simulator::wave::scene::{Scene, WaveDefinition};
+use wave_simulator::wave::sim::{Wave, WaveRegistry, DOWN, LEFT, RIGHT, UP};
+
+// a fresh directory for one test to write scenes into
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("wave-simulator-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn example_scene_loads() {
+    let scene = Scene::from_toml(include_str!("../scenes/example.toml")).unwrap();
+    let waves = scene.get_waves();
+    assert_eq!(waves.len(), 2);
+    assert_eq!(waves.get(0).unwrap().directions, UP);
+    assert_eq!(waves.get(1).unwrap().directions, LEFT | DOWN);
+    assert_eq!(waves.get(1).unwrap().wavelength, 12);
+
+    let mut sim = scene.instantiate();
+    sim.step(&waves);
+    assert!(sim.height_at(50, 90, &waves) > 0.0);
+    assert!(sim.height_at(10, 10, &waves) > 0.0);
+    assert_eq!(sim.height_at(50, 50, &waves), 0.0);
+}
+
+#[test]
+fn invalid_scenes_are_rejected() {
+    let bad_slot = "[[sources]]\nx = 0\ny = 0\nslot = 4\n";
+    assert!(Scene::from_toml(bad_slot).is_err());
+
+    let bad_slot = "[[waves]]\nslot = 7\namplitude = 1.0\nwavelength = 5\n\n\
+                    [[sources]]\nx = 0\ny = 0\nslot = 6\n";
+    assert!(Scene::from_toml(bad_slot).is_err());
+
+    let outside = "[[waves]]\nslot = 0\namplitude = 1.0\nwavelength = 5\n\n[[sources]]\nx = 100\ny = 0\nslot = 0\n";
+    assert!(Scene::from_toml(outside).is_err());
+
+    let huge = "[[waves]]\nslot = 1000000000\namplitude = 1.0\nwavelength = 5\n";
+    assert!(Scene::from_toml(huge).unwrap_err().contains("slot"));
+
+    let twice = "[[waves]]\nslot = 1\namplitude = 1.0\nwavelength = 5\n\n\
+                 [[waves]]\nslot = 1\namplitude = 2.0\nwavelength = 5\n";
+    assert!(Scene::from_toml(twice).is_err());
+
+    let direction = "[[waves]]\nslot = 0\namplitude = 1.0\nwavelength = 5\n\
+                     directions = [\"sideways\"]\n";
+    assert!(Scene::from_toml(direction).is_err());
+
+    // a half sine 0 updates long has no heights
+    let flat = "[[waves]]\nslot = 0\namplitude = 1.0\nwavelength = 0\n\n\
+                [[sources]]\nx = 0\ny = 0\nslot = 0\n";
+    assert!(Scene::from_toml(flat)
+        .unwrap_err()
+        .contains("no wavelength"));
+
+    let loud = "[[waves]]\nslot = 0\namplitude = 50.5\nwavelength = 5\n";
+    assert!(Scene::from_toml(loud).unwrap_err().contains("Amplitude"));
+}
+
+#[test]
+fn waves_that_are_not_numbers_are_rejected() {
+    let wave = WaveDefinition::from_wave(
+        0,
+        &Wave {
+            wavelength: 5,
+            amplitude_factor: 1.0,
+            ..Wave::default()
+        },
+    );
+    assert!(wave.validate().is_ok());
+    let nan = WaveDefinition {
+        amplitude: f32::NAN,
+        ..wave.clone()
+    };
+    assert!(nan.validate().unwrap_err().contains("Amplitude"));
+    [f32::NAN, f32::INFINITY].iter().for_each(|heading| {
+        let wave = WaveDefinition {
+            heading: *heading,
+            ..wave.clone()
+        };
+        assert!(wave.validate().unwrap_err().contains("Heading"));
+    });
+    let decay = WaveDefinition {
+        decay: f32::NAN,
+        ..wave
+    };
+    assert!(decay.validate().is_err());
+}
+
+#[test]
+fn scenes_survive_a_round_trip() {
+    let scene = Scene::from_toml(include_str!("../scenes/example.toml")).unwrap();
+    let again = Scene::from_toml(&scene.to_toml().unwrap()).unwrap();
+    assert_eq!(scene, again);
+}
+
+#[test]
+fn waves_are_written_back_as_definitions() {
+    let mut waves = WaveRegistry::new();
+    waves.set(
+        2,
+        Wave {
+            directions: UP | RIGHT,
+            wavelength: 9,
+            amplitude_factor: -1.5,
+            decay: 0.01,
+            lifetime: 40,
+            ..Wave::default()
+        },
+    );
+    let mut scene = Scene::default();
+    scene.set_waves(&waves);
+    // the empty slots before it are left out
+    assert_eq!(scene.waves.len(), 1);
+    assert_eq!(scene.waves[0].slot, 2);
+    let again = scene.get_waves();
+    let wave = again.get(2).unwrap();
+    assert_eq!(wave.directions, UP | RIGHT);
+    assert_eq!((wave.amplitude_factor, wave.lifetime), (-1.5, 40));
+}
+
+#[test]
+fn saved_scenes_load_back() {
+    let dir = scratch("save");
+    let mut mask = ObstacleMask::open(100, 100);
+    (0..100).for_each(|y| mask.set_blocked(0, y, true));
+    let mut scene = Scene::from_toml(include_str!("../scenes/example.toml")).unwrap();
+    scene.obstacle_mask = Some(mask.clone());
+    scene.bathymetry_map = Some(Bathymetry::shoal(100, 100));
+    scene.camera = Some(Camera {
+        x: 1.0,
+        y: 12.0,
+        z: -3.5,
+        pitch: 0.25,
+        yaw: 1.0,
+        roll: 0.0,
+    });
+    scene.save(dir.join("saved.toml")).unwrap();
+    assert!(dir.join("saved-obstacles.png").exists());
+
+    let loaded = Scene::load(dir.join("saved.toml")).unwrap();
+    assert_eq!(loaded.obstacles, Some(PathBuf::from("saved-obstacles.png")));
+    assert_eq!(loaded.obstacle_mask, Some(mask));
+    assert_eq!(loaded.camera, scene.camera);
+    assert_eq!(loaded.waves, scene.waves);
+    assert_eq!(loaded.sources, scene.sources);
+    let (shoal, loaded) = (Bathymetry::shoal(100, 100), loaded.bathymetry_map.unwrap());
+    shoal
+        .speeds
+        .iter()
+        .zip(loaded.speeds.iter())
+        .for_each(|(a, b)| assert!((a - b).abs() < 0.02));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn empty_masks_are_not_saved() {
+    let dir = scratch("empty");
+    let scene = Scene {
+        obstacle_mask: Some(ObstacleMask::open(100, 100)),
+        bathymetry_map: Some(Bathymetry::flat(100, 100)),
+        sources: vec![],
+        ..Scene::default()
+    };
+    scene.save(dir.join("empty.toml")).unwrap();
+    let loaded = Scene::load(dir.join("empty.toml")).unwrap();
+    assert_eq!(loaded.obstacles, None);
+    assert_eq!(loaded.bathymetry, None);
+    assert!(!dir.join("empty-obstacles.png").exists());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn cameras_are_optional() {
+    let scene = Scene::from_toml("[camera]\ny = 20.0\n").unwrap();
+    assert_eq!(scene.camera.unwrap().y, 20.0);
+    assert_eq!(scene.camera.unwrap().x, 0.0);
+    assert_eq!(Scene::from_toml("").unwrap().camera, None);
+}
@@ -0,0 +1,137 @@
+use wave_simulator::wave::constants::MAX_SLOTS;
+use wave_simulator::wave::sim::{
+    get_tick, is_activated, Simulation, Solver, Wave, WaveRegistry, ACTIVATED, DOWN, LEFT, RIGHT,
+    UP,
+};
+
+fn wave(directions: u8, wavelength: u8, amplitude_factor: f32) -> Wave {
+    Wave {
+        directions,
+        wavelength,
+        amplitude_factor,
+        ..Wave::default()
+    }
+}
+
+fn registry(waves: &[Wave]) -> WaveRegistry {
+    let mut registry = WaveRegistry::new();
+    for (slot, wave) in waves.iter().enumerate() {
+        registry.set(slot, *wave);
+    }
+    registry
+}
+
+#[test]
+fn propagates_only_in_its_directions() {
+    let waves = registry(&[wave(RIGHT, 10, 1.0)]);
+    let mut sim = Simulation::new(10, 10, 1);
+    sim.place(5, 5, 0, &waves);
+
+    sim.step(&waves);
+
+    // "right" reads the tile to the right, so the wave travels towards smaller x
+    assert!(is_activated(sim.get(0, 4, 5).unwrap()));
+    assert!(!is_activated(sim.get(0, 6, 5).unwrap()));
+    assert!(!is_activated(sim.get(0, 5, 4).unwrap()));
+    assert!(!is_activated(sim.get(0, 5, 6).unwrap()));
+    assert_eq!(get_tick(sim.get(0, 5, 5).unwrap()), 1);
+    assert_eq!(get_tick(sim.get(0, 4, 5).unwrap()), 1);
+}
+
+#[test]
+fn every_direction_reads_its_own_neighbour() {
+    let expected = [
+        (UP, (5, 4)),
+        (DOWN, (5, 6)),
+        (LEFT, (6, 5)),
+        (RIGHT, (4, 5)),
+    ];
+    expected.iter().for_each(|(direction, (x, y))| {
+        let waves = registry(&[wave(*direction, 10, 1.0)]);
+        let mut sim = Simulation::new(10, 10, 1);
+        sim.place(5, 5, 0, &waves);
+        sim.step(&waves);
+        let activated = sim.tiles.iter().filter(|c| is_activated(**c)).count();
+        assert_eq!(activated, 2);
+        assert!(is_activated(sim.get(0, *x, *y).unwrap()));
+    });
+}
+
+#[test]
+fn tile_resets_after_a_wavelength() {
+    let waves = registry(&[Wave::default(), wave(0, 3, 1.0)]);
+    let mut sim = Simulation::new(3, 3, 2);
+    sim.place(1, 1, 1, &waves);
+
+    (1..=3).for_each(|tick| {
+        sim.step(&waves);
+        assert_eq!(sim.get(1, 1, 1).unwrap(), ACTIVATED + tick);
+    });
+    sim.step(&waves);
+    assert_eq!(sim.get(1, 1, 1).unwrap(), 0);
+}
+
+#[test]
+fn slots_are_independent() {
+    let waves = registry(&[wave(UP, 4, 1.0), wave(DOWN, 4, 1.0)]);
+    let mut sim = Simulation::new(5, 5, 2);
+    sim.place(2, 2, 0, &waves);
+    sim.place(2, 2, 1, &waves);
+
+    sim.step(&waves);
+
+    assert!(is_activated(sim.get(0, 2, 1).unwrap()));
+    assert!(!is_activated(sim.get(1, 2, 1).unwrap()));
+    assert!(is_activated(sim.get(1, 2, 3).unwrap()));
+    assert!(!is_activated(sim.get(0, 2, 3).unwrap()));
+}
+
+#[test]
+fn any_number_of_slots() {
+    let waves = (0..9)
+        .map(|slot| wave(LEFT, 2 + slot as u8, 1.0))
+        .collect::<Vec<Wave>>();
+    let waves = registry(&waves);
+    let mut sim = Simulation::new(4, 4, 0);
+    sim.place(0, 0, 8, &waves);
+    assert_eq!(sim.slots, 9);
+    assert_eq!(sim.get_tile(0, 0).len(), 9);
+
+    sim.step(&waves);
+    assert!(is_activated(sim.get(8, 1, 0).unwrap()));
+    assert!((0..8).all(|slot| sim.get_layer(slot).iter().all(|c| *c == 0)));
+}
+
+#[test]
+fn slots_stop_at_the_limit() {
+    let mut waves = WaveRegistry::new();
+    assert!(waves.set(MAX_SLOTS - 1, wave(LEFT, 2, 1.0)));
+    assert!(!waves.set(MAX_SLOTS, wave(LEFT, 2, 1.0)));
+    assert!(!waves.set(1_000_000_000, wave(LEFT, 2, 1.0)));
+    assert_eq!(waves.len(), MAX_SLOTS);
+
+    let mut sim = Simulation::new(4, 4, 0);
+    sim.place(0, 0, 1_000_000_000, &waves);
+    assert_eq!(sim.slots, 0);
+    sim.place(0, 0, MAX_SLOTS - 1, &waves);
+    assert_eq!(sim.slots, MAX_SLOTS);
+}
+
+#[test]
+fn edges_are_never_activated_from_outside() {
+    let waves = registry(&[wave(UP | DOWN | LEFT | RIGHT, 255, 1.0)]);
+    let mut sim = Simulation::new(4, 4, 1);
+    (0..10).for_each(|_| sim.step(&waves));
+    assert!(sim.tiles.iter().all(|c| *c == 0));
+}
+
+#[test]
+fn heights_match_the_vertex_shader() {
+    let waves = registry(&[wave(0, 4, 2.0), wave(0, 6, -1.0), Wave::default()]);
+    let mut sim = Simulation::new(2, 2, 3);
+    sim.tiles[0] = ACTIVATED + 2;
+    sim.tiles[4] = ACTIVATED + 3;
+    sim.tiles[5] = 3;
+    assert!((sim.height_at(0, 0, &waves) - (2.0 - 1.0)).abs() < 1e-5);
+    assert_eq!(sim.height_at(1, 0, &waves), 0.0);
+}
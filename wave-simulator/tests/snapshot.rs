@@ -0,0 +1,164 @@
+use wave_simulator::wave::bathymetry::Bathymetry;
+use wave_simulator::wave::boundary::Boundary;
+use wave_simulator::wave::constants::MAX_SLOTS;
+use wave_simulator::wave::obstacles::ObstacleMask;
+use wave_simulator::wave::scene::Scene;
+use wave_simulator::wave::sim::{Solver, SolverMode, Wave};
+use wave_simulator::wave::snapshot::{Snapshot, MAGIC, VERSION};
+
+fn scene(mode: &str) -> Scene {
+    let src = format!(
+        "[[waves]]\nslot = 0\namplitude = 1.0\nwavelength = 8\ndirections = [\"up\", \"left\"]\n\
+         decay = 0.01\n\n[[waves]]\nslot = 1\namplitude = 0.5\nwavelength = 6\nkind = \"radial\"\n\n\
+         [[sources]]\nx = 50\ny = 80\nslot = 0\n\n[[sources]]\nx = 30\ny = 30\nslot = 1\n\n\
+         [solver]\nmode = \"{}\"\nboundary = \"periodic\"\n",
+        mode
+    );
+    let mut scene = Scene::from_toml(&src).unwrap();
+    let mut mask = ObstacleMask::open(100, 100);
+    (40..60).for_each(|x| mask.set_blocked(x, 50, true));
+    scene.obstacle_mask = Some(mask);
+    scene.bathymetry_map = Some(Bathymetry::shoal(100, 100));
+    scene
+}
+
+fn heights(sim: &dyn Solver, scene: &Scene) -> Vec<f32> {
+    let waves = scene.get_waves();
+    (0..100)
+        .flat_map(|y| (0..100).map(move |x| (x, y)))
+        .map(|(x, y)| sim.height_at(x, y, &waves))
+        .collect()
+}
+
+// runs 40 updates straight, and 15 then 25 more from a snapshot taken in between
+fn resumes_exactly(mode: &str) {
+    let scene = scene(mode);
+    let waves = scene.get_waves();
+    let mut straight = scene.instantiate();
+    (0..40).for_each(|_| straight.step(&waves));
+
+    let mut first = scene.instantiate();
+    (0..15).for_each(|_| first.step(&waves));
+    let bytes = first.snapshot(&waves, 15).to_bytes();
+    let snapshot = Snapshot::from_bytes(&bytes).unwrap();
+    assert_eq!(snapshot.time, 15);
+    let mut resumed = snapshot.restore();
+    (0..25).for_each(|_| resumed.step(&snapshot.waves));
+
+    assert_eq!(
+        heights(straight.as_ref(), &scene),
+        heights(resumed.as_ref(), &scene)
+    );
+    assert!(heights(resumed.as_ref(), &scene).iter().any(|h| *h != 0.0));
+}
+
+#[test]
+fn automaton_resumes_exactly() {
+    resumes_exactly("automaton");
+}
+
+#[test]
+fn wave_equation_resumes_exactly() {
+    resumes_exactly("wave-equation");
+}
+
+#[test]
+fn snapshots_keep_the_setup() {
+    let scene = scene("wave-equation");
+    let waves = scene.get_waves();
+    let sim = scene.instantiate();
+    let snapshot = Snapshot::from_bytes(&sim.snapshot(&waves, 3).to_bytes()).unwrap();
+    assert_eq!(snapshot.solver.mode, SolverMode::WaveEquation);
+    assert_eq!(snapshot.solver.boundary, Boundary::Periodic);
+    assert_eq!(snapshot.obstacles, scene.obstacle_mask.clone().unwrap());
+    assert_eq!(snapshot.bathymetry, scene.bathymetry_map.clone().unwrap());
+    assert_eq!(snapshot.waves.len(), 2);
+    assert_eq!(snapshot.waves.get(0).unwrap().decay, 0.01);
+    assert!(!snapshot.drivers.is_empty());
+}
+
+#[test]
+fn broken_snapshots_are_rejected() {
+    let scene = scene("automaton");
+    let bytes = scene
+        .instantiate()
+        .snapshot(&scene.get_waves(), 0)
+        .to_bytes();
+    assert!(bytes.starts_with(MAGIC));
+
+    assert!(Snapshot::from_bytes(b"not a snapshot").is_err());
+    assert!(Snapshot::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert!(Snapshot::from_bytes(&trailing).is_err());
+
+    let mut newer = bytes.clone();
+    newer[MAGIC.len()..MAGIC.len() + 4].copy_from_slice(&(VERSION + 1).to_le_bytes());
+    let e = Snapshot::from_bytes(&newer).err().unwrap();
+    assert!(e.contains("version"));
+}
+
+#[test]
+fn snapshots_past_the_slot_limit_are_rejected() {
+    let scene = scene("automaton");
+    let mut snapshot = scene.instantiate().snapshot(&scene.get_waves(), 0);
+    snapshot.ripples[0].slot = MAX_SLOTS as u32;
+    assert!(Snapshot::from_bytes(&snapshot.to_bytes()).is_err());
+
+    let mut snapshot = scene.instantiate().snapshot(&scene.get_waves(), 0);
+    snapshot.slots = 1_000_000_000;
+    assert!(snapshot.validate().is_err());
+}
+
+#[test]
+fn snapshots_that_would_not_run_are_rejected() {
+    let scene = scene("wave-equation");
+    let waves = scene.get_waves();
+    let mut sim = scene.instantiate();
+    sim.step(&waves);
+    let snapshot = sim.snapshot(&waves, 1);
+    assert!(snapshot.validate().is_ok());
+
+    let mut unstable = snapshot.clone();
+    unstable.solver.timestep = 1.0;
+    assert!(unstable.validate().is_err());
+
+    let mut loud = snapshot.clone();
+    let mut wave = *loud.waves.get(0).unwrap();
+    wave.amplitude_factor = f32::NAN;
+    loud.waves.set(0, wave);
+    assert!(loud.validate().unwrap_err().contains("Amplitude"));
+
+    // a driver off the grid would be written past the field
+    let mut outside = snapshot.clone();
+    outside.drivers[0].x = 100;
+    assert!(outside.validate().unwrap_err().contains("outside"));
+
+    // nor can anything be on a slot with no wave, which has no heights
+    let mut empty = snapshot.clone();
+    empty.waves.set(0, Wave::default());
+    assert!(empty.validate().unwrap_err().contains("never defined"));
+
+    let scene = self::scene("automaton");
+    let mut sim = scene.instantiate();
+    sim.step(&waves);
+    let mut snapshot = sim.snapshot(&waves, 1);
+    snapshot.ripples[0].y = 100;
+    assert!(snapshot.validate().is_err());
+}
+
+#[test]
+fn snapshots_are_files() {
+    let scene = scene("automaton");
+    let waves = scene.get_waves();
+    let mut sim = scene.instantiate();
+    (0..5).for_each(|_| sim.step(&waves));
+    let path = std::env::temp_dir().join(format!("wave-simulator-{}.snap", std::process::id()));
+    sim.snapshot(&waves, 5).save(&path).unwrap();
+    let restored = Snapshot::load(&path).unwrap().restore();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(
+        heights(sim.as_ref(), &scene),
+        heights(restored.as_ref(), &scene)
+    );
+}